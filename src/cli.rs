@@ -1,67 +1,374 @@
 use std::env;
 use std::fs::File;
-use std::io::BufWriter;
-use std::rc::Rc;
-use crate::html;
-use crate::generator;
-use crate::generator::preprocessor;
-use crate::generator::context;
-use crate::layout;
-
-fn generate_pdf_from_document(document: Box<html::DocumentRef>, output_file_path: &str) {
-
-    // Pre-process the entire document. Load external fonts, images etc.
-    let preproc = preprocessor::Preprocessor::new(Rc::clone(&document));
-    let resources_manager = preproc.process_document();
-
-    // Lay out all elements.
-    let engine = layout::Engine::new(Rc::clone(&document), Rc::clone(&resources_manager));
-    let root_element = engine.process_document();
-
-    let context = context::ConversionContext {
-        document: Rc::clone(&document),
-        root_element: Some(root_element),
-        resources_manager: Some(resources_manager)
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use mpdf::{ColorMode, ConversionOptions, Converter, Diagnostics, Error, PageMargins, PageOrientation, PageSize, Severity};
+
+/// Path that stands for the standard input or output stream.
+const STDIO_PATH: &str = "-";
+
+/// Exit code used when the conversion itself failed.
+const EXIT_FAILURE: i32 = 1;
+
+/// Exit code used when the program was called with invalid arguments.
+const EXIT_USAGE: i32 = 2;
+
+/// How much is printed to the standard error. Messages are printed at the verbosity they are logged with and above.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum Verbosity {
+
+    /// Only errors are printed.
+    Quiet,
+
+    /// Warnings are printed as well.
+    Normal,
+
+    /// Progress messages are printed as well.
+    Verbose
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+
+    /// The converted PDF document.
+    Pdf,
+
+    /// A textual dump of the layout tree, useful for debugging.
+    Layout
+}
+
+/// What the program was asked to do on the command line.
+enum CliCommand {
+
+    /// Convert a document with given arguments.
+    Convert(Box<CliArguments>),
+
+    /// Print the usage.
+    Help
+}
+
+/// Everything that was specified on the command line.
+struct CliArguments {
+    input_file_path: String,
+    output_file_path: String,
+    base_path: Option<PathBuf>,
+    format: OutputFormat,
+    verbosity: Verbosity,
+    warnings_as_errors: bool,
+    options: ConversionOptions
+}
+
+impl CliArguments {
+
+    /// Parses the arguments (without the program name).
+    fn parse(args: &[String]) -> Result<CliCommand, String> {
+        let mut positional = Vec::<String>::new();
+        let mut base_path = None;
+        let mut format = OutputFormat::Pdf;
+        let mut verbosity = Verbosity::Normal;
+        let mut warnings_as_errors = false;
+        let mut options = ConversionOptions::default();
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            if arg == STDIO_PATH || !arg.starts_with('-') {
+                positional.push(arg.clone());
+                continue;
+            }
+
+            // Both `--name value` and `--name=value` are accepted.
+            let (name, inline_value) = match arg.find('=') {
+                Some(pos) if arg.starts_with("--") => (&arg[..pos], Some(arg[pos + 1..].to_string())),
+                _ => (arg.as_str(), None)
+            };
+
+            let mut value = || -> Result<String, String> {
+                if let Some(value) = inline_value.clone() {
+                    return Ok(value);
+                }
+
+                return iter.next().cloned().ok_or(format!("missing value for {}", name));
+            };
+
+            match name {
+                "-h" | "--help" => return Ok(CliCommand::Help),
+                "-q" | "--quiet" => verbosity = Verbosity::Quiet,
+                "-v" | "--verbose" => verbosity = Verbosity::Verbose,
+                "-W" | "--warnings-as-errors" => warnings_as_errors = true,
+                "-s" | "--page-size" => options.page_size = parse_page_size(&value()?)?,
+                "-O" | "--orientation" => options.orientation = parse_orientation(&value()?)?,
+                "-m" | "--margin" => options.margins = parse_margins(&value()?)?,
+                "--margin-top" => options.margins.top = parse_length_mm(&value()?)?,
+                "--margin-right" => options.margins.right = parse_length_mm(&value()?)?,
+                "--margin-bottom" => options.margins.bottom = parse_length_mm(&value()?)?,
+                "--margin-left" => options.margins.left = parse_length_mm(&value()?)?,
+                "--font" => options.default_font = value()?,
+                "--base-url" => base_path = Some(PathBuf::from(value()?)),
+                "--title" => options.title = value()?,
+                "--author" => options.author = Some(value()?),
+                "--cmyk" => options.color_mode = ColorMode::Cmyk,
+                "-f" | "--format" => format = parse_format(&value()?)?,
+                _ => return Err(format!("unknown option {}", name))
+            }
+        }
+
+        if positional.len() != 2 {
+            return Err(String::from("expected exactly one input and one output file path"));
+        }
+
+        let output_file_path = positional.pop().unwrap();
+        let input_file_path = positional.pop().unwrap();
+
+        return Ok(CliCommand::Convert(Box::new(CliArguments {
+            input_file_path: input_file_path,
+            output_file_path: output_file_path,
+            base_path: base_path,
+            format: format,
+            verbosity: verbosity,
+            warnings_as_errors: warnings_as_errors,
+            options: options
+        })));
+    }
+}
+
+/// Parses a page size, either one of the predefined names (e.g. `A4`, `Letter`)
+/// or custom dimensions in the `<width>x<height>` form (e.g. `100mmx150mm`).
+fn parse_page_size(value: &str) -> Result<PageSize, String> {
+    if let Some(size) = PageSize::from_name(value) {
+        return Ok(size);
+    }
+
+    let dimensions: Vec<&str> = value.split('x').collect();
+    if dimensions.len() != 2 {
+        return Err(format!("invalid page size {}", value));
+    }
+
+    return Ok(PageSize {
+        width: parse_length_mm(dimensions[0])?,
+        height: parse_length_mm(dimensions[1])?
+    });
+}
+
+fn parse_orientation(value: &str) -> Result<PageOrientation, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "portrait" => Ok(PageOrientation::Portrait),
+        "landscape" => Ok(PageOrientation::Landscape),
+        _ => Err(format!("invalid orientation {}", value))
+    }
+}
+
+fn parse_format(value: &str) -> Result<OutputFormat, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "pdf" => Ok(OutputFormat::Pdf),
+        "layout" => Ok(OutputFormat::Layout),
+        _ => Err(format!("invalid output format {}", value))
+    }
+}
+
+/// Parses page margins given as 1 to 4 comma-separated lengths,
+/// following the order of CSS's `margin` property.
+fn parse_margins(value: &str) -> Result<PageMargins, String> {
+    let values = value.split(',')
+        .map(|v| parse_length_mm(v.trim()))
+        .collect::<Result<Vec<f64>, String>>()?;
+
+    return match values.as_slice() {
+        [all] => Ok(PageMargins::uniform(*all)),
+        [vertical, horizontal] => Ok(PageMargins { top: *vertical, right: *horizontal, bottom: *vertical, left: *horizontal }),
+        [top, horizontal, bottom] => Ok(PageMargins { top: *top, right: *horizontal, bottom: *bottom, left: *horizontal }),
+        [top, right, bottom, left] => Ok(PageMargins { top: *top, right: *right, bottom: *bottom, left: *left }),
+        _ => Err(format!("invalid margins {}", value))
+    };
+}
+
+/// Parses a length with an optional `mm`, `cm`, `in` or `pt` unit and returns it in millimeters.
+/// Lengths without a unit are treated as millimeters.
+fn parse_length_mm(value: &str) -> Result<f64, String> {
+    let units: [(&str, f64); 4] = [("mm", 1.0), ("cm", 10.0), ("in", 25.4), ("pt", 25.4 / 72.0)];
+    let (number, scale) = units.iter()
+        .find(|(unit, _)| value.ends_with(unit))
+        .map(|(unit, scale)| (&value[..value.len() - unit.len()], *scale))
+        .unwrap_or((value, 1.0));
+
+    match number.trim().parse::<f64>() {
+        Ok(number) if number >= 0.0 => Ok(number * scale),
+        _ => Err(format!("invalid length {}", value))
+    }
+}
+
+fn convert(converter: Converter, arguments: &CliArguments) -> Result<Diagnostics, Error> {
+    if arguments.format == OutputFormat::Layout {
+        log(arguments, Verbosity::Verbose, "Laying out document...");
+        let layout = converter.layout()?;
+        let diagnostics = layout.take_diagnostics();
+        if diagnostics.is_failure(arguments.warnings_as_errors) {
+            return Err(Error::Diagnostics(diagnostics));
+        }
+
+        let dump = layout.to_text();
+        write_output(&arguments.output_file_path, dump.as_bytes())?;
+        return Ok(diagnostics);
+    }
+
+    log(arguments, Verbosity::Verbose, "Converting document...");
+    let output = converter.convert()?;
+    write_output(&arguments.output_file_path, &output.pdf)?;
+    return Ok(output.diagnostics);
+}
+
+/// Writes given bytes into the output file, or the standard output if `-` was given.
+fn write_output(output_file_path: &str, bytes: &[u8]) -> io::Result<()> {
+    if output_file_path == STDIO_PATH {
+        let stdout = io::stdout();
+        let mut writer = stdout.lock();
+        writer.write_all(bytes)?;
+        return writer.flush();
+    }
+
+    return File::create(output_file_path)?.write_all(bytes);
+}
+
+/// Reads the entire input file, or the standard input if `-` was given.
+fn read_input(input_file_path: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+
+    if input_file_path == STDIO_PATH {
+        io::stdin().read_to_end(&mut bytes)
+            .map_err(|e| format!("unable to read standard input: {}", e))?;
+    } else {
+        File::open(input_file_path)
+            .and_then(|mut file| file.read_to_end(&mut bytes))
+            .map_err(|e| format!("unable to read {}: {}", input_file_path, e))?;
+    }
+
+    return Ok(bytes);
+}
+
+/// Returns the directory that relative resources are resolved against.
+/// Unless overridden with `--base-url`, it is the directory of the input file,
+/// or the current working directory for the standard input.
+fn get_base_path(arguments: &CliArguments) -> PathBuf {
+    if let Some(base_path) = &arguments.base_path {
+        return base_path.clone();
+    }
+
+    if arguments.input_file_path != STDIO_PATH {
+        if let Some(parent) = Path::new(&arguments.input_file_path).parent() {
+            return parent.to_path_buf();
+        }
+    }
+
+    return PathBuf::from(".");
+}
+
+/// Prints a message to the standard error, if the verbosity level allows it.
+fn log(arguments: &CliArguments, level: Verbosity, message: &str) {
+    if arguments.verbosity >= level {
+        eprintln!("{}", message);
+    }
+}
+
+/// Runs the command-line interface and returns the process exit code.
+pub fn run_cli() -> i32 {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let arguments = match CliArguments::parse(&args) {
+        Ok(CliCommand::Convert(arguments)) => arguments,
+        Ok(CliCommand::Help) => {
+            let _ = print_usage(&mut io::stdout());
+            return 0;
+        }
+
+        Err(e) => {
+            eprintln!("error: {}", e);
+            let _ = print_usage(&mut io::stderr());
+            return EXIT_USAGE;
+        }
+    };
+
+    let input = match read_input(&arguments.input_file_path) {
+        Ok(input) => input,
+        Err(e) => {
+            log(&arguments, Verbosity::Quiet, &format!("error: {}", e));
+            return EXIT_FAILURE;
+        }
     };
 
-    // Render to PDF or PNG.
-    let pdf = generator::generate_pdf(context);
-    let mut writer = BufWriter::new(File::create(output_file_path).unwrap());
-    match pdf.save(&mut writer) {
-        Ok(_) => println!("Done."),
-        Err(e) => println!("Error. {}", e)
-    }
-}
-
-pub fn run_cli() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 3 {
-        print_usage();
-        return;
-    }
-
-    let _input_file_path = &args[1];
-    let output_file_path = &args[2];
-
-    let doc = html::parse_text("
-        <div>
-            <div style=\"display: block; color: blue;\">
-                1
-            </div>
-            
-            <div style=\"display: block; color: red;\">
-                2
-            </div>
-        </div>
-            
-        <div style=\"display: block; color: green;\">
-            3
-        </div>
-    ");
-    
-    generate_pdf_from_document(doc, output_file_path);
-}
-
-fn print_usage() {
-    println!("Usage: ./mpdf <input file path> <output file path>");
-}
\ No newline at end of file
+    let converter = Converter::new()
+        .html_bytes(input)
+        .base_path(get_base_path(&arguments))
+        .options(arguments.options.clone())
+        .warnings_as_errors(arguments.warnings_as_errors);
+
+    match convert(converter, &arguments) {
+        Ok(diagnostics) => {
+            print_diagnostics(&arguments, &diagnostics);
+            return 0;
+        }
+
+        Err(e) => {
+            if let Error::Diagnostics(diagnostics) = &e {
+                print_diagnostics(&arguments, diagnostics);
+            }
+
+            log(&arguments, Verbosity::Quiet, &format!("error: {}", e));
+            return EXIT_FAILURE;
+        }
+    }
+}
+
+fn print_diagnostics(arguments: &CliArguments, diagnostics: &Diagnostics) {
+    for diagnostic in diagnostics.iter() {
+        let level = match diagnostic.severity {
+            Severity::Error => Verbosity::Quiet,
+            Severity::Warning => Verbosity::Normal
+        };
+
+        log(arguments, level, &diagnostic.to_string());
+    }
+}
+
+/// Prints how to call the program, for `--help` and after invalid arguments.
+fn print_usage(target: &mut dyn Write) -> io::Result<()> {
+    writeln!(target, "Usage: ./mpdf [options] <input file path> <output file path>")?;
+    writeln!(target, "Use - as the input or output file path to read from standard input or write to standard output.")?;
+    writeln!(target)?;
+    writeln!(target, "Options:")?;
+    writeln!(target, "  -s, --page-size <size>      A3, A4, A5, B4, B5, JIS-B4, JIS-B5, Letter, Legal, Ledger")?;
+    writeln!(target, "                              or <width>x<height> (default: A4)")?;
+    writeln!(target, "  -O, --orientation <value>   portrait or landscape (default: portrait)")?;
+    writeln!(target, "  -m, --margin <margins>      1 to 4 comma-separated lengths, in CSS order (default: 0)")?;
+    writeln!(target, "      --margin-top <length>   also --margin-right, --margin-bottom and --margin-left")?;
+    writeln!(target, "      --font <name>           font used when an element does not specify one (default: sans-serif)")?;
+    writeln!(target, "      --base-url <path>       directory relative resources are resolved against")?;
+    writeln!(target, "      --title <title>         title stored in the PDF's metadata")?;
+    writeln!(target, "      --author <author>       author stored in the PDF's metadata")?;
+    writeln!(target, "      --cmyk                  convert all colors to CMYK for print production")?;
+    writeln!(target, "  -f, --format <format>       pdf or layout (default: pdf)")?;
+    writeln!(target, "  -q, --quiet                 print only errors, no warnings")?;
+    writeln!(target, "  -v, --verbose               print progress messages")?;
+    writeln!(target, "  -W, --warnings-as-errors    fail the conversion if any warnings are reported")?;
+    writeln!(target, "  -h, --help                  print this message")?;
+    writeln!(target)?;
+    writeln!(target, "Lengths accept mm, cm, in and pt units and default to millimeters.")?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color_mode(args: &[&str]) -> ColorMode {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        return match CliArguments::parse(&args) {
+            Ok(CliCommand::Convert(arguments)) => arguments.options.color_mode,
+            _ => panic!("arguments should be valid")
+        };
+    }
+
+    #[test]
+    fn cmyk_option_converts_all_colors() {
+        assert_eq!(color_mode(&["in.html", "out.pdf"]), ColorMode::AsSpecified);
+        assert_eq!(color_mode(&["--cmyk", "in.html", "out.pdf"]), ColorMode::Cmyk);
+        assert_eq!(color_mode(&["in.html", "--cmyk", "-"]), ColorMode::Cmyk);
+    }
+}
@@ -0,0 +1,342 @@
+use std::cell::RefCell;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use crate::html;
+use crate::css::MediaEnvironment;
+use crate::diagnostics::{Diagnostics, DiagnosticsRef};
+use crate::error::{Error, Result};
+use crate::layout;
+use crate::utils::units;
+use crate::generator;
+use crate::generator::context::ConversionContext;
+use crate::generator::preprocessor::Preprocessor;
+use crate::generator::{
+    ColorMode,
+    ConversionOptions,
+    FileResourceResolver,
+    PageMargins,
+    PageOrientation,
+    PageSize,
+    ResourceResolver,
+    ResourcesManager,
+    ResourcesManagerRef
+};
+
+/// Source of the HTML document to convert.
+enum Input {
+    Text(String),
+    Bytes(Vec<u8>),
+    File(PathBuf)
+}
+
+/// Converts HTML documents to PDF.
+///
+/// ```no_run
+/// use mpdf::{Converter, PageSize};
+///
+/// let pdf = Converter::new()
+///     .html("<div>Hello</div>")
+///     .page_size(PageSize::LETTER)
+///     .convert()
+///     .unwrap()
+///     .pdf;
+/// ```
+#[derive(Default)]
+pub struct Converter {
+    input: Option<Input>,
+    options: ConversionOptions,
+    resolver: Option<Box<dyn ResourceResolver>>,
+    warnings_as_errors: bool,
+
+    /// Fonts registered by the user, as (name, font file contents) pairs.
+    fonts: Vec<(String, Vec<u8>)>
+}
+
+/// Result of a successful conversion.
+pub struct Output {
+
+    /// Contents of the PDF file.
+    pub pdf: Vec<u8>,
+
+    /// Problems that did not prevent the conversion.
+    pub diagnostics: Diagnostics
+}
+
+/// Result of laying out a document, before it is rendered.
+pub struct LayoutResult {
+    pub(crate) document: html::DocumentRef,
+    pub(crate) pages: Vec<layout::Page>,
+    pub(crate) resources_manager: ResourcesManagerRef,
+    pub(crate) diagnostics: DiagnosticsRef
+}
+
+impl LayoutResult {
+
+    /// Moves problems found while laying out the document out of the result.
+    pub fn take_diagnostics(&self) -> Diagnostics {
+        return self.diagnostics.replace(Diagnostics::new());
+    }
+
+    /// Returns a human-readable description of all pages and their layout trees, useful for debugging.
+    pub fn to_text(&self) -> String {
+        let document = self.document.borrow();
+        let mut dump = String::new();
+
+        for page in &self.pages {
+            dump.push_str(&format!(
+                "page {}{} width={:.2} height={:.2} margins={:.2} {:.2} {:.2} {:.2}\n",
+                page.context.index + 1,
+                page.context.name.as_ref().map_or(String::new(), |name| format!(" ({})", name)),
+                page.geometry.width, page.geometry.height,
+                page.geometry.margin_top, page.geometry.margin_right, page.geometry.margin_bottom, page.geometry.margin_left
+            ));
+
+            write_layout_tree(&document, &page.root_element, 1, &mut dump);
+
+            for margin_box in &page.margin_boxes {
+                dump.push_str(&format!(
+                    "  @{} x={:.2} y={:.2} width={:.2} height={:.2} {:?}\n",
+                    margin_box.position.name(), margin_box.x, margin_box.y, margin_box.width, margin_box.height, margin_box.text
+                ));
+
+                if let Some(element) = &margin_box.element {
+                    write_layout_tree(&document, element, 2, &mut dump);
+                }
+            }
+        }
+
+        return dump;
+    }
+}
+
+impl Converter {
+
+    /// Sets the HTML document to convert.
+    pub fn html(mut self, html: &str) -> Self {
+        self.input = Some(Input::Text(html.to_string()));
+        self
+    }
+
+    /// Sets the raw bytes of the HTML document to convert.
+    /// The character encoding is detected from the byte order mark or `<meta charset>`.
+    pub fn html_bytes(mut self, bytes: Vec<u8>) -> Self {
+        self.input = Some(Input::Bytes(bytes));
+        self
+    }
+
+    /// Sets the path of the HTML file to convert.
+    /// Unless a resource resolver is set, relative resources are resolved against the file's directory.
+    pub fn html_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.input = Some(Input::File(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Replaces all conversion options at once.
+    pub fn options(mut self, options: ConversionOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn page_size(mut self, page_size: PageSize) -> Self {
+        self.options.page_size = page_size;
+        self
+    }
+
+    pub fn orientation(mut self, orientation: PageOrientation) -> Self {
+        self.options.orientation = orientation;
+        self
+    }
+
+    pub fn margins(mut self, margins: PageMargins) -> Self {
+        self.options.margins = margins;
+        self
+    }
+
+    /// Sets the name of the font used by elements that do not specify one.
+    pub fn default_font(mut self, name: &str) -> Self {
+        self.options.default_font = name.to_string();
+        self
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.options.title = title.to_string();
+        self
+    }
+
+    pub fn author(mut self, author: &str) -> Self {
+        self.options.author = Some(author.to_string());
+        self
+    }
+
+    pub fn color_mode(mut self, color_mode: ColorMode) -> Self {
+        self.options.color_mode = color_mode;
+        self
+    }
+
+    /// Sets the resolver used to load external resources referenced by the document.
+    pub fn resource_resolver<R: ResourceResolver + 'static>(mut self, resolver: R) -> Self {
+        self.resolver = Some(Box::new(resolver));
+        self
+    }
+
+    /// Resolves external resources from the file system, relative to given directory.
+    pub fn base_path<P: AsRef<Path>>(self, path: P) -> Self {
+        self.resource_resolver(FileResourceResolver::new(path.as_ref().to_path_buf()))
+    }
+
+    /// Registers a font under given name, so elements can use it without it being resolved.
+    pub fn font(mut self, name: &str, data: Vec<u8>) -> Self {
+        self.fonts.push((name.to_string(), data));
+        self
+    }
+
+    /// Makes the conversion fail if any warnings are reported.
+    pub fn warnings_as_errors(mut self, warnings_as_errors: bool) -> Self {
+        self.warnings_as_errors = warnings_as_errors;
+        self
+    }
+
+    /// Converts the document and returns the contents of the PDF file.
+    pub fn convert(self) -> Result<Output> {
+        let mut bytes = Vec::new();
+        let diagnostics = self.write_to(&mut bytes)?;
+
+        return Ok(Output {
+            pdf: bytes,
+            diagnostics: diagnostics
+        });
+    }
+
+    /// Converts the document and writes the PDF file into `writer`.
+    /// Returns problems that did not prevent the conversion.
+    pub fn write_to<W: Write>(self, writer: &mut W) -> Result<Diagnostics> {
+        let options = self.options.clone();
+        let warnings_as_errors = self.warnings_as_errors;
+        let layout = self.layout()?;
+
+        let context = ConversionContext {
+            document: layout.document,
+            pages: Some(layout.pages),
+            resources_manager: Some(layout.resources_manager),
+            options: options.clone(),
+            diagnostics: Rc::clone(&layout.diagnostics)
+        };
+
+        let pdf = generator::generate_pdf(context)?;
+        let diagnostics = layout.diagnostics.replace(Diagnostics::new());
+        if diagnostics.is_failure(warnings_as_errors) {
+            return Err(Error::Diagnostics(diagnostics));
+        }
+
+        generator::save_pdf(pdf, &options, writer)?;
+        return Ok(diagnostics);
+    }
+
+    /// Parses the document, loads its resources and lays it out without rendering it.
+    pub fn layout(self) -> Result<LayoutResult> {
+        let mut diagnostics = Diagnostics::new();
+        let base_path = match &self.input {
+            Some(Input::File(path)) => path.parent().map(|parent| parent.to_path_buf()),
+            _ => None
+        };
+
+        let resolver = match self.resolver {
+            Some(resolver) => resolver,
+            None => Box::new(FileResourceResolver::new(base_path.unwrap_or_else(|| PathBuf::from("."))))
+        };
+
+        let mut manager = ResourcesManager::new(resolver);
+        for (name, data) in self.fonts {
+            if !manager.add_font_data(name.clone(), data) {
+                return Err(Error::Font(name));
+            }
+        }
+
+        let input = self.input.ok_or(Error::MissingInput)?;
+
+        // Media queries are evaluated against the whole page, whose size may be set by `@page` rules.
+        // Stylesheets are applied for the page size of the options first and, if the first page gets
+        // a different size, applied again for that one. Rules that only apply to the second media
+        // environment do not change it further, and all pages share the first page's environment.
+        let options_environment = MediaEnvironment {
+            width: units::mm_to_pt(self.options.page_width()),
+            height: units::mm_to_pt(self.options.page_height())
+        };
+
+        // Viewport-percentage lengths in `@page` rules can only be relative to the page area of the options,
+        // as the rules themselves set the size of the page.
+        let options_viewport = html::Viewport {
+            width: units::mm_to_pt(self.options.content_width()),
+            height: units::mm_to_pt(self.options.content_height())
+        };
+
+        let document = parse_input(&input, &manager, options_environment, &mut diagnostics)?;
+        let first_page_style = document.borrow().compute_page_style(&html::PageContext { name: None, index: 0, is_blank: false }, options_viewport);
+        let first_page = layout::PageGeometry::from_properties(&first_page_style, &self.options);
+        let page_environment = MediaEnvironment {
+            width: first_page.width,
+            height: first_page.height
+        };
+
+        if page_environment != options_environment {
+            document.borrow_mut().apply_stylesheets(&page_environment);
+        }
+
+        // Everything else is relative to the page area of the first page, like media queries are to its size.
+        let viewport = html::Viewport {
+            width: first_page.content_width(),
+            height: first_page.content_height()
+        };
+
+        document.borrow_mut().compute_styles(viewport, &mut diagnostics);
+
+        // Pre-process the entire document. Load external fonts, images etc.
+        let diagnostics = Rc::new(RefCell::new(diagnostics));
+        let manager = Rc::new(RefCell::new(manager));
+        let preproc = Preprocessor::new(Rc::clone(&document), manager, self.options.clone(), Rc::clone(&diagnostics));
+        let resources_manager = preproc.process_document();
+
+        // Lay out all elements.
+        let engine = layout::Engine::new(Rc::clone(&document), Rc::clone(&resources_manager), self.options, viewport);
+        let pages = engine.process_document();
+
+        return Ok(LayoutResult {
+            document: *document,
+            pages: pages,
+            resources_manager: resources_manager,
+            diagnostics: diagnostics
+        });
+    }
+
+    pub fn new() -> Self {
+        return Self::default();
+    }
+}
+
+/// Parses the document to convert, with media queries evaluated against `media_environment`.
+fn parse_input(input: &Input, manager: &ResourcesManager, media_environment: MediaEnvironment, diagnostics: &mut Diagnostics) -> Result<Box<html::DocumentRef>> {
+    match input {
+        Input::Text(text) => html::parse_text(text, manager, media_environment, diagnostics),
+        Input::Bytes(bytes) => html::parse_bytes(bytes, manager, media_environment, diagnostics),
+        Input::File(path) => html::parse_bytes(&std::fs::read(path)?, manager, media_environment, diagnostics)
+    }
+}
+
+/// Appends a human-readable description of the layout tree to `output`.
+fn write_layout_tree(document: &html::Document, element: &layout::Element, depth: usize, output: &mut String) {
+    let name = match &element.kind {
+        layout::ElementKind::Box => document.get_element_immutable(element.element).tag.clone(),
+        layout::ElementKind::Line => String::from("line"),
+        layout::ElementKind::Text { text, .. } => format!("\"{}\"", text)
+    };
+
+    output.push_str(&format!(
+        "{}{} x={:.2} y={:.2} width={:.2} height={:.2}\n",
+        "  ".repeat(depth), name, element.x, element.y, element.width, element.height
+    ));
+
+    for child in &element.children {
+        write_layout_tree(document, child, depth + 1, output);
+    }
+}
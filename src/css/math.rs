@@ -0,0 +1,438 @@
+use crate::css::tokenizer::{Token, TokenKind};
+use crate::css::value_parser::LENGTH_UNITS;
+
+// Math functions: calc(), min(), max() and clamp().
+// https://www.w3.org/TR/css-values-4/#math
+
+/// Names of the functions that are parsed as math expressions.
+pub const MATH_FUNCTIONS: &[&str] = &["calc", "min", "max", "clamp"];
+
+/// What a math expression resolves to.
+/// https://www.w3.org/TR/css-values-4/#calc-type-checking
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MathType {
+    Number,
+    Length,
+    Percentage,
+
+    /// A sum of lengths and percentages, e.g. `100% - 40px`.
+    LengthPercentage
+}
+
+#[derive(Debug, Clone)]
+pub enum MathExpression {
+    Number(f64),
+
+    /// A length with its unit.
+    Length(f64, String),
+    Percentage(f64),
+    Sum(Box<MathExpression>, Box<MathExpression>),
+    Difference(Box<MathExpression>, Box<MathExpression>),
+    Product(Box<MathExpression>, Box<MathExpression>),
+    Quotient(Box<MathExpression>, Box<MathExpression>),
+    Min(Vec<MathExpression>),
+    Max(Vec<MathExpression>),
+
+    /// `clamp(min, value, max)`
+    Clamp(Box<MathExpression>, Box<MathExpression>, Box<MathExpression>)
+}
+
+impl MathType {
+
+    /// Returns the type of a sum of values of given types, or `None` if they cannot be added.
+    fn add(self, other: MathType) -> Option<MathType> {
+        if self == other {
+            return Some(self);
+        }
+
+        if self == MathType::Number || other == MathType::Number {
+            return None;
+        }
+
+        return Some(MathType::LengthPercentage);
+    }
+}
+
+impl MathExpression {
+
+    /// Returns what the expression resolves to, or `None` if it mixes incompatible values, e.g. `1px + 2`.
+    pub fn get_type(&self) -> Option<MathType> {
+        match self {
+            MathExpression::Number(_) => Some(MathType::Number),
+            MathExpression::Length(_, _) => Some(MathType::Length),
+            MathExpression::Percentage(_) => Some(MathType::Percentage),
+            MathExpression::Sum(left, right) | MathExpression::Difference(left, right) => left.get_type()?.add(right.get_type()?),
+            MathExpression::Product(left, right) => {
+                match (left.get_type()?, right.get_type()?) {
+                    (MathType::Number, other) | (other, MathType::Number) => Some(other),
+                    _ => None
+                }
+            }
+
+            MathExpression::Quotient(left, right) => {
+                match right.get_type()? {
+                    MathType::Number => left.get_type(),
+                    _ => None
+                }
+            }
+
+            MathExpression::Min(arguments) | MathExpression::Max(arguments) => {
+                let mut result = arguments.first()?.get_type()?;
+                for argument in arguments.iter().skip(1) {
+                    result = result.add(argument.get_type()?)?;
+                }
+
+                return Some(result);
+            }
+
+            MathExpression::Clamp(min, value, max) => min.get_type()?.add(value.get_type()?)?.add(max.get_type()?)
+        }
+    }
+
+    /// Returns whether the expression contains a percentage, so it can only be evaluated
+    /// once the size percentages refer to is known.
+    pub fn has_percentage(&self) -> bool {
+        match self {
+            MathExpression::Number(_) | MathExpression::Length(_, _) => false,
+            MathExpression::Percentage(_) => true,
+            MathExpression::Sum(left, right) | MathExpression::Difference(left, right) |
+            MathExpression::Product(left, right) | MathExpression::Quotient(left, right) => left.has_percentage() || right.has_percentage(),
+            MathExpression::Min(arguments) | MathExpression::Max(arguments) => arguments.iter().any(|argument| argument.has_percentage()),
+            MathExpression::Clamp(min, value, max) => min.has_percentage() || value.has_percentage() || max.has_percentage()
+        }
+    }
+
+    /// Returns a copy of the expression with all lengths converted by `convert`, which
+    /// is given a length's value and lowercase unit. Returns `None` if any conversion fails.
+    pub fn map_lengths(&self, convert: &dyn Fn(f64, &str) -> Option<f64>) -> Option<MathExpression> {
+        let map = |expression: &MathExpression| expression.map_lengths(convert).map(Box::new);
+
+        let result = match self {
+            MathExpression::Number(_) | MathExpression::Percentage(_) => self.clone(),
+            MathExpression::Length(value, unit) => MathExpression::Length(convert(*value, &unit.to_ascii_lowercase())?, String::from("pt")),
+            MathExpression::Sum(left, right) => MathExpression::Sum(map(left)?, map(right)?),
+            MathExpression::Difference(left, right) => MathExpression::Difference(map(left)?, map(right)?),
+            MathExpression::Product(left, right) => MathExpression::Product(map(left)?, map(right)?),
+            MathExpression::Quotient(left, right) => MathExpression::Quotient(map(left)?, map(right)?),
+            MathExpression::Min(arguments) => MathExpression::Min(arguments.iter().map(|argument| argument.map_lengths(convert)).collect::<Option<Vec<_>>>()?),
+            MathExpression::Max(arguments) => MathExpression::Max(arguments.iter().map(|argument| argument.map_lengths(convert)).collect::<Option<Vec<_>>>()?),
+            MathExpression::Clamp(min, value, max) => MathExpression::Clamp(map(min)?, map(value)?, map(max)?)
+        };
+
+        return Some(result);
+    }
+
+    /// Calculates the value of the expression. Lengths are taken as they are, so they should be
+    /// converted to the same unit first, see `map_lengths`. Percentages refer to `percentage_reference`.
+    ///
+    /// Returns `None` if there is a percentage but no reference, or when dividing by zero.
+    pub fn evaluate(&self, percentage_reference: Option<f64>) -> Option<f64> {
+        let evaluate_all = |arguments: &Vec<MathExpression>| -> Option<Vec<f64>> {
+            return arguments.iter().map(|argument| argument.evaluate(percentage_reference)).collect();
+        };
+
+        match self {
+            MathExpression::Number(value) | MathExpression::Length(value, _) => Some(*value),
+            MathExpression::Percentage(value) => percentage_reference.map(|reference| reference * value / 100.0),
+            MathExpression::Sum(left, right) => Some(left.evaluate(percentage_reference)? + right.evaluate(percentage_reference)?),
+            MathExpression::Difference(left, right) => Some(left.evaluate(percentage_reference)? - right.evaluate(percentage_reference)?),
+            MathExpression::Product(left, right) => Some(left.evaluate(percentage_reference)? * right.evaluate(percentage_reference)?),
+            MathExpression::Quotient(left, right) => {
+                let divisor = right.evaluate(percentage_reference)?;
+                if divisor == 0.0 {
+                    return None;
+                }
+
+                return Some(left.evaluate(percentage_reference)? / divisor);
+            }
+
+            MathExpression::Min(arguments) => evaluate_all(arguments)?.into_iter().reduce(f64::min),
+            MathExpression::Max(arguments) => evaluate_all(arguments)?.into_iter().reduce(f64::max),
+
+            // The minimum wins over the maximum when they overlap.
+            MathExpression::Clamp(min, value, max) => {
+                let value = value.evaluate(percentage_reference)?.min(max.evaluate(percentage_reference)?);
+                return Some(value.max(min.evaluate(percentage_reference)?));
+            }
+        }
+    }
+
+    /// Returns the expression written in CSS syntax, as the argument of a `calc()` if it is not a function itself.
+    pub fn to_css_string(&self) -> String {
+        match self {
+            MathExpression::Min(_) | MathExpression::Max(_) | MathExpression::Clamp(_, _, _) => self.to_css_fragment(),
+
+            // A sum does not need its own parentheses inside calc().
+            MathExpression::Sum(left, right) => format!("calc({} + {})", left.to_css_fragment(), right.to_css_fragment()),
+            MathExpression::Difference(left, right) => format!("calc({} - {})", left.to_css_fragment(), right.to_css_fragment()),
+            _ => format!("calc({})", self.to_css_fragment())
+        }
+    }
+
+    fn to_css_fragment(&self) -> String {
+        let join = |arguments: &Vec<MathExpression>| -> String {
+            return arguments.iter().map(|argument| argument.to_css_fragment()).collect::<Vec<String>>().join(", ");
+        };
+
+        match self {
+            MathExpression::Number(value) => value.to_string(),
+            MathExpression::Length(value, unit) => format!("{}{}", value, unit),
+            MathExpression::Percentage(value) => format!("{}%", value),
+            MathExpression::Sum(left, right) => format!("({} + {})", left.to_css_fragment(), right.to_css_fragment()),
+            MathExpression::Difference(left, right) => format!("({} - {})", left.to_css_fragment(), right.to_css_fragment()),
+            MathExpression::Product(left, right) => format!("{} * {}", left.to_css_fragment(), right.to_css_fragment()),
+            MathExpression::Quotient(left, right) => format!("{} / {}", left.to_css_fragment(), right.to_css_fragment()),
+            MathExpression::Min(arguments) => format!("min({})", join(arguments)),
+            MathExpression::Max(arguments) => format!("max({})", join(arguments)),
+            MathExpression::Clamp(min, value, max) => format!("clamp({}, {}, {})", min.to_css_fragment(), value.to_css_fragment(), max.to_css_fragment())
+        }
+    }
+}
+
+/// Returns whether given token starts a math function.
+pub fn is_math_function(token: &Token) -> bool {
+    return token.kind == TokenKind::Function && MATH_FUNCTIONS.iter().any(|name| token.value.eq_ignore_ascii_case(name));
+}
+
+/// Parses a math function whose function token is at `pos`. `tokens` must not contain whitespace.
+/// Returns the position just past the function's closing parenthesis and the parsed expression,
+/// or `None` if the function is not valid, including when it mixes incompatible types.
+pub fn parse_math_function(tokens: &[Token], pos: usize) -> Option<(usize, MathExpression)> {
+    let mut parser = MathParser {
+        tokens: tokens,
+        pos: pos
+    };
+
+    let expression = parser.parse_function()?;
+    expression.get_type()?;
+    return Some((parser.pos, expression));
+}
+
+/// Recursive descent parser of math expressions.
+/// https://www.w3.org/TR/css-values-4/#calc-syntax
+struct MathParser<'a> {
+    tokens: &'a [Token],
+    pos: usize
+}
+
+impl MathParser<'_> {
+
+    /// Parses `calc()`, `min()`, `max()` or `clamp()` along with its arguments.
+    fn parse_function(&mut self) -> Option<MathExpression> {
+        let token = self.tokens.get(self.pos)?;
+        if !is_math_function(token) {
+            return None;
+        }
+
+        let name = token.value.to_ascii_lowercase();
+        self.pos += 1;
+
+        let mut arguments = vec![self.parse_sum()?];
+        while self.accept(TokenKind::Comma, None) {
+            arguments.push(self.parse_sum()?);
+        }
+
+        if !self.accept(TokenKind::ParenthesisClose, None) {
+            return None;
+        }
+
+        match (name.as_str(), arguments.len()) {
+            ("calc", 1) => arguments.pop(),
+            ("min", _) => Some(MathExpression::Min(arguments)),
+            ("max", _) => Some(MathExpression::Max(arguments)),
+            ("clamp", 3) => {
+                let max = arguments.pop()?;
+                let value = arguments.pop()?;
+                let min = arguments.pop()?;
+                return Some(MathExpression::Clamp(Box::new(min), Box::new(value), Box::new(max)));
+            }
+
+            _ => None
+        }
+    }
+
+    /// `<calc-sum> = <calc-product> [ [ '+' | '-' ] <calc-product> ]*`
+    fn parse_sum(&mut self) -> Option<MathExpression> {
+        let mut expression = self.parse_product()?;
+
+        loop {
+            if self.accept(TokenKind::Delimeter, Some("+")) {
+                expression = MathExpression::Sum(Box::new(expression), Box::new(self.parse_product()?));
+            } else if self.accept(TokenKind::Delimeter, Some("-")) {
+                expression = MathExpression::Difference(Box::new(expression), Box::new(self.parse_product()?));
+            } else {
+                return Some(expression);
+            }
+        }
+    }
+
+    /// `<calc-product> = <calc-value> [ [ '*' | '/' ] <calc-value> ]*`
+    fn parse_product(&mut self) -> Option<MathExpression> {
+        let mut expression = self.parse_value()?;
+
+        loop {
+            if self.accept(TokenKind::Delimeter, Some("*")) {
+                expression = MathExpression::Product(Box::new(expression), Box::new(self.parse_value()?));
+            } else if self.accept(TokenKind::Delimeter, Some("/")) {
+                expression = MathExpression::Quotient(Box::new(expression), Box::new(self.parse_value()?));
+            } else {
+                return Some(expression);
+            }
+        }
+    }
+
+    /// `<calc-value> = <number> | <dimension> | <percentage> | ( <calc-sum> )`, or a nested math function.
+    fn parse_value(&mut self) -> Option<MathExpression> {
+        let token = self.tokens.get(self.pos)?;
+
+        match token.kind {
+            TokenKind::Number | TokenKind::Integer => {
+                self.pos += 1;
+                return Some(MathExpression::Number(token.value.parse().ok()?));
+            }
+
+            TokenKind::Percentage => {
+                self.pos += 1;
+                return Some(MathExpression::Percentage(token.value.parse().ok()?));
+            }
+
+            TokenKind::Dimension => {
+                let unit = token.unit.to_ascii_lowercase();
+                if !LENGTH_UNITS.contains(&unit.as_str()) {
+                    return None;
+                }
+
+                self.pos += 1;
+                return Some(MathExpression::Length(token.value.parse().ok()?, unit));
+            }
+
+            TokenKind::ParenthesisOpen => {
+                self.pos += 1;
+                let expression = self.parse_sum()?;
+                if !self.accept(TokenKind::ParenthesisClose, None) {
+                    return None;
+                }
+
+                return Some(expression);
+            }
+
+            TokenKind::Function => {
+                return self.parse_function();
+            }
+
+            _ => {
+                return None;
+            }
+        }
+    }
+
+    /// Skips the current token if it is of given kind and, optionally, has given value.
+    fn accept(&mut self, kind: TokenKind, value: Option<&str>) -> bool {
+        let matches = self.tokens.get(self.pos)
+            .is_some_and(|token| token.kind == kind && value.is_none_or(|value| token.value == value));
+
+        if matches {
+            self.pos += 1;
+        }
+
+        return matches;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::tokenizer::tokenize;
+
+    /// Parses a math function that makes up all of given text.
+    fn parse(text: &str) -> Option<MathExpression> {
+        let tokens: Vec<Token> = tokenize(text).into_iter()
+            .filter(|token| token.kind != TokenKind::Whitespace && token.kind != TokenKind::EndOfInput)
+            .collect();
+
+        return parse_math_function(&tokens, 0)
+            .filter(|(end, _)| *end == tokens.len())
+            .map(|(_, expression)| expression);
+    }
+
+    fn get_type(text: &str) -> Option<MathType> {
+        return parse(text).and_then(|expression| expression.get_type());
+    }
+
+    fn evaluate(text: &str, percentage_reference: Option<f64>) -> Option<f64> {
+        return parse(text).expect("expression should be valid").evaluate(percentage_reference);
+    }
+
+    #[test]
+    fn type_checking() {
+        assert_eq!(get_type("calc(2 * 3)"), Some(MathType::Number));
+        assert_eq!(get_type("calc(1px + 2em)"), Some(MathType::Length));
+        assert_eq!(get_type("calc(2 * 1px)"), Some(MathType::Length));
+        assert_eq!(get_type("calc(1px / 2)"), Some(MathType::Length));
+        assert_eq!(get_type("calc(50% * 2)"), Some(MathType::Percentage));
+        assert_eq!(get_type("calc(100% - 10px)"), Some(MathType::LengthPercentage));
+        assert_eq!(get_type("clamp(1px, 50%, 3px)"), Some(MathType::LengthPercentage));
+        assert_eq!(get_type("max(1px, calc(2 * 3px))"), Some(MathType::Length));
+    }
+
+    #[test]
+    fn incompatible_types_are_rejected() {
+        assert!(parse("calc(1px + 2)").is_none());
+        assert!(parse("calc(2 - 1%)").is_none());
+        assert!(parse("calc(1px * 2px)").is_none());
+        assert!(parse("calc(1px / 2px)").is_none());
+        assert!(parse("calc(2 / 1px)").is_none());
+        assert!(parse("min(1px, 2)").is_none());
+        assert!(parse("clamp(1px, 2, 3px)").is_none());
+    }
+
+    #[test]
+    fn invalid_syntax_is_rejected() {
+        assert!(parse("calc()").is_none());
+        assert!(parse("calc(1px, 2px)").is_none());
+        assert!(parse("clamp(1px, 2px)").is_none());
+        assert!(parse("calc(1px +)").is_none());
+        assert!(parse("calc((1px + 2px)").is_none());
+        assert!(parse("calc(1deg)").is_none());
+        assert!(parse("calc(1px 2px)").is_none());
+    }
+
+    #[test]
+    fn evaluation() {
+        assert_eq!(evaluate("calc(1 + 2 * 3)", None), Some(7.0));
+        assert_eq!(evaluate("calc((1 + 2) * 3)", None), Some(9.0));
+        assert_eq!(evaluate("calc(10px - 4px - 3px)", None), Some(3.0));
+        assert_eq!(evaluate("min(10px, max(2px, 5px))", None), Some(5.0));
+        assert_eq!(evaluate("calc(50% + 10px)", Some(200.0)), Some(110.0));
+        assert_eq!(evaluate("calc(50% + 10px)", None), None);
+    }
+
+    #[test]
+    fn clamp_minimum_wins_over_maximum() {
+        assert_eq!(evaluate("clamp(1px, 2px, 3px)", None), Some(2.0));
+        assert_eq!(evaluate("clamp(1px, 5px, 3px)", None), Some(3.0));
+        assert_eq!(evaluate("clamp(1px, 0px, 3px)", None), Some(1.0));
+        assert_eq!(evaluate("clamp(10px, 5px, 2px)", None), Some(10.0));
+    }
+
+    #[test]
+    fn division_by_zero_has_no_value() {
+        assert_eq!(evaluate("calc(10px / 0)", None), None);
+        assert_eq!(evaluate("calc(10px / (2 - 2))", None), None);
+        assert_eq!(evaluate("max(1px, 10px / 0)", None), None);
+        assert_eq!(evaluate("calc(10px / 4)", None), Some(2.5));
+    }
+
+    #[test]
+    fn lengths_are_converted() {
+        let expression = parse("calc(1in + 2PX)").expect("expression should be valid");
+        let converted = expression.map_lengths(&|value, unit| match unit {
+            "in" => Some(value * 72.0),
+            "px" => Some(value * 0.75),
+            _ => None
+        });
+
+        assert_eq!(converted.and_then(|expression| expression.evaluate(None)), Some(73.5));
+        assert!(expression.map_lengths(&|_, _| None).is_none());
+    }
+}
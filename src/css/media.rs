@@ -0,0 +1,370 @@
+use crate::css::tokenizer::{Token, TokenKind, tokenize};
+use crate::html::DEFAULT_FONT_SIZE;
+use crate::utils::units::absolute_unit_to_pt;
+
+// Media queries are evaluated against the page the document is rendered on.
+// Documents are always rendered for print, so `print` and `all` are the only matching media types.
+// https://www.w3.org/TR/mediaqueries-4/
+
+/// Lengths converted from different units are rarely exactly equal,
+/// so the page size is compared to them with this tolerance, in points.
+const LENGTH_TOLERANCE: f64 = 0.01;
+
+/// The output device media queries are evaluated against. For paged media
+/// that is the page box, both sizes are in points.
+/// https://www.w3.org/TR/mediaqueries-4/#width
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MediaEnvironment {
+    pub width: f64,
+    pub height: f64
+}
+
+/// How a range media feature compares the environment to its value, given by the `min-` or `max-` prefix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Exact,
+    Min,
+    Max
+}
+
+/// https://www.w3.org/TR/mediaqueries-4/#orientation
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Orientation {
+    Portrait,
+    Landscape
+}
+
+/// A supported media feature. The value is `None` when the feature is used in a boolean context, like `(width)`.
+#[derive(Debug, Clone, PartialEq)]
+enum MediaFeature {
+    Width(Comparison, Option<f64>),
+    Height(Comparison, Option<f64>),
+    Orientation(Option<Orientation>)
+}
+
+/// A single media query, like `not screen` or `print and (min-width: 20cm)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaQuery {
+    negated: bool,
+
+    /// Lowercase name of the media type, `None` if the query consists of media features only.
+    media_type: Option<String>,
+    features: Vec<MediaFeature>
+}
+
+/// A comma-separated list of media queries, as found in `@media` and `@import` rules or in `media` attributes.
+/// It matches if any of its queries matches, or if it is empty.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaQueryList {
+
+    /// Queries of the list, `None` for those that could not be parsed, which never match.
+    queries: Vec<Option<MediaQuery>>
+}
+
+impl MediaFeature {
+    fn matches(&self, environment: &MediaEnvironment) -> bool {
+        match *self {
+            MediaFeature::Width(comparison, value) => compare_length(environment.width, comparison, value),
+            MediaFeature::Height(comparison, value) => compare_length(environment.height, comparison, value),
+            MediaFeature::Orientation(None) => true,
+            MediaFeature::Orientation(Some(orientation)) => {
+
+                // A square page is in portrait orientation.
+                let actual = if environment.height >= environment.width { Orientation::Portrait } else { Orientation::Landscape };
+                orientation == actual
+            }
+        }
+    }
+}
+
+impl MediaQuery {
+    pub fn matches(&self, environment: &MediaEnvironment) -> bool {
+        let type_matches = matches!(self.media_type.as_deref(), None | Some("all") | Some("print"));
+
+        let matches = type_matches && self.features.iter().all(|feature| feature.matches(environment));
+        return matches != self.negated;
+    }
+}
+
+impl MediaQueryList {
+    pub fn matches(&self, environment: &MediaEnvironment) -> bool {
+        if self.queries.is_empty() {
+            return true;
+        }
+
+        return self.queries.iter().flatten().any(|query| query.matches(environment));
+    }
+
+    /// Returns whether any of the queries could not be parsed.
+    pub fn has_invalid_queries(&self) -> bool {
+        return self.queries.iter().any(|query| query.is_none());
+    }
+}
+
+/// Compares a size of the environment to the value of a range media feature.
+/// In a boolean context, the feature matches if the size is not zero.
+fn compare_length(actual: f64, comparison: Comparison, value: Option<f64>) -> bool {
+    let value = match value {
+        Some(value) => value,
+        None => return actual != 0.0
+    };
+
+    match comparison {
+        Comparison::Exact => (actual - value).abs() <= LENGTH_TOLERANCE,
+        Comparison::Min => actual + LENGTH_TOLERANCE >= value,
+        Comparison::Max => actual - LENGTH_TOLERANCE <= value
+    }
+}
+
+/// Parses a length used as the value of a media feature into points. Relative lengths
+/// are relative to the initial font size, as there is no element to take it from.
+/// https://www.w3.org/TR/mediaqueries-4/#units
+fn parse_length(token: &Token) -> Option<f64> {
+    match token.kind {
+        TokenKind::Integer | TokenKind::Number if token.value.parse::<f64>().ok()? == 0.0 => Some(0.0),
+        TokenKind::Dimension => {
+            let value = token.value.parse::<f64>().ok()?;
+            let unit = token.unit.to_ascii_lowercase();
+
+            let size = match unit.as_str() {
+                "em" | "rem" => DEFAULT_FONT_SIZE,
+                "ex" => DEFAULT_FONT_SIZE / 2.0,
+                _ => absolute_unit_to_pt(&unit)?
+            };
+
+            Some(value * size)
+        }
+
+        _ => None
+    }
+}
+
+/// Parses the tokens of a media feature between its parentheses, e.g. `min-width: 10cm`.
+fn parse_media_feature(tokens: &[&Token]) -> Option<MediaFeature> {
+    let (name, value) = match tokens {
+        [name] => (name, None),
+        [name, colon, value] if colon.kind == TokenKind::Colon => (name, Some(*value)),
+        _ => return None
+    };
+
+    if name.kind != TokenKind::Identifier {
+        return None;
+    }
+
+    let name = name.value.to_ascii_lowercase();
+    let (comparison, name) = if let Some(name) = name.strip_prefix("min-") {
+        (Comparison::Min, name)
+    } else if let Some(name) = name.strip_prefix("max-") {
+        (Comparison::Max, name)
+    } else {
+        (Comparison::Exact, name.as_str())
+    };
+
+    // Prefixed features cannot be used in a boolean context.
+    if comparison != Comparison::Exact && value.is_none() {
+        return None;
+    }
+
+    match name {
+        "width" | "height" => {
+            let length = match value.map(parse_length) {
+                Some(None) => return None,
+                length => length.flatten()
+            };
+
+            if name == "width" {
+                Some(MediaFeature::Width(comparison, length))
+            } else {
+                Some(MediaFeature::Height(comparison, length))
+            }
+        }
+
+        "orientation" if comparison == Comparison::Exact => {
+            match value.map(parse_orientation) {
+                Some(None) => None,
+                orientation => Some(MediaFeature::Orientation(orientation.flatten()))
+            }
+        }
+
+        _ => None
+    }
+}
+
+fn parse_orientation(token: &Token) -> Option<Orientation> {
+    if token.kind != TokenKind::Identifier {
+        return None;
+    }
+
+    match token.value.to_ascii_lowercase().as_str() {
+        "portrait" => Some(Orientation::Portrait),
+        "landscape" => Some(Orientation::Landscape),
+        _ => None
+    }
+}
+
+/// Parses a single media query, whitespace excluded. Only the `and` combinator is supported:
+/// `[not | only]? <media-type> [and <media-feature>]*` or `<media-feature> [and <media-feature>]*`.
+/// https://www.w3.org/TR/mediaqueries-4/#mq-syntax
+fn parse_media_query(tokens: &[&Token]) -> Option<MediaQuery> {
+    let mut negated = false;
+    let mut media_type = None;
+    let mut idx = 0;
+
+    let is_keyword = |idx: usize, keyword: &str| {
+        tokens.get(idx).is_some_and(|token| token.kind == TokenKind::Identifier && token.value.eq_ignore_ascii_case(keyword))
+    };
+
+    if is_keyword(0, "not") || is_keyword(0, "only") {
+        negated = is_keyword(0, "not");
+        idx += 1;
+    }
+
+    match tokens.get(idx) {
+        Some(token) if token.kind == TokenKind::Identifier => {
+            let name = token.value.to_ascii_lowercase();
+            if ["and", "not", "only", "or"].contains(&name.as_str()) {
+                return None;
+            }
+
+            media_type = Some(name);
+            idx += 1;
+        }
+
+        // `not` and `only` have to be followed by a media type.
+        _ if idx > 0 => return None,
+        _ => {}
+    }
+
+    let mut features = Vec::<MediaFeature>::new();
+    while idx < tokens.len() {
+
+        // Features are separated by `and`, which also separates the first of them from the media type.
+        if media_type.is_some() || !features.is_empty() {
+            if !is_keyword(idx, "and") {
+                return None;
+            }
+
+            idx += 1;
+        }
+
+        if tokens.get(idx)?.kind != TokenKind::ParenthesisOpen {
+            return None;
+        }
+
+        let end = idx + tokens[idx..].iter().position(|token| token.kind == TokenKind::ParenthesisClose)?;
+        features.push(parse_media_feature(&tokens[idx + 1..end])?);
+        idx = end + 1;
+    }
+
+    if media_type.is_none() && features.is_empty() {
+        return None;
+    }
+
+    return Some(MediaQuery {
+        negated: negated,
+        media_type: media_type,
+        features: features
+    });
+}
+
+/// Parses a comma-separated list of media queries. Queries that cannot be parsed are kept
+/// in the list as ones that never match, so the rest of the list still applies.
+/// https://www.w3.org/TR/mediaqueries-4/#error-handling
+pub fn parse_media_query_list(tokens: &[Token]) -> MediaQueryList {
+    let mut queries = Vec::<Option<MediaQuery>>::new();
+    let mut query = Vec::<&Token>::new();
+    let mut depth = 0;
+
+    if tokens.iter().all(|token| token.kind == TokenKind::Whitespace || token.kind == TokenKind::EndOfInput) {
+        return MediaQueryList::default();
+    }
+
+    for token in tokens {
+        match token.kind {
+            TokenKind::Comma if depth == 0 => {
+                queries.push(parse_media_query(&query));
+                query.clear();
+                continue;
+            }
+
+            TokenKind::Function | TokenKind::ParenthesisOpen | TokenKind::SquareParenthesisOpen | TokenKind::BracketOpen => depth += 1,
+            TokenKind::ParenthesisClose | TokenKind::SquareParenthesisClose | TokenKind::BracketClose => depth -= 1,
+            TokenKind::Whitespace | TokenKind::EndOfInput => continue,
+            _ => {}
+        }
+
+        query.push(token);
+    }
+
+    queries.push(parse_media_query(&query));
+    return MediaQueryList { queries: queries };
+}
+
+/// Parses a media query list given as text, e.g. the value of a `media` attribute.
+pub fn parse_media_list(media: &str) -> MediaQueryList {
+    return parse_media_query_list(&tokenize(media));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORTRAIT: MediaEnvironment = MediaEnvironment { width: 595.0, height: 842.0 };
+    const LANDSCAPE: MediaEnvironment = MediaEnvironment { width: 842.0, height: 595.0 };
+
+    fn matches(media: &str, environment: &MediaEnvironment) -> bool {
+        return parse_media_list(media).matches(environment);
+    }
+
+    #[test]
+    fn only_print_media_match() {
+        assert!(matches("", &PORTRAIT));
+        assert!(matches("print", &PORTRAIT));
+        assert!(matches("ALL", &PORTRAIT));
+        assert!(matches("only print", &PORTRAIT));
+        assert!(matches("screen, print", &PORTRAIT));
+        assert!(matches("not screen", &PORTRAIT));
+
+        assert!(!matches("screen", &PORTRAIT));
+        assert!(!matches("not print", &PORTRAIT));
+        assert!(!matches("speech and (min-width: 1pt)", &PORTRAIT));
+    }
+
+    #[test]
+    fn widths_are_compared_to_the_page() {
+        assert!(matches("(min-width: 595pt)", &PORTRAIT));
+        assert!(matches("(max-width: 21cm)", &PORTRAIT));
+        assert!(matches("print and (min-width: 20cm) and (max-width: 22cm)", &PORTRAIT));
+        assert!(matches("(width: 595pt)", &PORTRAIT));
+        assert!(matches("(width)", &PORTRAIT));
+
+        assert!(!matches("(min-width: 600pt)", &PORTRAIT));
+        assert!(!matches("(max-width: 500pt)", &PORTRAIT));
+        assert!(!matches("(min-height: 600pt)", &LANDSCAPE));
+        assert!(matches("(max-height: 600pt)", &LANDSCAPE));
+    }
+
+    #[test]
+    fn orientation_depends_on_the_page_size() {
+        assert!(matches("(orientation: portrait)", &PORTRAIT));
+        assert!(!matches("(orientation: landscape)", &PORTRAIT));
+        assert!(matches("(orientation: landscape)", &LANDSCAPE));
+        assert!(matches("(orientation)", &LANDSCAPE));
+
+        // A square page is in portrait orientation.
+        assert!(matches("(orientation: portrait)", &MediaEnvironment { width: 500.0, height: 500.0 }));
+    }
+
+    #[test]
+    fn invalid_queries_never_match() {
+        let media = parse_media_list("(min-width), print");
+        assert!(media.has_invalid_queries());
+        assert!(media.matches(&PORTRAIT));
+
+        assert!(!matches("(min-width: 10px", &PORTRAIT));
+        assert!(!matches("(colour: red)", &PORTRAIT));
+        assert!(!matches("(orientation: sideways)", &PORTRAIT));
+        assert!(!matches("not (width)", &PORTRAIT));
+        assert!(!matches("print (width)", &PORTRAIT));
+    }
+}
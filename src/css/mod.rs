@@ -3,8 +3,35 @@ mod parser;
 mod token_buffer;
 mod property;
 mod primitive_value;
+mod selector;
+mod selector_parser;
+mod stylesheet;
+mod media;
+mod property_registry;
+mod value_parser;
+mod shorthand;
+mod math;
+mod variables;
 
-pub use self::parser::{Parser, parse_inline};
+pub use self::parser::{Parser, parse_stylesheet, parse_property_value, get_initial_value};
+pub use self::tokenizer::Token;
+pub use self::variables::{is_custom_property, substitute_variables};
 pub use self::property::PropertyDeclaration;
-pub use self::primitive_value::PrimitiveValue;
-pub use self::token_buffer::ParserTokenBuffer;
\ No newline at end of file
+pub use self::primitive_value::{PrimitiveValue, CssWideKeyword};
+pub use self::token_buffer::ParserTokenBuffer;
+pub use self::selector::{
+    Selector,
+    CompoundSelector,
+    SimpleSelector,
+    Combinator,
+    AttributeSelector,
+    AttributeOperator,
+    PseudoClass,
+    PageSelector,
+    PagePseudoClass,
+    Specificity
+};
+pub use self::stylesheet::{Stylesheet, StyleRule, PageRule, MarginRule, MarginBoxPosition, ImportRule};
+pub use self::media::{MediaEnvironment, MediaQueryList, parse_media_list};
+pub use self::math::{MathExpression, MathType};
+pub use self::property_registry::is_inherited_property;
@@ -1,127 +1,731 @@
-use crate::utils;
+use crate::diagnostics::Diagnostics;
 use crate::css::PropertyDeclaration;
-use crate::css::PrimitiveValue;
+use crate::css::{PrimitiveValue, CssWideKeyword};
 use crate::css::ParserTokenBuffer;
-use crate::css::tokenizer::{tokenize, TokenKind};
+use crate::css::{Stylesheet, StyleRule, PageRule, MarginRule, MarginBoxPosition, ImportRule};
+use crate::css::media::{MediaQueryList, parse_media_query_list};
+use crate::css::selector_parser::{parse_selector_list, parse_page_selector_list};
+use crate::css::tokenizer::{tokenize, tokens_to_string, Token, TokenKind};
+use crate::css::variables::{is_custom_property, contains_variable};
+use crate::css::property_registry::get_property_definition;
+use crate::css::value_parser::parse_value;
+use crate::css::shorthand::{get_shorthand_definition, expand_shorthand};
 
 pub struct Parser {
     input: String,
-    buffer: ParserTokenBuffer
+    buffer: ParserTokenBuffer,
+    diagnostics: Diagnostics,
+
+    /// Media query lists of the `@media` rules being parsed, outermost first.
+    media: Vec<MediaQueryList>
 }
 
 impl Parser {
     pub fn parse_inline(&mut self) -> Vec<PropertyDeclaration> {
-        let mut result = Vec::<PropertyDeclaration>::new();
         self.buffer = ParserTokenBuffer::new(tokenize(self.input.as_str()));
+        return self.parse_declaration_list(false);
+    }
+
+    pub fn parse_stylesheet(&mut self) -> Stylesheet {
+        let mut stylesheet = Stylesheet::new();
+        self.buffer = ParserTokenBuffer::new(tokenize(self.input.as_str()));
+        self.parse_rule_list(&mut stylesheet, false);
+        return stylesheet;
+    }
+
+    /// Parses rules into `stylesheet` up to the end of input, or up to and including
+    /// the `}` that closes the block when `in_block` is set (e.g. the block of an `@media` rule).
+    fn parse_rule_list(&mut self, stylesheet: &mut Stylesheet, in_block: bool) {
+        loop {
+            self.buffer.skip_whitespace();
+
+            match self.buffer.peek().kind {
+                TokenKind::EndOfInput => {
+                    break;
+                }
+
+                TokenKind::BracketClose if in_block => {
+                    self.buffer.next();
+                    break;
+                }
+
+                // `<!--` and `-->` are allowed at the top level for compatibility
+                // with old browsers and are simply ignored.
+                TokenKind::Cdo | TokenKind::Cdc if !in_block => {
+                    self.buffer.next();
+                }
 
-        while !self.buffer.is_out_of_bounds() {
-            let token = self.buffer.next();
-            if token.kind == TokenKind::EndOfInput {
-                break;
+                TokenKind::At => {
+                    let token = self.buffer.next().clone();
+
+                    if token.value.eq_ignore_ascii_case("@import") {
+
+                        // `@import` rules are only valid before all other rules.
+                        if in_block || !stylesheet.rules.is_empty() {
+                            self.diagnostics.warning_at(token.location, String::from("@import rules must precede all other rules, rule ignored"));
+                            self.skip_at_rule(in_block);
+                        } else if let Some(import) = self.parse_import_rule() {
+                            stylesheet.imports.push(import);
+                        }
+
+                        continue;
+                    }
+
+                    if token.value.eq_ignore_ascii_case("@media") {
+                        self.parse_media_rule(stylesheet, in_block);
+                        continue;
+                    }
+
+                    if token.value.eq_ignore_ascii_case("@page") {
+                        if let Some(rule) = self.parse_page_rule(in_block) {
+                            stylesheet.page_rules.push(rule);
+                        }
+
+                        continue;
+                    }
+
+                    self.diagnostics.warning_at(token.location, format!("unsupported at-rule {}", token.value));
+                    self.skip_at_rule(in_block);
+                }
+
+                _ => {
+                    if let Some(rule) = self.parse_style_rule(in_block) {
+                        stylesheet.rules.push(rule);
+                    }
+                }
             }
+        }
+    }
 
-            if token.kind == TokenKind::Identifier {
-                if let Some(declaration) = self.parse_declaration() {
-                    result.push(declaration);
+    /// Parses an `@media` rule, whose name has been consumed already, adding the rules
+    /// in its block to `stylesheet` along with its media query list.
+    /// https://www.w3.org/TR/css-conditional-3/#at-media
+    fn parse_media_rule(&mut self, stylesheet: &mut Stylesheet, in_block: bool) {
+        let location = self.buffer.peek().location;
+        let mut prelude = Vec::<Token>::new();
+
+        loop {
+            match self.buffer.peek().kind {
+                TokenKind::BracketOpen => {
+                    self.buffer.next();
+                    break;
+                }
+
+                TokenKind::BracketClose if in_block => {
+                    self.diagnostics.warning_at(location, String::from("expected a block after @media, rule ignored"));
+                    return;
                 }
+
+                TokenKind::Semicolon | TokenKind::EndOfInput => {
+                    self.diagnostics.warning_at(location, String::from("expected a block after @media, rule ignored"));
+                    self.buffer.expect(TokenKind::Semicolon);
+                    return;
+                }
+
+                _ => prelude.extend(self.read_component_value())
             }
         }
 
-        return result;
+        let media = parse_media_query_list(&prelude);
+        if media.has_invalid_queries() {
+            self.diagnostics.warning_at(location, format!("invalid media query in '{}', it never matches", tokens_to_string(&prelude).trim()));
+        }
+
+        self.media.push(media);
+        self.parse_rule_list(stylesheet, true);
+        self.media.pop();
     }
 
-    fn parse_declaration(&mut self) -> Option<PropertyDeclaration> {
-        let property_name = self.buffer.current().value.clone();
-        let mut declaration = PropertyDeclaration::new(property_name.clone());
-        self.buffer.expect(TokenKind::Colon);
-        let mut value: Option<PrimitiveValue> = None;
-        let mut supported = true;
+    /// Parses an `@page` rule, whose name has been consumed already.
+    /// https://www.w3.org/TR/css-page-3/#at-page-rule
+    fn parse_page_rule(&mut self, in_block: bool) -> Option<PageRule> {
+        let location = self.buffer.peek().location;
+        let mut prelude = Vec::<Token>::new();
+
+        loop {
+            match self.buffer.peek().kind {
+                TokenKind::BracketOpen => {
+                    self.buffer.next();
+                    break;
+                }
+
+                TokenKind::BracketClose if in_block => {
+                    self.diagnostics.warning_at(location, String::from("expected a block after @page, rule ignored"));
+                    return None;
+                }
 
-        match property_name.as_str() {
-            "width" | "height" => {
-                value = self.parse_generic_dimension_value();
+                TokenKind::Semicolon | TokenKind::EndOfInput => {
+                    self.diagnostics.warning_at(location, String::from("expected a block after @page, rule ignored"));
+                    self.buffer.expect(TokenKind::Semicolon);
+                    return None;
+                }
+
+                _ => prelude.extend(self.read_component_value())
             }
+        }
 
-            "display" => {
-                value = self.parse_display_value();
+        let mut margin_rules = Vec::<MarginRule>::new();
+        let declarations = self.parse_declarations(true, Some(&mut margin_rules));
+
+        match parse_page_selector_list(&prelude) {
+            Some(selectors) => {
+                return Some(PageRule {
+                    selectors: selectors,
+                    declarations: declarations,
+                    margin_rules: margin_rules,
+                    media: self.media.clone()
+                });
             }
 
-            "color" | "background-color" => {
-                value = self.parse_color_value();
+            None => {
+                self.diagnostics.warning_at(location, format!("invalid page selector '{}', rule ignored", tokens_to_string(&prelude).trim()));
+                return None;
             }
+        }
+    }
+
+    /// Parses a margin rule, like `@top-center { ... }`, whose name has been consumed already.
+    /// Margin rules have no prelude, a rule with one is ignored.
+    /// https://www.w3.org/TR/css-page-3/#syntax-page-selector
+    fn parse_margin_rule(&mut self, position: MarginBoxPosition, in_block: bool) -> Option<MarginRule> {
+        self.buffer.skip_whitespace();
+
+        let token = self.buffer.peek().clone();
+        if token.kind != TokenKind::BracketOpen {
+            self.diagnostics.warning_at(token.location, format!("expected a block after @{}, rule ignored", position.name()));
+            self.skip_at_rule(in_block);
+            return None;
+        }
+
+        self.buffer.next();
+        return Some(MarginRule {
+            position: position,
+            declarations: self.parse_declaration_list(true)
+        });
+    }
 
-            _ => {
-                supported = false;
-                println!("unsupported property declaration: {}", property_name);
-                self.buffer.skip_until(TokenKind::Semicolon);
-                self.buffer.expect(TokenKind::Semicolon);
+    /// Parses a rule consisting of a selector list and a declaration block.
+    /// A rule with an invalid selector is skipped entirely, its block included.
+    fn parse_style_rule(&mut self, in_block: bool) -> Option<StyleRule> {
+        let location = self.buffer.peek().location;
+        let mut prelude = Vec::<Token>::new();
+
+        loop {
+            match self.buffer.peek().kind {
+                TokenKind::BracketClose if in_block => {
+                    self.diagnostics.warning_at(location, String::from("unexpected '}', expected a declaration block"));
+                    return None;
+                }
+
+                TokenKind::BracketOpen => {
+                    self.buffer.next();
+                    break;
+                }
+
+                TokenKind::EndOfInput => {
+                    self.diagnostics.warning_at(self.buffer.peek().location, String::from("unexpected end of input, expected a declaration block"));
+                    return None;
+                }
+
+                _ => prelude.extend(self.read_component_value())
             }
         }
 
-        if supported {
-            if value.is_none() {
-                println!("invalid '{}' property value - cannot be {}", property_name, self.buffer.current().kind.to_string());
+        let declarations = self.parse_declaration_list(true);
+
+        match parse_selector_list(&prelude) {
+            Some(selectors) => {
+                return Some(StyleRule {
+                    selectors: selectors,
+                    declarations: declarations,
+                    media: self.media.clone()
+                });
+            }
+
+            None => {
+                self.diagnostics.warning_at(location, format!("invalid selector '{}', rule ignored", tokens_to_string(&prelude).trim()));
                 return None;
             }
-    
-            declaration.value = value.unwrap();
         }
-
-        return Some(declaration);
     }
 
-    fn parse_generic_dimension_value(&mut self) -> Option<PrimitiveValue> {
-        let token = self.buffer.next();
-        match token.kind {
-            TokenKind::Dimension => {
-                if let Ok(value) = token.value.to_string().parse() {
-                    return Some(PrimitiveValue::from_dimension_value(&token.value, value, &token.unit));
+    /// Parses the url and media list of an `@import` rule, up to and including the `;` that ends it.
+    fn parse_import_rule(&mut self) -> Option<ImportRule> {
+        self.buffer.skip_whitespace();
+
+        let token = self.buffer.peek().clone();
+        let url = match token.kind {
+            TokenKind::Url | TokenKind::String => {
+                self.buffer.next();
+                Some(token.value.clone())
+            }
+
+            TokenKind::Function if token.value.eq_ignore_ascii_case("url") => {
+                self.buffer.next();
+                self.buffer.skip_whitespace();
+                let url = self.buffer.peek().clone();
+
+                if url.kind == TokenKind::String {
+                    self.buffer.next();
+                    self.buffer.skip_whitespace();
                 }
 
-                return None;
+                if url.kind == TokenKind::String && self.buffer.expect(TokenKind::ParenthesisClose) {
+                    Some(url.value)
+                } else {
+                    None
+                }
             }
 
-            _ => {
+            _ => None
+        };
+
+        let url = match url {
+            Some(url) => url,
+            None => {
+                self.diagnostics.warning_at(token.location, String::from("expected a url or a string after @import, rule ignored"));
+                self.skip_at_rule(false);
                 return None;
             }
+        };
+
+        let mut media = Vec::<Token>::new();
+        loop {
+            match self.buffer.peek().kind {
+                TokenKind::EndOfInput => break,
+                TokenKind::Semicolon => {
+                    self.buffer.next();
+                    break;
+                }
+
+                _ => media.extend(self.read_component_value())
+            }
         }
+
+        let location = media.iter().find(|token| token.kind != TokenKind::Whitespace).map(|token| token.location).unwrap_or(token.location);
+        let media = parse_media_query_list(&media);
+        if media.has_invalid_queries() {
+            self.diagnostics.warning_at(location, String::from("invalid media query in @import rule, it never matches"));
+        }
+
+        return Some(ImportRule {
+            url: url,
+            media: media
+        });
     }
 
-    fn parse_display_value(&mut self) -> Option<PrimitiveValue> {
-        let token = self.buffer.next();
-        match token.kind {
-            TokenKind::Identifier => {
-                return Some(PrimitiveValue::from_identifier(&token.value));
+    /// Parses declarations up to the end of input, or up to and including the `}` that closes
+    /// the block when `in_block` is set. Anything that is not a declaration is skipped up to the next `;`.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-list-of-declarations
+    fn parse_declaration_list(&mut self, in_block: bool) -> Vec<PropertyDeclaration> {
+        return self.parse_declarations(in_block, None);
+    }
+
+    /// Parses a declaration list like `parse_declaration_list`. Margin rules found in the list
+    /// are added to `margin_rules`, or skipped like any other at-rule if it is `None`.
+    fn parse_declarations(&mut self, in_block: bool, mut margin_rules: Option<&mut Vec<MarginRule>>) -> Vec<PropertyDeclaration> {
+        let mut result = Vec::<PropertyDeclaration>::new();
+
+        loop {
+            let token = self.buffer.peek().clone();
+            match token.kind {
+                TokenKind::EndOfInput => break,
+                TokenKind::BracketClose if in_block => {
+                    self.buffer.next();
+                    break;
+                }
+
+                TokenKind::Whitespace | TokenKind::Semicolon => {
+                    self.buffer.next();
+                }
+
+                TokenKind::Identifier => {
+                    self.buffer.next();
+                    let declarations = self.parse_declaration(in_block);
+                    result.extend(declarations);
+                }
+
+                TokenKind::At => {
+                    self.buffer.next();
+
+                    let position = MarginBoxPosition::from_name(&token.value[1..]);
+                    if let (Some(position), Some(margin_rules)) = (position, margin_rules.as_deref_mut()) {
+                        if let Some(rule) = self.parse_margin_rule(position, in_block) {
+                            margin_rules.push(rule);
+                        }
+                    } else {
+                        self.diagnostics.warning_at(token.location, format!("unsupported at-rule {} in a declaration block", token.value));
+                        self.skip_at_rule(in_block);
+                    }
+                }
+
+                _ => {
+                    self.diagnostics.warning_at(token.location, format!("unexpected '{}', expected a property name", tokens_to_string(std::slice::from_ref(&token))));
+                    self.read_declaration_value(in_block);
+                }
             }
+        }
 
-            _ => {
-                return None;
+        return result;
+    }
+
+    /// Skips the rest of an at-rule, that is everything up to and including the next `;`
+    /// or the entire `{}` block following the rule's prelude. When the rule is in a block,
+    /// the `}` that closes the block ends the rule too, but is not consumed.
+    fn skip_at_rule(&mut self, in_block: bool) {
+        loop {
+            match self.buffer.peek().kind {
+                TokenKind::EndOfInput => break,
+                TokenKind::BracketClose if in_block => break,
+                TokenKind::Semicolon => {
+                    self.buffer.next();
+                    break;
+                }
+
+                TokenKind::BracketOpen => {
+                    self.read_component_value();
+                    break;
+                }
+
+                _ => {
+                    self.read_component_value();
+                }
             }
         }
     }
 
-    fn parse_color_value(&mut self) -> Option<PrimitiveValue> {
-        let token = self.buffer.next();
-        match token.kind {
-            TokenKind::Hash |
-            TokenKind::Identifier => {
-                return Some(PrimitiveValue::from_color(&token.value, utils::color::code_to_color(&token.value)));
+    /// Parses a single declaration, whose name has been consumed already, up to and including the `;` that ends it.
+    /// A shorthand property is expanded into declarations of all its longhands.
+    fn parse_declaration(&mut self, in_block: bool) -> Vec<PropertyDeclaration> {
+        let name = self.buffer.current().clone();
+
+        // Names of custom properties are case-sensitive.
+        let property_name = match &name.value {
+            name if is_custom_property(name) => name.clone(),
+            name => name.to_ascii_lowercase()
+        };
+
+        self.buffer.skip_whitespace();
+        if !self.buffer.expect(TokenKind::Colon) {
+            self.diagnostics.warning_at(self.buffer.peek().location, format!("expected ':' after property name '{}', declaration ignored", property_name));
+            self.read_declaration_value(in_block);
+            return Vec::new();
+        }
+
+        let mut tokens = self.read_declaration_value(in_block);
+
+        if !is_known_property(&property_name) {
+            self.diagnostics.warning_at(name.location, format!("unsupported property declaration: {}", property_name));
+            return Vec::new();
+        }
+
+        if let Some(token) = tokens.iter().find(|token| token.kind == TokenKind::BadString || token.kind == TokenKind::BadUrl) {
+            self.diagnostics.warning_at(token.location, format!("{} in '{}' property declaration, declaration ignored", token.kind, property_name));
+            return Vec::new();
+        }
+
+        let important = match parse_important(&mut tokens) {
+            Some(important) => important,
+            None => {
+                self.diagnostics.warning_at(name.location, format!("expected 'important' after '!' in '{}' property declaration", property_name));
+                return Vec::new();
             }
+        };
 
-            _ => {
-                return None;
+        let values = match parse_property_value(&property_name, &tokens) {
+            Some(values) => values,
+            None => {
+                let location = tokens.first().map(|token| token.location).unwrap_or(name.location);
+                self.diagnostics.warning_at(location, format!("invalid value of '{}' property: {}", property_name, tokens_to_string(&tokens)));
+                return Vec::new();
             }
+        };
+
+        let mut declarations = Vec::<PropertyDeclaration>::new();
+        for (name, value) in values {
+            let mut declaration = PropertyDeclaration::new(name);
+            declaration.value = value;
+            declaration.important = important;
+            declarations.push(declaration);
         }
+
+        return declarations;
+    }
+
+    /// Reads all tokens of current declaration's value, consuming the `;` that ends it.
+    /// When the declaration is in a block, the `}` that closes the block ends it too, but is not consumed.
+    fn read_declaration_value(&mut self, in_block: bool) -> Vec<Token> {
+        let mut tokens = Vec::<Token>::new();
+
+        loop {
+            match self.buffer.peek().kind {
+                TokenKind::EndOfInput => break,
+                TokenKind::BracketClose if in_block => break,
+                TokenKind::Semicolon => {
+                    self.buffer.next();
+                    break;
+                }
+
+                _ => tokens.extend(self.read_component_value())
+            }
+        }
+
+        return tokens;
+    }
+
+    /// Reads the next component value, which is either a single token, or a function or a block
+    /// including everything up to its matching closing token (or the end of input).
+    /// https://www.w3.org/TR/css-syntax-3/#consume-component-value
+    fn read_component_value(&mut self) -> Vec<Token> {
+        if self.buffer.peek().kind == TokenKind::EndOfInput {
+            return Vec::new();
+        }
+
+        let token = self.buffer.next().clone();
+        let closing_kind = get_closing_token_kind(&token.kind);
+        let mut tokens = vec![token];
+
+        if let Some(closing_kind) = closing_kind {
+            loop {
+                let kind = &self.buffer.peek().kind;
+                if *kind == TokenKind::EndOfInput {
+                    break;
+                }
+
+                if *kind == closing_kind {
+                    tokens.push(self.buffer.next().clone());
+                    break;
+                }
+
+                tokens.extend(self.read_component_value());
+            }
+        }
+
+        return tokens;
+    }
+
+    /// Moves all problems found while parsing out of the parser.
+    pub fn take_diagnostics(&mut self) -> Diagnostics {
+        return std::mem::take(&mut self.diagnostics);
     }
 
     pub fn new(input: String) -> Parser {
         Parser {
             input: input,
-            buffer: ParserTokenBuffer::default()
+            buffer: ParserTokenBuffer::default(),
+            diagnostics: Diagnostics::new(),
+            media: Vec::new()
         }
     }
 }
 
-pub fn parse_inline(input: &str) -> Vec<PropertyDeclaration> {
-    return Parser::new(input.to_string()).parse_inline();
-}
\ No newline at end of file
+/// Returns kind of the token that closes a function or a block started by a token of given kind.
+fn get_closing_token_kind(kind: &TokenKind) -> Option<TokenKind> {
+    match kind {
+        TokenKind::Function | TokenKind::ParenthesisOpen => Some(TokenKind::ParenthesisClose),
+        TokenKind::SquareParenthesisOpen => Some(TokenKind::SquareParenthesisClose),
+        TokenKind::BracketOpen => Some(TokenKind::BracketClose),
+        _ => None
+    }
+}
+
+/// Returns whether given property is supported, which includes all custom properties.
+fn is_known_property(name: &str) -> bool {
+    return is_custom_property(name) || get_property_definition(name).is_some() || get_shorthand_definition(name).is_some();
+}
+
+/// Returns the initial value of given longhand property, or `None` if the property is not supported
+/// or its initial value depends on the user agent or on other properties.
+/// https://www.w3.org/TR/css-cascade-4/#initial-values
+pub fn get_initial_value(property_name: &str) -> Option<PrimitiveValue> {
+    let definition = get_property_definition(property_name)?;
+    if definition.initial.is_empty() {
+        return None;
+    }
+
+    let tokens: Vec<Token> = tokenize(definition.initial).into_iter().filter(|token| token.kind != TokenKind::EndOfInput).collect();
+    return parse_value(&definition.grammar, &tokens);
+}
+
+/// Parses the value of a property into values of all (longhand) properties it sets.
+/// Values with a `var()` cannot be parsed until the variables are substituted,
+/// so they are kept as unparsed tokens, just like the values of custom properties.
+/// Returns `None` if the value is invalid or the property is not supported.
+pub fn parse_property_value(property_name: &str, tokens: &[Token]) -> Option<Vec<(String, PrimitiveValue)>> {
+    if !is_known_property(property_name) {
+        return None;
+    }
+
+    let shorthand_definition = get_shorthand_definition(property_name);
+    let longhands: Vec<String> = match shorthand_definition {
+        Some(shorthand) => shorthand.longhands.iter().map(|longhand| longhand.to_string()).collect(),
+        None => vec![property_name.to_string()]
+    };
+
+    // A CSS-wide keyword applies to all longhands of a shorthand.
+    if let Some(keyword) = parse_css_wide_keyword(tokens) {
+        return Some(longhands.into_iter().map(|longhand| (longhand, PrimitiveValue::from_css_wide_keyword(keyword))).collect());
+    }
+
+    if is_custom_property(property_name) || contains_variable(tokens) {
+        let mut tokens = tokens.to_vec();
+        trim_whitespace(&mut tokens);
+
+        let shorthand_name = shorthand_definition.map(|shorthand| shorthand.name.to_string());
+        return Some(longhands.into_iter().map(|longhand| (longhand, PrimitiveValue::from_unparsed(tokens.clone(), shorthand_name.clone()))).collect());
+    }
+
+    if let Some(shorthand) = shorthand_definition {
+        return expand_shorthand(shorthand, tokens).map(|values| values.into_iter()
+            .map(|(longhand, value)| (longhand.to_string(), value))
+            .collect());
+    }
+
+    let definition = get_property_definition(property_name)?;
+    let value = parse_value(&definition.grammar, tokens)?;
+
+    // `currentColor` is the value of the `color` property, so for `color` itself it means the parent's color.
+    // https://www.w3.org/TR/css-color-4/#resolving-other-colors
+    if property_name == "color" && value.is_keyword("currentcolor") {
+        return Some(vec![(property_name.to_string(), PrimitiveValue::from_css_wide_keyword(CssWideKeyword::Inherit))]);
+    }
+
+    return Some(vec![(property_name.to_string(), value)]);
+}
+
+/// Removes `!important` from the end of a declaration's value.
+/// Returns whether it was there, or `None` if there is a `!` which is not followed by `important`.
+fn parse_important(tokens: &mut Vec<Token>) -> Option<bool> {
+    trim_whitespace(tokens);
+
+    let bang_position = match tokens.iter().rposition(|token| token.kind == TokenKind::Delimeter && token.value == "!") {
+        Some(position) => position,
+        None => return Some(false)
+    };
+
+    let rest: Vec<&Token> = tokens[bang_position + 1..].iter().filter(|token| token.kind != TokenKind::Whitespace).collect();
+    if rest.len() != 1 || rest[0].kind != TokenKind::Identifier || !rest[0].value.eq_ignore_ascii_case("important") {
+        return None;
+    }
+
+    tokens.truncate(bang_position);
+    trim_whitespace(tokens);
+    return Some(true);
+}
+
+/// Parses `inherit`, `initial`, `unset` or `revert`, if that is the entire value.
+fn parse_css_wide_keyword(tokens: &[Token]) -> Option<CssWideKeyword> {
+    match tokens {
+        [token] if token.kind == TokenKind::Identifier => CssWideKeyword::from_name(&token.value),
+        _ => None
+    }
+}
+
+fn trim_whitespace(tokens: &mut Vec<Token>) {
+    while tokens.last().is_some_and(|token| token.kind == TokenKind::Whitespace) {
+        tokens.pop();
+    }
+
+    while tokens.first().is_some_and(|token| token.kind == TokenKind::Whitespace) {
+        tokens.remove(0);
+    }
+}
+
+pub fn parse_stylesheet(input: &str) -> Stylesheet {
+    return Parser::new(input.to_string()).parse_stylesheet();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::SourceLocation;
+
+    /// Parses given stylesheet, returning it with the messages and locations of all problems found.
+    fn parse(input: &str) -> (Stylesheet, Vec<(String, Option<SourceLocation>)>) {
+        let mut parser = Parser::new(input.to_string());
+        let stylesheet = parser.parse_stylesheet();
+        let diagnostics = parser.take_diagnostics().iter()
+            .map(|diagnostic| (diagnostic.message.clone(), diagnostic.location))
+            .collect();
+
+        return (stylesheet, diagnostics);
+    }
+
+    /// Returns names of the properties each rule declares, in order.
+    fn declared_properties(stylesheet: &Stylesheet) -> Vec<Vec<String>> {
+        return stylesheet.rules.iter()
+            .map(|rule| rule.declarations.iter().map(|declaration| declaration.name.clone()).collect())
+            .collect();
+    }
+
+    #[test]
+    fn invalid_values_drop_only_their_declaration() {
+        let (stylesheet, diagnostics) = parse("p { color: 12pt; width: 10pt; text-align: middle; orphans: 3 } div { width: 5pt }");
+        assert_eq!(declared_properties(&stylesheet), vec![vec!["width", "orphans"], vec!["width"]]);
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics[0].0.starts_with("invalid value of 'color' property"));
+        assert!(diagnostics[1].0.starts_with("invalid value of 'text-align' property"));
+    }
+
+    #[test]
+    fn blocks_in_values_extend_to_their_closing_bracket() {
+
+        // The `;` and `}` in the blocks do not end the declaration or the rule.
+        let (stylesheet, _) = parse("p { color: (red; width: 1pt}); orphans: 3 } div { width: 5pt }");
+        assert_eq!(declared_properties(&stylesheet), vec![vec!["orphans"], vec!["width"]]);
+
+        let (stylesheet, _) = parse("p { color: [red; } width: 1pt]; orphans: 3 } div { width: 5pt }");
+        assert_eq!(declared_properties(&stylesheet), vec![vec!["orphans"], vec!["width"]]);
+
+        let (stylesheet, _) = parse("p { color: {red; width: 1pt}; orphans: 3 } div { width: 5pt }");
+        assert_eq!(declared_properties(&stylesheet), vec![vec!["orphans"], vec!["width"]]);
+
+        // A block that is never closed takes the rest of the input.
+        let (stylesheet, _) = parse("p { orphans: 3; color: (red; } div { width: 5pt }");
+        assert_eq!(declared_properties(&stylesheet), vec![vec!["orphans"]]);
+    }
+
+    #[test]
+    fn invalid_selectors_drop_only_their_rule() {
+        let (stylesheet, diagnostics) = parse("p { width: 1pt } div, p!! { width: 2pt } span { width: 3pt }");
+        assert_eq!(stylesheet.rules.len(), 2);
+        assert_eq!(stylesheet.rules[1].declarations[0].value.as_dimension_value().value, 3.0);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].0, "invalid selector 'div, p!!', rule ignored");
+    }
+
+    #[test]
+    fn hashes_that_are_not_identifiers_are_not_id_selectors() {
+        let (stylesheet, diagnostics) = parse("#1a { width: 1pt } p, #-2 { width: 2pt } #a1 { width: 3pt } #\\31 a { width: 4pt }");
+        assert_eq!(stylesheet.rules.len(), 2);
+        assert_eq!(stylesheet.rules[0].declarations[0].value.as_dimension_value().value, 3.0);
+        assert_eq!(stylesheet.rules[1].declarations[0].value.as_dimension_value().value, 4.0);
+        assert_eq!(diagnostics.len(), 2);
+    }
+
+    #[test]
+    fn unsupported_at_rules_are_skipped() {
+        let input = "@charset \"utf-8\"; @font-face { font-family: a; src: url(a.ttf) } p { @unknown a; width: 1pt } @keyframes a { from { width: 0 } } div { width: 2pt }";
+        let (stylesheet, diagnostics) = parse(input);
+        assert_eq!(declared_properties(&stylesheet), vec![vec!["width"], vec!["width"]]);
+
+        let messages: Vec<&str> = diagnostics.iter().map(|(message, _)| message.as_str()).collect();
+        assert_eq!(messages, vec![
+            "unsupported at-rule @charset",
+            "unsupported at-rule @font-face",
+            "unsupported at-rule @unknown in a declaration block",
+            "unsupported at-rule @keyframes"
+        ]);
+    }
+
+    #[test]
+    fn problems_are_located_in_the_source() {
+        let (_, diagnostics) = parse("p {\n  color: 12pt;\n  colr: red;\n}\n\ndiv!! { }");
+        let locations: Vec<(usize, usize)> = diagnostics.iter()
+            .map(|(_, location)| location.map(|location| (location.line, location.column)).unwrap())
+            .collect();
+
+        // The value of the invalid declaration, the unknown property name and the start of the rule.
+        assert_eq!(locations, vec![(2, 10), (3, 3), (6, 1)]);
+    }
+}
@@ -1,6 +1,8 @@
 #![allow(unused)]
 
 use crate::utils::Color;
+use crate::css::math::MathExpression;
+use crate::css::tokenizer::{Token, tokens_to_string};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum PrimitiveValueKind {
@@ -8,15 +10,73 @@ pub enum PrimitiveValueKind {
     Color,
     String,
     DimensionValue,
+    Number,
+    Percentage,
+    Url,
+
+    /// A function like `counter(name)`, `value` holds its name and `items` its arguments.
+    Function,
+
+    /// Multiple values, e.g. `0% 100%`, stored in `items`.
+    List,
+
+    /// A math function like `calc(100% - 40px)`, stored in `math`.
+    Math,
+
+    /// Tokens of a custom property's value, or of a value with `var()` that will be parsed
+    /// once the variables are substituted, stored in `unparsed`.
+    Unparsed,
+    CssWideKeyword,
     None
 }
 
+/// Keywords that are valid values of every property.
+/// https://www.w3.org/TR/css-cascade-4/#defaulting-keywords
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CssWideKeyword {
+    Inherit,
+    Initial,
+    Unset,
+    Revert
+}
+
+impl CssWideKeyword {
+    pub fn from_name(name: &str) -> Option<CssWideKeyword> {
+        match name.to_ascii_lowercase().as_str() {
+            "inherit" => Some(CssWideKeyword::Inherit),
+            "initial" => Some(CssWideKeyword::Initial),
+            "unset" => Some(CssWideKeyword::Unset),
+            "revert" => Some(CssWideKeyword::Revert),
+            _ => None
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PrimitiveValue {
     pub kind: PrimitiveValueKind,
     pub value: String,
     color: Color,
-    dimension_value: DimensionValue
+    dimension_value: DimensionValue,
+
+    /// Values of a list or arguments of a function.
+    items: Vec<PrimitiveValue>,
+
+    /// Whether the items of a list are separated by commas rather than spaces.
+    is_comma_separated: bool,
+    math: Option<Box<MathExpression>>,
+    unparsed: Option<Box<UnparsedValue>>
+}
+
+/// A value that has not been parsed yet, see `PrimitiveValueKind::Unparsed`.
+#[derive(Debug, Clone)]
+pub struct UnparsedValue {
+    pub tokens: Vec<Token>,
+
+    /// Name of the shorthand property the value was given to, if any.
+    /// The value then has to be parsed as the shorthand's value and expanded, which gives
+    /// the value of each longhand.
+    pub shorthand: Option<String>
 }
 
 #[derive(Debug, Default, Clone)]
@@ -29,60 +89,197 @@ impl PrimitiveValue {
     pub fn as_color(&self) -> &Color { &self.color }
     pub fn as_string(&self) -> &String { &self.value }
     pub fn as_dimension_value(&self) -> &DimensionValue { &self.dimension_value }
-    
+    pub fn as_number(&self) -> f64 { self.dimension_value.value }
+    pub fn as_items(&self) -> &Vec<PrimitiveValue> { &self.items }
+    pub fn is_comma_separated(&self) -> bool { self.is_comma_separated }
+    pub fn as_math(&self) -> Option<&MathExpression> { self.math.as_deref() }
+    pub fn as_unparsed(&self) -> Option<&UnparsedValue> { self.unparsed.as_deref() }
+
     pub fn is_color(&self) -> bool { self.kind == PrimitiveValueKind::Color }
     pub fn is_string(&self) -> bool { self.kind == PrimitiveValueKind::String }
     pub fn is_dimension_value(&self) -> bool { self.kind == PrimitiveValueKind::DimensionValue }
     pub fn is_identifier(&self) -> bool { self.kind == PrimitiveValueKind::Identifier }
+    pub fn is_number(&self) -> bool { self.kind == PrimitiveValueKind::Number }
+    pub fn is_percentage(&self) -> bool { self.kind == PrimitiveValueKind::Percentage }
+    pub fn is_url(&self) -> bool { self.kind == PrimitiveValueKind::Url }
+    pub fn is_function(&self) -> bool { self.kind == PrimitiveValueKind::Function }
+    pub fn is_list(&self) -> bool { self.kind == PrimitiveValueKind::List }
+    pub fn is_math(&self) -> bool { self.kind == PrimitiveValueKind::Math }
+    pub fn is_unparsed(&self) -> bool { self.kind == PrimitiveValueKind::Unparsed }
     pub fn is_none(&self) -> bool { self.kind == PrimitiveValueKind::None }
 
+    /// Returns whether this is given keyword, compared case-insensitively.
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        return self.is_identifier() && self.value.eq_ignore_ascii_case(keyword);
+    }
+    pub fn is_css_wide_keyword(&self) -> bool { self.kind == PrimitiveValueKind::CssWideKeyword }
+
+    pub fn as_css_wide_keyword(&self) -> Option<CssWideKeyword> {
+        if !self.is_css_wide_keyword() {
+            return None;
+        }
+
+        return CssWideKeyword::from_name(&self.value);
+    }
+
     pub fn has_value(&self) -> bool { !self.value.is_empty() }
+
+    /// Returns the value written in CSS syntax.
+    pub fn to_css_string(&self) -> String {
+        match self.kind {
+            PrimitiveValueKind::String => format!("\"{}\"", self.value),
+            PrimitiveValueKind::Url => format!("url({})", self.value),
+            PrimitiveValueKind::Function => {
+                let arguments = self.items.iter().map(|item| item.to_css_string()).collect::<Vec<String>>();
+                format!("{}({})", self.value, arguments.join(", "))
+            }
+
+            _ => self.value.clone()
+        }
+    }
 }
 
 impl PrimitiveValue {
-    pub fn from_color(value: &String, color: Color) -> Self {
+    pub fn from_color(value: &str, color: Color) -> Self {
         Self {
             kind: PrimitiveValueKind::Color,
-            value: value.clone(),
+            value: value.to_string(),
             color: color,
             ..Self::default()
         }
     }
 
-    pub fn from_string(value: &String) -> Self {
+    pub fn from_string(value: &str) -> Self {
         Self {
             kind: PrimitiveValueKind::String,
-            value: value.clone(),
+            value: value.to_string(),
             ..Self::default()
         }
     }
     
-    pub fn from_dimension_value(full_value: &String, value: f64, dimension: &String) -> Self {
+    pub fn from_dimension_value(full_value: &str, value: f64, dimension: &str) -> Self {
         Self {
-            kind: PrimitiveValueKind::String,
-            value: full_value.clone(),
+            kind: PrimitiveValueKind::DimensionValue,
+            value: full_value.to_string(),
             dimension_value: DimensionValue {
                 value: value,
-                dimension: dimension.clone()
+                dimension: dimension.to_string()
             },
             ..Self::default()
         }
     }
 
-    pub fn from_identifier(value: &String) -> Self {
+    pub fn from_identifier(value: &str) -> Self {
         Self {
             kind: PrimitiveValueKind::Identifier,
-            value: value.clone(),
+            value: value.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_number(full_value: &str, value: f64) -> Self {
+        Self {
+            kind: PrimitiveValueKind::Number,
+            value: full_value.to_string(),
+            dimension_value: DimensionValue {
+                value: value,
+                dimension: String::new()
+            },
             ..Self::default()
         }
     }
 
-    pub fn default() -> Self {
+    pub fn from_percentage(full_value: &str, value: f64) -> Self {
+        Self {
+            kind: PrimitiveValueKind::Percentage,
+            value: full_value.to_string(),
+            dimension_value: DimensionValue {
+                value: value,
+                dimension: String::from("%")
+            },
+            ..Self::default()
+        }
+    }
+
+    pub fn from_url(url: &str) -> Self {
+        Self {
+            kind: PrimitiveValueKind::Url,
+            value: url.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_function(name: &str, arguments: Vec<PrimitiveValue>) -> Self {
+        Self {
+            kind: PrimitiveValueKind::Function,
+            value: name.to_string(),
+            items: arguments,
+            is_comma_separated: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn from_list(items: Vec<PrimitiveValue>, is_comma_separated: bool) -> Self {
+        let separator = if is_comma_separated { ", " } else { " " };
+        let text = items.iter().map(|item| item.to_css_string()).collect::<Vec<String>>().join(separator);
+
+        Self {
+            kind: PrimitiveValueKind::List,
+            value: text,
+            items: items,
+            is_comma_separated: is_comma_separated,
+            ..Self::default()
+        }
+    }
+
+    pub fn from_math(expression: MathExpression) -> Self {
+        Self {
+            kind: PrimitiveValueKind::Math,
+            value: expression.to_css_string(),
+            math: Some(Box::new(expression)),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_unparsed(tokens: Vec<Token>, shorthand: Option<String>) -> Self {
+        Self {
+            kind: PrimitiveValueKind::Unparsed,
+            value: tokens_to_string(&tokens),
+            unparsed: Some(Box::new(UnparsedValue {
+                tokens: tokens,
+                shorthand: shorthand
+            })),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_css_wide_keyword(keyword: CssWideKeyword) -> Self {
+        let value = match keyword {
+            CssWideKeyword::Inherit => "inherit",
+            CssWideKeyword::Initial => "initial",
+            CssWideKeyword::Unset => "unset",
+            CssWideKeyword::Revert => "revert"
+        };
+
+        Self {
+            kind: PrimitiveValueKind::CssWideKeyword,
+            value: String::from(value),
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for PrimitiveValue {
+    fn default() -> Self {
         Self {
             kind: PrimitiveValueKind::None,
             value: String::new(),
             color: Color::default(),
-            dimension_value: DimensionValue::default()
+            dimension_value: DimensionValue::default(),
+            items: Vec::new(),
+            is_comma_separated: false,
+            math: None,
+            unparsed: None
         }
     }
-}
\ No newline at end of file
+}
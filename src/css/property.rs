@@ -1,16 +1,20 @@
 use crate::css::primitive_value::{PrimitiveValue};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PropertyDeclaration {
     pub name: String,
     pub value: PrimitiveValue,
+
+    /// Whether the declaration was marked with `!important`.
+    pub important: bool
 }
 
 impl PropertyDeclaration {
     pub fn new(name: String) -> PropertyDeclaration {
         PropertyDeclaration {
             name: name,
-            value: PrimitiveValue::default()
+            value: PrimitiveValue::default(),
+            important: false
         }
     }
 }
\ No newline at end of file
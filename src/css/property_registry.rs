@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use self::ValueGrammar as G;
+
+// All properties of CSS 2.1, with their value definitions, initial values and inheritance:
+// https://www.w3.org/TR/CSS21/propidx.html
+//
+// Aural properties (azimuth, cue, pause, ...) are left out, they have no meaning in a PDF.
+// Shorthand properties are not listed here, they are expanded into these longhands.
+//
+// A few properties of later specifications are included too, like `opacity` from
+// https://www.w3.org/TR/css-color-3/#transparency, `page` and `size` from https://www.w3.org/TR/css-page-3/,
+// `string-set` from https://www.w3.org/TR/css-gcpm-3/ and `break-*` from https://www.w3.org/TR/css-break-3/,
+// which replace the `page-break-*` properties of CSS 2.1.
+
+/// Describes which values a property accepts, using the same combinators as the
+/// value definition syntax: https://www.w3.org/TR/CSS21/about.html#value-defs
+#[derive(Debug)]
+pub enum ValueGrammar {
+
+    /// A single keyword, compared case-insensitively.
+    Keyword(&'static str),
+
+    /// Any of given keywords.
+    Keywords(&'static [&'static str]),
+
+    /// `<length>`, including unitless zero.
+    Length,
+
+    /// `<percentage>`
+    Percentage,
+
+    /// `<number>`
+    Number,
+
+    /// `<integer>`
+    Integer,
+
+    /// `<color>`
+    Color,
+
+    /// `<uri>`
+    Uri,
+
+    /// `<string>`
+    String,
+
+    /// `<identifier>`, e.g. a counter name.
+    Identifier,
+
+    /// A font family name, either a string or a sequence of identifiers.
+    FamilyName,
+
+    /// A literal comma.
+    Comma,
+
+    /// Given value with all numbers being zero or greater.
+    NonNegative(&'static ValueGrammar),
+
+    /// A function with given name, whose arguments match given value.
+    Function(&'static str, &'static ValueGrammar),
+
+    /// `a | b` - exactly one of the values.
+    Alternatives(&'static [ValueGrammar]),
+
+    /// `a b` - all values in given order.
+    Sequence(&'static [ValueGrammar]),
+
+    /// `a?`
+    Optional(&'static ValueGrammar),
+
+    /// `a || b` - one or more of the values, in any order.
+    AnyOrder(&'static [ValueGrammar]),
+
+    /// `a{min,max}` - given value repeated at least `min` and at most `max` times.
+    Repeat(&'static ValueGrammar, usize, usize),
+
+    /// `a [, a]*` - one or more comma-separated values.
+    CommaList(&'static ValueGrammar)
+}
+
+/// Definition of a single (longhand) property.
+#[derive(Debug)]
+pub struct PropertyDefinition {
+    pub name: &'static str,
+    pub grammar: ValueGrammar,
+
+    /// Initial value, written in CSS syntax. Empty if it depends on the user agent
+    /// or on other properties (e.g. `border-top-color` is the value of `color`),
+    /// computed values fall back to the defaults of the layout then.
+    pub initial: &'static str,
+
+    /// Whether children take the property's value from their parent, unless they set it themselves.
+    pub inherited: bool
+}
+
+const fn property(name: &'static str, grammar: ValueGrammar, initial: &'static str, inherited: bool) -> PropertyDefinition {
+    PropertyDefinition {
+        name: name,
+        grammar: grammar,
+        initial: initial,
+        inherited: inherited
+    }
+}
+
+const LENGTH_PERCENTAGE: G = G::Alternatives(&[G::Length, G::Percentage]);
+const LENGTH_PERCENTAGE_AUTO: G = G::Alternatives(&[G::Length, G::Percentage, G::Keyword("auto")]);
+const NON_NEGATIVE_LENGTH_PERCENTAGE: G = G::NonNegative(&LENGTH_PERCENTAGE);
+const NON_NEGATIVE_LENGTH_PERCENTAGE_AUTO: G = G::NonNegative(&LENGTH_PERCENTAGE_AUTO);
+const NON_NEGATIVE_LENGTH_PERCENTAGE_NONE: G = G::Alternatives(&[NON_NEGATIVE_LENGTH_PERCENTAGE, G::Keyword("none")]);
+const COLOR_OR_TRANSPARENT: G = G::Alternatives(&[G::Color, G::Keyword("transparent")]);
+const LENGTH_OR_NORMAL: G = G::Alternatives(&[G::Length, G::Keyword("normal")]);
+const BREAK_BETWEEN: G = G::Keywords(&[
+    "auto", "avoid", "always", "all", "avoid-page", "page", "left", "right", "recto", "verso",
+    "avoid-column", "column", "avoid-region", "region"
+]);
+
+/// https://www.w3.org/TR/css-page-3/#typedef-page-size-page-size
+const PAGE_SIZE: G = G::Keywords(&["a5", "a4", "a3", "b5", "b4", "jis-b5", "jis-b4", "letter", "legal", "ledger"]);
+
+pub const BORDER_STYLE: G = G::Keywords(&["none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge", "inset", "outset"]);
+pub const BORDER_WIDTH: G = G::Alternatives(&[G::Keywords(&["thin", "medium", "thick"]), G::NonNegative(&G::Length)]);
+
+pub const LIST_STYLE_TYPE: G = G::Keywords(&[
+    "disc", "circle", "square", "decimal", "decimal-leading-zero", "lower-roman", "upper-roman",
+    "lower-greek", "lower-latin", "upper-latin", "armenian", "georgian", "lower-alpha", "upper-alpha", "none"
+]);
+
+pub const LIST_STYLE_POSITION: G = G::Keywords(&["inside", "outside"]);
+pub const LIST_STYLE_IMAGE: G = G::Alternatives(&[G::Uri, G::Keyword("none")]);
+
+pub const FONT_STYLE: G = G::Keywords(&["normal", "italic", "oblique"]);
+pub const FONT_VARIANT: G = G::Keywords(&["normal", "small-caps"]);
+pub const FONT_WEIGHT: G = G::Alternatives(&[G::Keywords(&["normal", "bold", "bolder", "lighter"]), G::Integer]);
+pub const FONT_SIZE: G = G::Alternatives(&[
+    G::Keywords(&["xx-small", "x-small", "small", "medium", "large", "x-large", "xx-large", "larger", "smaller"]),
+    NON_NEGATIVE_LENGTH_PERCENTAGE
+]);
+
+pub const LINE_HEIGHT: G = G::Alternatives(&[G::Keyword("normal"), G::NonNegative(&G::Number), NON_NEGATIVE_LENGTH_PERCENTAGE]);
+
+pub const FONT_FAMILY: G = G::CommaList(&G::Alternatives(&[
+    G::FamilyName,
+    G::Keywords(&["serif", "sans-serif", "cursive", "fantasy", "monospace"])
+]));
+
+pub const BACKGROUND_COLOR: G = COLOR_OR_TRANSPARENT;
+pub const BACKGROUND_IMAGE: G = G::Alternatives(&[G::Uri, G::Keyword("none")]);
+pub const BACKGROUND_REPEAT: G = G::Keywords(&["repeat", "repeat-x", "repeat-y", "no-repeat"]);
+pub const BACKGROUND_ATTACHMENT: G = G::Keywords(&["scroll", "fixed"]);
+pub const BACKGROUND_POSITION: G = G::Alternatives(&[
+    G::Sequence(&[
+        G::Alternatives(&[LENGTH_PERCENTAGE, G::Keywords(&["left", "center", "right"])]),
+        G::Optional(&G::Alternatives(&[LENGTH_PERCENTAGE, G::Keywords(&["top", "center", "bottom"])]))
+    ]),
+    G::AnyOrder(&[G::Keywords(&["left", "center", "right"]), G::Keywords(&["top", "center", "bottom"])])
+]);
+
+const COUNTER_CHANGES: G = G::Alternatives(&[
+    G::Repeat(&G::Sequence(&[G::Identifier, G::Optional(&G::Integer)]), 1, usize::MAX),
+    G::Keyword("none")
+]);
+
+const COUNTER: G = G::Function("counter", &G::Sequence(&[G::Identifier, G::Optional(&G::Sequence(&[G::Comma, LIST_STYLE_TYPE]))]));
+const COUNTERS: G = G::Function("counters", &G::Sequence(&[G::Identifier, G::Comma, G::String, G::Optional(&G::Sequence(&[G::Comma, LIST_STYLE_TYPE]))]));
+
+/// Which of the assignments on a page `string()` and `element()` use.
+/// https://www.w3.org/TR/css-gcpm-3/#using-named-strings
+const RUNNING_POLICY: G = G::Optional(&G::Sequence(&[G::Comma, G::Keywords(&["first", "start", "last", "first-except"])]));
+
+const CONTENT_ITEM: G = G::Alternatives(&[
+    G::String,
+    G::Uri,
+    COUNTER,
+    COUNTERS,
+    G::Function("attr", &G::Identifier),
+    G::Function("string", &G::Sequence(&[G::Identifier, RUNNING_POLICY])),
+    G::Function("element", &G::Sequence(&[G::Identifier, RUNNING_POLICY])),
+    G::Keywords(&["open-quote", "close-quote", "no-open-quote", "no-close-quote"])
+]);
+
+/// https://www.w3.org/TR/css-gcpm-3/#setting-named-strings-the-string-set-pro
+const STRING_SET_ITEM: G = G::Alternatives(&[
+    G::String,
+    COUNTER,
+    COUNTERS,
+    G::Function("attr", &G::Identifier),
+    G::Function("content", &G::Optional(&G::Keywords(&["text", "before", "after", "first-letter"])))
+]);
+
+const CLIP_OFFSET: G = G::Alternatives(&[G::Length, G::Keyword("auto")]);
+
+const CURSOR_KEYWORDS: G = G::Keywords(&[
+    "auto", "crosshair", "default", "pointer", "move", "e-resize", "ne-resize", "nw-resize", "n-resize",
+    "se-resize", "sw-resize", "s-resize", "w-resize", "text", "wait", "help", "progress"
+]);
+
+static PROPERTIES: &[PropertyDefinition] = &[
+    property("background-attachment", BACKGROUND_ATTACHMENT, "scroll", false),
+    property("background-color", BACKGROUND_COLOR, "transparent", false),
+    property("background-image", BACKGROUND_IMAGE, "none", false),
+    property("background-position", BACKGROUND_POSITION, "0% 0%", false),
+    property("background-repeat", BACKGROUND_REPEAT, "repeat", false),
+    property("border-collapse", G::Keywords(&["collapse", "separate"]), "separate", true),
+    property("border-spacing", G::NonNegative(&G::Sequence(&[G::Length, G::Optional(&G::Length)])), "0", true),
+    property("border-top-color", COLOR_OR_TRANSPARENT, "", false),
+    property("border-right-color", COLOR_OR_TRANSPARENT, "", false),
+    property("border-bottom-color", COLOR_OR_TRANSPARENT, "", false),
+    property("border-left-color", COLOR_OR_TRANSPARENT, "", false),
+    property("border-top-style", BORDER_STYLE, "none", false),
+    property("border-right-style", BORDER_STYLE, "none", false),
+    property("border-bottom-style", BORDER_STYLE, "none", false),
+    property("border-left-style", BORDER_STYLE, "none", false),
+    property("border-top-width", BORDER_WIDTH, "medium", false),
+    property("border-right-width", BORDER_WIDTH, "medium", false),
+    property("border-bottom-width", BORDER_WIDTH, "medium", false),
+    property("border-left-width", BORDER_WIDTH, "medium", false),
+    property("bottom", LENGTH_PERCENTAGE_AUTO, "auto", false),
+    property("break-after", BREAK_BETWEEN, "auto", false),
+    property("break-before", BREAK_BETWEEN, "auto", false),
+    property("break-inside", G::Keywords(&["auto", "avoid", "avoid-page", "avoid-column", "avoid-region"]), "auto", false),
+    property("caption-side", G::Keywords(&["top", "bottom"]), "top", true),
+    property("clear", G::Keywords(&["none", "left", "right", "both"]), "none", false),
+    property("clip", G::Alternatives(&[
+        G::Function("rect", &G::Sequence(&[
+            CLIP_OFFSET, G::Optional(&G::Comma), CLIP_OFFSET, G::Optional(&G::Comma),
+            CLIP_OFFSET, G::Optional(&G::Comma), CLIP_OFFSET
+        ])),
+        G::Keyword("auto")
+    ]), "auto", false),
+    property("color", G::Color, "black", true),
+    property("content", G::Alternatives(&[G::Keywords(&["normal", "none"]), G::Repeat(&CONTENT_ITEM, 1, usize::MAX)]), "normal", false),
+    property("counter-increment", COUNTER_CHANGES, "none", false),
+    property("counter-reset", COUNTER_CHANGES, "none", false),
+    property("cursor", G::Sequence(&[G::Repeat(&G::Sequence(&[G::Uri, G::Comma]), 0, usize::MAX), CURSOR_KEYWORDS]), "auto", true),
+    property("direction", G::Keywords(&["ltr", "rtl"]), "ltr", true),
+    property("display", G::Keywords(&[
+        "inline", "block", "list-item", "inline-block", "table", "inline-table", "table-row-group",
+        "table-header-group", "table-footer-group", "table-row", "table-column-group", "table-column",
+        "table-cell", "table-caption", "none"
+    ]), "inline", false),
+    property("empty-cells", G::Keywords(&["show", "hide"]), "show", true),
+    property("float", G::Keywords(&["left", "right", "none"]), "none", false),
+    property("font-family", FONT_FAMILY, "", true),
+    property("font-size", FONT_SIZE, "medium", true),
+    property("font-style", FONT_STYLE, "normal", true),
+    property("font-variant", FONT_VARIANT, "normal", true),
+    property("font-weight", FONT_WEIGHT, "normal", true),
+    property("height", NON_NEGATIVE_LENGTH_PERCENTAGE_AUTO, "auto", false),
+    property("left", LENGTH_PERCENTAGE_AUTO, "auto", false),
+    property("letter-spacing", LENGTH_OR_NORMAL, "normal", true),
+    property("line-height", LINE_HEIGHT, "normal", true),
+    property("list-style-image", LIST_STYLE_IMAGE, "none", true),
+    property("list-style-position", LIST_STYLE_POSITION, "outside", true),
+    property("list-style-type", LIST_STYLE_TYPE, "disc", true),
+    property("margin-top", LENGTH_PERCENTAGE_AUTO, "0", false),
+    property("margin-right", LENGTH_PERCENTAGE_AUTO, "0", false),
+    property("margin-bottom", LENGTH_PERCENTAGE_AUTO, "0", false),
+    property("margin-left", LENGTH_PERCENTAGE_AUTO, "0", false),
+    property("max-height", NON_NEGATIVE_LENGTH_PERCENTAGE_NONE, "none", false),
+    property("max-width", NON_NEGATIVE_LENGTH_PERCENTAGE_NONE, "none", false),
+    property("min-height", NON_NEGATIVE_LENGTH_PERCENTAGE, "0", false),
+    property("min-width", NON_NEGATIVE_LENGTH_PERCENTAGE, "0", false),
+    property("opacity", G::Alternatives(&[G::Number, G::Percentage]), "1", false),
+    property("orphans", G::NonNegative(&G::Integer), "2", true),
+    property("outline-color", G::Alternatives(&[G::Color, G::Keyword("invert")]), "invert", false),
+    property("outline-style", BORDER_STYLE, "none", false),
+    property("outline-width", BORDER_WIDTH, "medium", false),
+    property("overflow", G::Keywords(&["visible", "hidden", "scroll", "auto"]), "visible", false),
+    property("padding-top", NON_NEGATIVE_LENGTH_PERCENTAGE, "0", false),
+    property("padding-right", NON_NEGATIVE_LENGTH_PERCENTAGE, "0", false),
+    property("padding-bottom", NON_NEGATIVE_LENGTH_PERCENTAGE, "0", false),
+    property("padding-left", NON_NEGATIVE_LENGTH_PERCENTAGE, "0", false),
+    property("page", G::Alternatives(&[G::Keyword("auto"), G::Identifier]), "auto", false),
+    property("position", G::Alternatives(&[
+        G::Keywords(&["static", "relative", "absolute", "fixed"]),
+        G::Function("running", &G::Identifier)
+    ]), "static", false),
+    property("quotes", G::Alternatives(&[G::Repeat(&G::Sequence(&[G::String, G::String]), 1, usize::MAX), G::Keyword("none")]), "", true),
+    property("right", LENGTH_PERCENTAGE_AUTO, "auto", false),
+    property("size", G::Alternatives(&[
+        G::Keyword("auto"),
+        G::Repeat(&G::NonNegative(&G::Length), 1, 2),
+        G::AnyOrder(&[PAGE_SIZE, G::Keywords(&["portrait", "landscape"])])
+    ]), "auto", false),
+    property("string-set", G::Alternatives(&[
+        G::CommaList(&G::Sequence(&[G::Identifier, G::Repeat(&STRING_SET_ITEM, 1, usize::MAX)])),
+        G::Keyword("none")
+    ]), "none", false),
+    property("table-layout", G::Keywords(&["auto", "fixed"]), "auto", false),
+    property("text-align", G::Keywords(&["left", "right", "center", "justify"]), "left", true),
+    property("text-decoration", G::Alternatives(&[
+        G::Keyword("none"),
+        G::AnyOrder(&[G::Keyword("underline"), G::Keyword("overline"), G::Keyword("line-through"), G::Keyword("blink")])
+    ]), "none", false),
+    property("text-indent", LENGTH_PERCENTAGE, "0", true),
+    property("text-transform", G::Keywords(&["capitalize", "uppercase", "lowercase", "none"]), "none", true),
+    property("top", LENGTH_PERCENTAGE_AUTO, "auto", false),
+    property("unicode-bidi", G::Keywords(&["normal", "embed", "bidi-override"]), "normal", false),
+    property("vertical-align", G::Alternatives(&[
+        G::Keywords(&["baseline", "sub", "super", "top", "text-top", "middle", "bottom", "text-bottom"]),
+        LENGTH_PERCENTAGE
+    ]), "baseline", false),
+    property("visibility", G::Keywords(&["visible", "hidden", "collapse"]), "visible", true),
+    property("white-space", G::Keywords(&["normal", "pre", "nowrap", "pre-wrap", "pre-line"]), "normal", true),
+    property("widows", G::NonNegative(&G::Integer), "2", true),
+    property("width", NON_NEGATIVE_LENGTH_PERCENTAGE_AUTO, "auto", false),
+    property("word-spacing", LENGTH_OR_NORMAL, "normal", true),
+    property("z-index", G::Alternatives(&[G::Keyword("auto"), G::Integer]), "auto", false)
+];
+
+lazy_static! {
+    static ref PROPERTIES_BY_NAME: HashMap<&'static str, &'static PropertyDefinition> = {
+        PROPERTIES.iter().map(|definition| (definition.name, definition)).collect()
+    };
+}
+
+/// Returns the definition of a property by its (lowercase) name.
+pub fn get_property_definition(name: &str) -> Option<&'static PropertyDefinition> {
+    return PROPERTIES_BY_NAME.get(name).cloned();
+}
+
+/// Returns whether given property is inherited by default.
+pub fn is_inherited_property(name: &str) -> bool {
+    return get_property_definition(name).is_some_and(|definition| definition.inherited);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::get_initial_value;
+
+    #[test]
+    fn initial_values_match_the_grammar_of_their_property() {
+        for definition in PROPERTIES {
+            if !definition.initial.is_empty() {
+                assert!(get_initial_value(definition.name).is_some(), "initial value of {} should be valid", definition.name);
+            }
+        }
+
+        assert!(get_initial_value("display").is_some_and(|value| value.is_keyword("inline")));
+        assert!(get_initial_value("font-family").is_none());
+        assert!(get_initial_value("--custom").is_none());
+    }
+}
@@ -0,0 +1,217 @@
+/// A single condition on an element, e.g. its tag name or one of its classes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimpleSelector {
+
+    /// `*`
+    Universal,
+
+    /// `div`
+    Type(String),
+
+    /// `.name`
+    Class(String),
+
+    /// `#name`
+    Id(String),
+
+    /// `[name]`, `[name=value]`, `[name^=value i]` etc.
+    Attribute(AttributeSelector),
+
+    /// `:first-child`, `:nth-child(2n+1)`, `:not(.name)` etc.
+    PseudoClass(PseudoClass)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttributeOperator {
+
+    /// `[name=value]` - the value is exactly `value`.
+    Equals,
+
+    /// `[name~=value]` - the value is a whitespace-separated list containing `value`.
+    Includes,
+
+    /// `[name|=value]` - the value is `value` or starts with `value-`.
+    DashMatch,
+
+    /// `[name^=value]` - the value starts with `value`.
+    Prefix,
+
+    /// `[name$=value]` - the value ends with `value`.
+    Suffix,
+
+    /// `[name*=value]` - the value contains `value`.
+    Substring
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeSelector {
+    pub name: String,
+
+    /// Operator and the value to compare against. `None` if only the presence of the attribute is checked.
+    pub condition: Option<(AttributeOperator, String)>,
+
+    /// Whether the value should be compared case-insensitively (the `i` flag).
+    pub case_insensitive: bool
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PseudoClass {
+    Root,
+    Empty,
+    FirstChild,
+    LastChild,
+    OnlyChild,
+    FirstOfType,
+    LastOfType,
+    OnlyOfType,
+
+    /// `:nth-child(an+b)`, stored as `(a, b)`.
+    NthChild(i32, i32),
+    NthLastChild(i32, i32),
+    NthOfType(i32, i32),
+    NthLastOfType(i32, i32),
+
+    /// `:not(selector list)`
+    Not(Vec<Selector>),
+
+    /// `:is(selector list)`
+    Is(Vec<Selector>)
+}
+
+/// Describes how an element matched by a compound selector relates
+/// to the element matched by the compound selector before it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Combinator {
+
+    /// `a b`
+    Descendant,
+
+    /// `a > b`
+    Child,
+
+    /// `a + b`
+    NextSibling,
+
+    /// `a ~ b`
+    SubsequentSibling
+}
+
+/// A sequence of simple selectors that all have to match the same element, e.g. `div.note#first`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CompoundSelector {
+    pub components: Vec<SimpleSelector>
+}
+
+/// A chain of compound selectors separated by combinators, e.g. `div > .note`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Selector {
+
+    /// Compound selectors in the order they appear in the source.
+    /// The last one is the one the matched element itself has to satisfy.
+    pub compounds: Vec<CompoundSelector>,
+
+    /// Combinators between compound selectors, `combinators[i]` sits
+    /// between `compounds[i]` and `compounds[i + 1]`.
+    pub combinators: Vec<Combinator>
+}
+
+/// A pseudo-class of a page selector, which selects pages by their position in the document.
+/// https://www.w3.org/TR/css-page-3/#page-selectors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PagePseudoClass {
+
+    /// `:first` - the first page of the document.
+    First,
+
+    /// `:left` - a left-hand (verso) page.
+    Left,
+
+    /// `:right` - a right-hand (recto) page.
+    Right,
+
+    /// `:blank` - a page left empty by a forced break.
+    Blank
+}
+
+/// Selects pages of an `@page` rule, e.g. `chapter:first`. A selector without
+/// a name and pseudo-classes, as in a plain `@page` rule, selects all pages.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PageSelector {
+
+    /// Name of the page type, given by the `page` property of elements on the page.
+    pub name: Option<String>,
+    pub pseudo_classes: Vec<PagePseudoClass>
+}
+
+/// Selector's specificity as the number of (ids, classes/attributes/pseudo-classes, types).
+/// https://www.w3.org/TR/selectors-3/#specificity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Specificity(pub u32, pub u32, pub u32);
+
+impl std::ops::Add for Specificity {
+    type Output = Specificity;
+
+    fn add(self, other: Specificity) -> Specificity {
+        Specificity(self.0 + other.0, self.1 + other.1, self.2 + other.2)
+    }
+}
+
+impl SimpleSelector {
+    pub fn specificity(&self) -> Specificity {
+        match self {
+            SimpleSelector::Universal => Specificity(0, 0, 0),
+            SimpleSelector::Type(_) => Specificity(0, 0, 1),
+            SimpleSelector::Id(_) => Specificity(1, 0, 0),
+            SimpleSelector::Class(_) | SimpleSelector::Attribute(_) => Specificity(0, 1, 0),
+
+            // `:not()` and `:is()` themselves do not count, only their most specific argument does.
+            SimpleSelector::PseudoClass(PseudoClass::Not(selectors)) | SimpleSelector::PseudoClass(PseudoClass::Is(selectors)) => {
+                return selectors.iter().map(|selector| selector.specificity()).max().unwrap_or_default();
+            }
+
+            SimpleSelector::PseudoClass(_) => Specificity(0, 1, 0)
+        }
+    }
+}
+
+impl CompoundSelector {
+    pub fn specificity(&self) -> Specificity {
+        return self.components.iter().fold(Specificity::default(), |sum, component| sum + component.specificity());
+    }
+
+    pub fn new() -> Self {
+        Self {
+            components: Vec::new()
+        }
+    }
+}
+
+impl PageSelector {
+
+    /// Returns specificity as the number of (names, `:first` and `:blank`, `:left` and `:right`).
+    /// https://www.w3.org/TR/css-page-3/#cascading-and-page-context
+    pub fn specificity(&self) -> Specificity {
+        let count = |pseudo_classes: &[PagePseudoClass]| {
+            self.pseudo_classes.iter().filter(|pseudo_class| pseudo_classes.contains(pseudo_class)).count() as u32
+        };
+
+        return Specificity(
+            self.name.is_some() as u32,
+            count(&[PagePseudoClass::First, PagePseudoClass::Blank]),
+            count(&[PagePseudoClass::Left, PagePseudoClass::Right])
+        );
+    }
+}
+
+impl Selector {
+    pub fn specificity(&self) -> Specificity {
+        return self.compounds.iter().fold(Specificity::default(), |sum, compound| sum + compound.specificity());
+    }
+
+    pub fn new() -> Self {
+        Self {
+            compounds: Vec::new(),
+            combinators: Vec::new()
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use crate::css::{Selector, CompoundSelector, SimpleSelector, Combinator};
+use crate::css::{AttributeSelector, AttributeOperator, PseudoClass, PageSelector, PagePseudoClass};
+use crate::css::tokenizer::{Token, TokenKind, HashType};
+
+// Selectors are parsed from the tokens of a rule's prelude, as described here:
+// https://www.w3.org/TR/selectors-3/#w3cselgrammar
+//
+// Every function returns `None` as soon as something unexpected is found,
+// because a rule with an invalid selector has to be ignored entirely.
+
+/// Parses a comma-separated list of selectors.
+/// Returns `None` if any of the selectors is invalid.
+pub fn parse_selector_list(tokens: &[Token]) -> Option<Vec<Selector>> {
+    let mut selectors = Vec::<Selector>::new();
+
+    for selector_tokens in split_top_level_commas(tokens) {
+        selectors.push(parse_selector(selector_tokens)?);
+    }
+
+    return Some(selectors);
+}
+
+pub fn parse_selector(tokens: &[Token]) -> Option<Selector> {
+    let mut selector = Selector::new();
+    let mut compound = CompoundSelector::new();
+    let mut pending_combinator: Option<Combinator> = None;
+    let mut idx = 0;
+
+    while idx < tokens.len() {
+        let token = &tokens[idx];
+
+        if token.kind == TokenKind::Whitespace {
+            if !compound.components.is_empty() && pending_combinator.is_none() {
+                pending_combinator = Some(Combinator::Descendant);
+            }
+
+            idx += 1;
+            continue;
+        }
+
+        if let Some(combinator) = get_combinator(token) {
+
+            // A combinator needs a compound selector on both sides,
+            // but may be surrounded by whitespace.
+            if compound.components.is_empty() {
+                return None;
+            }
+
+            match pending_combinator {
+                None | Some(Combinator::Descendant) => pending_combinator = Some(combinator),
+                _ => return None
+            }
+
+            idx += 1;
+            continue;
+        }
+
+        if let Some(combinator) = pending_combinator.take() {
+            selector.compounds.push(compound);
+            selector.combinators.push(combinator);
+            compound = CompoundSelector::new();
+        }
+
+        let component = parse_simple_selector(tokens, &mut idx, compound.components.is_empty())?;
+        compound.components.push(component);
+    }
+
+    match pending_combinator {
+        None | Some(Combinator::Descendant) => {}
+        _ => return None
+    }
+
+    if compound.components.is_empty() {
+        return None;
+    }
+
+    selector.compounds.push(compound);
+    return Some(selector);
+}
+
+/// Parses a single simple selector starting at `idx` and moves `idx` past it.
+/// Type and universal selectors are only allowed at the start of a compound selector.
+fn parse_simple_selector(tokens: &[Token], idx: &mut usize, is_first: bool) -> Option<SimpleSelector> {
+    let token = &tokens[*idx];
+    *idx += 1;
+
+    match token.kind {
+        TokenKind::Identifier if is_first => {
+            return Some(SimpleSelector::Type(token.value.to_ascii_lowercase()));
+        }
+
+        TokenKind::Delimeter if token.value == "*" && is_first => {
+            return Some(SimpleSelector::Universal);
+        }
+
+        TokenKind::Delimeter if token.value == "." => {
+            let name = tokens.get(*idx).filter(|t| t.kind == TokenKind::Identifier)?;
+            *idx += 1;
+            return Some(SimpleSelector::Class(name.value.clone()));
+        }
+
+        // `#1a` is not an ID selector, since an identifier cannot start with a digit.
+        TokenKind::Hash if token.hash_type == HashType::Id => {
+            return Some(SimpleSelector::Id(token.value.clone()));
+        }
+
+        TokenKind::SquareParenthesisOpen => {
+            return parse_attribute_selector(tokens, idx).map(SimpleSelector::Attribute);
+        }
+
+        TokenKind::Colon => {
+            return parse_pseudo_class(tokens, idx).map(SimpleSelector::PseudoClass);
+        }
+
+        _ => {
+            return None;
+        }
+    }
+}
+
+/// Parses the inside of `[...]`, with `idx` pointing just past the opening bracket.
+fn parse_attribute_selector(tokens: &[Token], idx: &mut usize) -> Option<AttributeSelector> {
+    skip_whitespace(tokens, idx);
+    let name = tokens.get(*idx).filter(|t| t.kind == TokenKind::Identifier)?.value.to_ascii_lowercase();
+    *idx += 1;
+    skip_whitespace(tokens, idx);
+
+    let mut condition = None;
+    let mut case_insensitive = false;
+
+    if tokens.get(*idx)?.kind == TokenKind::Delimeter {
+        let operator = match tokens[*idx].value.as_str() {
+            "=" => AttributeOperator::Equals,
+            "~" => AttributeOperator::Includes,
+            "|" => AttributeOperator::DashMatch,
+            "^" => AttributeOperator::Prefix,
+            "$" => AttributeOperator::Suffix,
+            "*" => AttributeOperator::Substring,
+            _ => return None
+        };
+
+        *idx += 1;
+
+        // All operators except `=` consist of two delimiters, e.g. `^` and `=`.
+        if operator != AttributeOperator::Equals {
+            tokens.get(*idx).filter(|t| t.kind == TokenKind::Delimeter && t.value == "=")?;
+            *idx += 1;
+        }
+
+        skip_whitespace(tokens, idx);
+        let value = tokens.get(*idx).filter(|t| t.kind == TokenKind::Identifier || t.kind == TokenKind::String)?;
+        *idx += 1;
+        skip_whitespace(tokens, idx);
+
+        if let Some(flag) = tokens.get(*idx).filter(|t| t.kind == TokenKind::Identifier) {
+            match flag.value.to_ascii_lowercase().as_str() {
+                "i" => case_insensitive = true,
+                "s" => case_insensitive = false,
+                _ => return None
+            }
+
+            *idx += 1;
+            skip_whitespace(tokens, idx);
+        }
+
+        condition = Some((operator, value.value.clone()));
+    }
+
+    tokens.get(*idx).filter(|t| t.kind == TokenKind::SquareParenthesisClose)?;
+    *idx += 1;
+
+    return Some(AttributeSelector {
+        name: name,
+        condition: condition,
+        case_insensitive: case_insensitive
+    });
+}
+
+/// Parses a pseudo-class, with `idx` pointing just past the colon.
+fn parse_pseudo_class(tokens: &[Token], idx: &mut usize) -> Option<PseudoClass> {
+    let token = tokens.get(*idx)?;
+    *idx += 1;
+
+    match token.kind {
+        TokenKind::Identifier => {
+            match token.value.to_ascii_lowercase().as_str() {
+                "root" => Some(PseudoClass::Root),
+                "empty" => Some(PseudoClass::Empty),
+                "first-child" => Some(PseudoClass::FirstChild),
+                "last-child" => Some(PseudoClass::LastChild),
+                "only-child" => Some(PseudoClass::OnlyChild),
+                "first-of-type" => Some(PseudoClass::FirstOfType),
+                "last-of-type" => Some(PseudoClass::LastOfType),
+                "only-of-type" => Some(PseudoClass::OnlyOfType),
+                _ => None
+            }
+        }
+
+        TokenKind::Function => {
+            let arguments = read_function_arguments(tokens, idx)?;
+
+            match token.value.to_ascii_lowercase().as_str() {
+                "nth-child" => parse_nth(arguments).map(|(a, b)| PseudoClass::NthChild(a, b)),
+                "nth-last-child" => parse_nth(arguments).map(|(a, b)| PseudoClass::NthLastChild(a, b)),
+                "nth-of-type" => parse_nth(arguments).map(|(a, b)| PseudoClass::NthOfType(a, b)),
+                "nth-last-of-type" => parse_nth(arguments).map(|(a, b)| PseudoClass::NthLastOfType(a, b)),
+                "not" => parse_selector_list(arguments).map(PseudoClass::Not),
+                "is" => parse_selector_list(arguments).map(PseudoClass::Is),
+                _ => None
+            }
+        }
+
+        _ => None
+    }
+}
+
+/// Returns the tokens between a function token and its closing parenthesis,
+/// with `idx` pointing just past the function token. Moves `idx` past the closing parenthesis.
+fn read_function_arguments<'a>(tokens: &'a [Token], idx: &mut usize) -> Option<&'a [Token]> {
+    let start = *idx;
+    let mut depth = 1;
+
+    while *idx < tokens.len() {
+        match tokens[*idx].kind {
+            TokenKind::Function | TokenKind::ParenthesisOpen => depth += 1,
+            TokenKind::ParenthesisClose => {
+                depth -= 1;
+                if depth == 0 {
+                    *idx += 1;
+                    return Some(&tokens[start..*idx - 1]);
+                }
+            }
+
+            _ => {}
+        }
+
+        *idx += 1;
+    }
+
+    return None;
+}
+
+/// Parses the `an+b` argument of `:nth-child()` and similar pseudo-classes into `(a, b)`.
+/// https://www.w3.org/TR/css-syntax-3/#anb-microsyntax
+fn parse_nth(tokens: &[Token]) -> Option<(i32, i32)> {
+
+    // Tokens of `an+b` vary a lot, e.g. `2n-1` is a single dimension while `-n + 3`
+    // is an identifier, a delimiter and a number. It's simpler to parse the text back.
+    let mut text = String::new();
+    for token in tokens {
+        match token.kind {
+            TokenKind::Whitespace => {}
+            TokenKind::Identifier | TokenKind::Integer | TokenKind::Delimeter => text.push_str(&token.value),
+            TokenKind::Dimension => {
+                text.push_str(&token.value);
+                text.push_str(&token.unit);
+            }
+
+            _ => return None
+        }
+    }
+
+    let text = text.to_ascii_lowercase();
+    match text.as_str() {
+        "odd" => return Some((2, 1)),
+        "even" => return Some((2, 0)),
+        _ => {}
+    }
+
+    match text.find('n') {
+        Some(n_pos) => {
+            let a = match &text[..n_pos] {
+                "" | "+" => 1,
+                "-" => -1,
+                a => a.parse().ok()?
+            };
+
+            let b_text = &text[n_pos + 1..];
+            let b = if b_text.is_empty() {
+                0
+            } else if b_text.starts_with('+') || b_text.starts_with('-') {
+                b_text.parse().ok()?
+            } else {
+                return None;
+            };
+
+            return Some((a, b));
+        }
+
+        None => {
+            return text.parse().ok().map(|b| (0, b));
+        }
+    }
+}
+
+fn get_combinator(token: &Token) -> Option<Combinator> {
+    if token.kind != TokenKind::Delimeter {
+        return None;
+    }
+
+    match token.value.as_str() {
+        ">" => Some(Combinator::Child),
+        "+" => Some(Combinator::NextSibling),
+        "~" => Some(Combinator::SubsequentSibling),
+        _ => None
+    }
+}
+
+/// Parses the comma-separated page selectors of an `@page` rule. An empty prelude selects all pages.
+/// Returns `None` if any of the selectors is invalid.
+/// https://www.w3.org/TR/css-page-3/#syntax-page-selector
+pub fn parse_page_selector_list(tokens: &[Token]) -> Option<Vec<PageSelector>> {
+    if tokens.iter().all(|token| token.kind == TokenKind::Whitespace) {
+        return Some(vec![PageSelector::default()]);
+    }
+
+    let mut selectors = Vec::<PageSelector>::new();
+
+    for selector_tokens in split_top_level_commas(tokens) {
+        selectors.push(parse_page_selector(selector_tokens)?);
+    }
+
+    return Some(selectors);
+}
+
+/// Parses a page selector like `chapter:first:right`, which must not contain whitespace except around it.
+fn parse_page_selector(tokens: &[Token]) -> Option<PageSelector> {
+    let mut selector = PageSelector::default();
+    let mut idx = 0;
+
+    skip_whitespace(tokens, &mut idx);
+
+    if let Some(token) = tokens.get(idx).filter(|token| token.kind == TokenKind::Identifier) {
+        selector.name = Some(token.value.clone());
+        idx += 1;
+    }
+
+    while tokens.get(idx).is_some_and(|token| token.kind == TokenKind::Colon) {
+        let name = tokens.get(idx + 1).filter(|token| token.kind == TokenKind::Identifier)?;
+        let pseudo_class = match name.value.to_ascii_lowercase().as_str() {
+            "first" => PagePseudoClass::First,
+            "left" => PagePseudoClass::Left,
+            "right" => PagePseudoClass::Right,
+            "blank" => PagePseudoClass::Blank,
+            _ => return None
+        };
+
+        selector.pseudo_classes.push(pseudo_class);
+        idx += 2;
+    }
+
+    skip_whitespace(tokens, &mut idx);
+
+    if idx < tokens.len() || (selector.name.is_none() && selector.pseudo_classes.is_empty()) {
+        return None;
+    }
+
+    return Some(selector);
+}
+
+/// Splits tokens on commas that are not nested inside a function, e.g. `:not(a, b)`.
+fn split_top_level_commas(tokens: &[Token]) -> Vec<&[Token]> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (idx, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::Function | TokenKind::ParenthesisOpen => depth += 1,
+            TokenKind::ParenthesisClose => depth -= 1,
+            TokenKind::Comma if depth == 0 => {
+                parts.push(&tokens[start..idx]);
+                start = idx + 1;
+            }
+
+            _ => {}
+        }
+    }
+
+    parts.push(&tokens[start..]);
+    return parts;
+}
+
+fn skip_whitespace(tokens: &[Token], idx: &mut usize) {
+    while tokens.get(*idx).is_some_and(|t| t.kind == TokenKind::Whitespace) {
+        *idx += 1;
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use crate::css::{PrimitiveValue, CssWideKeyword};
+use crate::css::property_registry::get_property_definition;
+use crate::css::tokenizer::{Token, TokenKind};
+use crate::css::value_parser::{parse_value, split_component_values};
+
+// Shorthand properties of CSS 2.1, which set several longhand properties at once:
+// https://www.w3.org/TR/CSS21/propidx.html
+
+/// How a shorthand's value is split into values of its longhands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShorthandKind {
+
+    /// One to four values for the top, right, bottom and left side, e.g. `margin: 1px 2px`.
+    /// A missing right side copies the top, a missing bottom copies the top
+    /// and a missing left copies the right.
+    BoxSides,
+
+    /// Values of the longhands in any order, each at most once, e.g. `background: red url(a.png)`.
+    AnyOrder,
+
+    /// Like `AnyOrder` with the values of the first three longhands (width, style and color),
+    /// which are then repeated for every side.
+    Border,
+
+    /// `[ <font-style> || <font-variant> || <font-weight> ]? <font-size> [ / <line-height> ]? <font-family>`
+    Font,
+
+    /// A `page-break-*` property of CSS 2.1, which is a legacy alias of a `break-*` property.
+    /// It accepts fewer keywords and its `always` means `page`.
+    /// https://www.w3.org/TR/css-break-3/#page-break-properties
+    PageBreak
+}
+
+/// Definition of a shorthand property.
+#[derive(Debug)]
+pub struct ShorthandDefinition {
+    pub name: &'static str,
+
+    /// All longhands the shorthand sets. Those not given in the shorthand's value
+    /// are reset to their initial values.
+    pub longhands: &'static [&'static str],
+    pub kind: ShorthandKind
+}
+
+const fn shorthand(name: &'static str, longhands: &'static [&'static str], kind: ShorthandKind) -> ShorthandDefinition {
+    ShorthandDefinition {
+        name: name,
+        longhands: longhands,
+        kind: kind
+    }
+}
+
+static SHORTHANDS: &[ShorthandDefinition] = &[
+    shorthand("background", &["background-color", "background-image", "background-repeat", "background-attachment", "background-position"], ShorthandKind::AnyOrder),
+    shorthand("border", &[
+        "border-top-width", "border-top-style", "border-top-color",
+        "border-right-width", "border-right-style", "border-right-color",
+        "border-bottom-width", "border-bottom-style", "border-bottom-color",
+        "border-left-width", "border-left-style", "border-left-color"
+    ], ShorthandKind::Border),
+    shorthand("border-top", &["border-top-width", "border-top-style", "border-top-color"], ShorthandKind::AnyOrder),
+    shorthand("border-right", &["border-right-width", "border-right-style", "border-right-color"], ShorthandKind::AnyOrder),
+    shorthand("border-bottom", &["border-bottom-width", "border-bottom-style", "border-bottom-color"], ShorthandKind::AnyOrder),
+    shorthand("border-left", &["border-left-width", "border-left-style", "border-left-color"], ShorthandKind::AnyOrder),
+    shorthand("border-color", &["border-top-color", "border-right-color", "border-bottom-color", "border-left-color"], ShorthandKind::BoxSides),
+    shorthand("border-style", &["border-top-style", "border-right-style", "border-bottom-style", "border-left-style"], ShorthandKind::BoxSides),
+    shorthand("border-width", &["border-top-width", "border-right-width", "border-bottom-width", "border-left-width"], ShorthandKind::BoxSides),
+    shorthand("font", &["font-style", "font-variant", "font-weight", "font-size", "line-height", "font-family"], ShorthandKind::Font),
+    shorthand("list-style", &["list-style-type", "list-style-position", "list-style-image"], ShorthandKind::AnyOrder),
+    shorthand("margin", &["margin-top", "margin-right", "margin-bottom", "margin-left"], ShorthandKind::BoxSides),
+    shorthand("outline", &["outline-color", "outline-style", "outline-width"], ShorthandKind::AnyOrder),
+    shorthand("padding", &["padding-top", "padding-right", "padding-bottom", "padding-left"], ShorthandKind::BoxSides),
+    shorthand("page-break-after", &["break-after"], ShorthandKind::PageBreak),
+    shorthand("page-break-before", &["break-before"], ShorthandKind::PageBreak),
+    shorthand("page-break-inside", &["break-inside"], ShorthandKind::PageBreak)
+];
+
+lazy_static! {
+    static ref SHORTHANDS_BY_NAME: HashMap<&'static str, &'static ShorthandDefinition> = {
+        SHORTHANDS.iter().map(|definition| (definition.name, definition)).collect()
+    };
+}
+
+/// Returns the definition of a shorthand property by its (lowercase) name.
+pub fn get_shorthand_definition(name: &str) -> Option<&'static ShorthandDefinition> {
+    return SHORTHANDS_BY_NAME.get(name).cloned();
+}
+
+/// Splits a shorthand's value into values of its longhands, in the order of `definition.longhands`.
+/// Longhands that are not given are set to `initial`. Returns `None` if the value is invalid.
+pub fn expand_shorthand(definition: &ShorthandDefinition, tokens: &[Token]) -> Option<Vec<(&'static str, PrimitiveValue)>> {
+    let components = split_component_values(tokens);
+    if components.is_empty() {
+        return None;
+    }
+
+    let values = match definition.kind {
+        ShorthandKind::BoxSides => expand_box_sides(definition.longhands, &components)?,
+        ShorthandKind::AnyOrder => expand_any_order(definition.longhands, &components)?,
+        ShorthandKind::Border => {
+            let side_values = expand_any_order(&definition.longhands[..3], &components)?;
+            (0..definition.longhands.len()).map(|idx| side_values[idx % 3].clone()).collect()
+        }
+
+        ShorthandKind::Font => expand_font(definition.longhands, &components)?,
+        ShorthandKind::PageBreak => vec![Some(expand_page_break(definition.longhands[0], &components)?)]
+    };
+
+    let result = definition.longhands.iter()
+        .zip(values)
+        .map(|(longhand, value)| (*longhand, value.unwrap_or_else(|| PrimitiveValue::from_css_wide_keyword(CssWideKeyword::Initial))))
+        .collect();
+
+    return Some(result);
+}
+
+/// Parses a value of given longhand property.
+fn parse_longhand_value(longhand: &str, tokens: &[Token]) -> Option<PrimitiveValue> {
+    let definition = get_property_definition(longhand)?;
+    return parse_value(&definition.grammar, tokens);
+}
+
+fn expand_box_sides(longhands: &[&str], components: &[Vec<Token>]) -> Option<Vec<Option<PrimitiveValue>>> {
+    if components.len() > 4 {
+        return None;
+    }
+
+    let mut values = Vec::<PrimitiveValue>::new();
+    for (longhand, component) in longhands.iter().zip(components) {
+        values.push(parse_longhand_value(longhand, component)?);
+    }
+
+    let top = values[0].clone();
+    let right = values.get(1).cloned().unwrap_or_else(|| top.clone());
+    let bottom = values.get(2).cloned().unwrap_or_else(|| top.clone());
+    let left = values.get(3).cloned().unwrap_or_else(|| right.clone());
+
+    return Some(vec![Some(top), Some(right), Some(bottom), Some(left)]);
+}
+
+/// Assigns components to the first longhand, in order of `longhands`, that accepts them.
+/// A longhand takes as many consecutive components as it can, e.g. `background-position` can take two.
+/// If the rest of the components cannot be assigned then, the next longhand is tried, so that
+/// `list-style: none square` gives `none` to `list-style-image` once `square` needs `list-style-type`.
+fn expand_any_order(longhands: &[&str], components: &[Vec<Token>]) -> Option<Vec<Option<PrimitiveValue>>> {
+    let mut values: Vec<Option<PrimitiveValue>> = vec![None; longhands.len()];
+    if !assign_any_order(longhands, components, &mut values) {
+        return None;
+    }
+
+    return Some(values);
+}
+
+/// Assigns `components` to the longhands that have no value yet, see `expand_any_order`.
+fn assign_any_order(longhands: &[&str], components: &[Vec<Token>], values: &mut [Option<PrimitiveValue>]) -> bool {
+    if components.is_empty() {
+        return true;
+    }
+
+    for (longhand_idx, longhand) in longhands.iter().enumerate() {
+        if values[longhand_idx].is_some() {
+            continue;
+        }
+
+        for end in (1..=components.len()).rev() {
+            if let Some(value) = parse_longhand_value(longhand, &components[..end].concat()) {
+                values[longhand_idx] = Some(value);
+                if assign_any_order(longhands, &components[end..], values) {
+                    return true;
+                }
+
+                values[longhand_idx] = None;
+            }
+        }
+    }
+
+    return false;
+}
+
+fn expand_font(longhands: &[&str], components: &[Vec<Token>]) -> Option<Vec<Option<PrimitiveValue>>> {
+    let mut values: Vec<Option<PrimitiveValue>> = vec![None; longhands.len()];
+    let mut idx = 0;
+
+    // font-style, font-variant and font-weight come first, in any order. `normal`
+    // is valid for all three, so it just takes one of the places and sets nothing.
+    'style: for _ in 0..3 {
+        let component = components.get(idx)?;
+        if is_identifier(component, "normal") {
+            idx += 1;
+            continue;
+        }
+
+        for longhand_idx in 0..3 {
+            if values[longhand_idx].is_none() {
+                if let Some(value) = parse_longhand_value(longhands[longhand_idx], component) {
+                    values[longhand_idx] = Some(value);
+                    idx += 1;
+                    continue 'style;
+                }
+            }
+        }
+
+        break;
+    }
+
+    values[3] = Some(parse_longhand_value(longhands[3], components.get(idx)?)?);
+    idx += 1;
+
+    if components.get(idx).is_some_and(|component| is_delimeter(component, "/")) {
+        values[4] = Some(parse_longhand_value(longhands[4], components.get(idx + 1)?)?);
+        idx += 2;
+    }
+
+    values[5] = Some(parse_longhand_value(longhands[5], &components.get(idx..)?.concat())?);
+    return Some(values);
+}
+
+fn expand_page_break(longhand: &str, components: &[Vec<Token>]) -> Option<PrimitiveValue> {
+    let keywords: &[&str] = if longhand == "break-inside" { &["auto", "avoid"] } else { &["auto", "always", "avoid", "left", "right"] };
+
+    let keyword = match components {
+        [component] => keywords.iter().find(|keyword| is_identifier(component, keyword))?,
+        _ => return None
+    };
+
+    let value = if *keyword == "always" { "page" } else { keyword };
+    return Some(PrimitiveValue::from_identifier(value));
+}
+
+fn is_identifier(component: &[Token], name: &str) -> bool {
+    return matches!(component, [token] if token.kind == TokenKind::Identifier && token.value.eq_ignore_ascii_case(name));
+}
+
+fn is_delimeter(component: &[Token], value: &str) -> bool {
+    return matches!(component, [token] if token.kind == TokenKind::Delimeter && token.value == value);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::css::parser::parse_property_value;
+    use crate::css::tokenizer::{tokenize, TokenKind};
+
+    /// Expands given shorthand declaration, returning each longhand with its value as text.
+    fn expand(name: &str, value: &str) -> Option<Vec<(String, String)>> {
+        let tokens: Vec<_> = tokenize(value).into_iter().filter(|token| token.kind != TokenKind::EndOfInput).collect();
+        return parse_property_value(name, &tokens).map(|values| values.into_iter()
+            .map(|(longhand, value)| (longhand, value.as_string().clone()))
+            .collect());
+    }
+
+    /// Returns the values `expand` gives for given shorthand declaration, without the longhands' names.
+    fn expand_values(name: &str, value: &str) -> Option<Vec<String>> {
+        return expand(name, value).map(|longhands| longhands.into_iter().map(|(_, value)| value).collect());
+    }
+
+    #[test]
+    fn box_sides() {
+        assert_eq!(expand("margin", "1pt"), Some(vec![
+            (String::from("margin-top"), String::from("1pt")),
+            (String::from("margin-right"), String::from("1pt")),
+            (String::from("margin-bottom"), String::from("1pt")),
+            (String::from("margin-left"), String::from("1pt"))
+        ]));
+
+        assert_eq!(expand_values("padding", "1pt 2pt").unwrap(), vec!["1pt", "2pt", "1pt", "2pt"]);
+        assert_eq!(expand_values("margin", "1pt auto 3pt").unwrap(), vec!["1pt", "auto", "3pt", "auto"]);
+        assert_eq!(expand_values("border-style", "solid dashed dotted none").unwrap(), vec!["solid", "dashed", "dotted", "none"]);
+
+        assert_eq!(expand("margin", "1pt 2pt 3pt 4pt 5pt"), None);
+        assert_eq!(expand("padding", "1pt red"), None);
+    }
+
+    #[test]
+    fn border() {
+        let values = expand_values("border", "red 2pt dashed").unwrap();
+        assert_eq!(values.len(), 12);
+        assert!(values.chunks(3).all(|side| side == ["2pt", "dashed", "red"]));
+
+        // Values that are not given are reset.
+        let values = expand_values("border-top", "solid").unwrap();
+        assert_eq!(values, vec!["initial", "solid", "initial"]);
+
+        assert_eq!(expand("border", "solid solid"), None);
+        assert_eq!(expand("border", "2pt 3pt"), None);
+    }
+
+    #[test]
+    fn font() {
+        assert_eq!(expand("font", "bold 12pt/1.4 Helvetica"), Some(vec![
+            (String::from("font-style"), String::from("initial")),
+            (String::from("font-variant"), String::from("initial")),
+            (String::from("font-weight"), String::from("bold")),
+            (String::from("font-size"), String::from("12pt")),
+            (String::from("line-height"), String::from("1.4")),
+            (String::from("font-family"), String::from("\"Helvetica\""))
+        ]));
+
+        assert_eq!(expand_values("font", "italic normal small-caps 10pt \"Times New Roman\", serif").unwrap(),
+            vec!["italic", "small-caps", "initial", "10pt", "initial", "\"Times New Roman\", serif"]);
+
+        // The size and the family are required.
+        assert_eq!(expand("font", "bold Helvetica"), None);
+        assert_eq!(expand("font", "12pt"), None);
+        assert_eq!(expand("font", "12pt/ Helvetica"), None);
+    }
+
+    #[test]
+    fn any_order() {
+        assert_eq!(expand_values("background", "url(a.png) no-repeat red").unwrap(), vec!["red", "a.png", "no-repeat", "initial", "initial"]);
+        assert_eq!(expand_values("background", "left top blue").unwrap(), vec!["blue", "initial", "initial", "initial", "left top"]);
+        assert_eq!(expand("background", "red blue"), None);
+
+        // `none` goes to whichever of `list-style-type` and `list-style-image` it can.
+        assert_eq!(expand_values("list-style", "none").unwrap(), vec!["none", "initial", "initial"]);
+        assert_eq!(expand_values("list-style", "none square").unwrap(), vec!["square", "initial", "none"]);
+        assert_eq!(expand_values("list-style", "inside none").unwrap(), vec!["none", "inside", "initial"]);
+        assert_eq!(expand_values("list-style", "none none").unwrap(), vec!["none", "initial", "none"]);
+        assert_eq!(expand("list-style", "none none none"), None);
+    }
+
+    #[test]
+    fn page_break_aliases() {
+        assert_eq!(expand("page-break-before", "always"), Some(vec![(String::from("break-before"), String::from("page"))]));
+        assert_eq!(expand("page-break-after", "left"), Some(vec![(String::from("break-after"), String::from("left"))]));
+        assert_eq!(expand("page-break-inside", "avoid"), Some(vec![(String::from("break-inside"), String::from("avoid"))]));
+
+        // Only the keywords of CSS 2.1 are allowed.
+        assert_eq!(expand("page-break-before", "page"), None);
+        assert_eq!(expand("page-break-inside", "always"), None);
+        assert_eq!(expand("page-break-after", "always always"), None);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::css::PropertyDeclaration;
+use crate::css::{Selector, PageSelector};
+use crate::css::MediaQueryList;
+
+/// A list of rules parsed from a `<style>` element or a CSS file.
+#[derive(Debug, Clone, Default)]
+pub struct Stylesheet {
+
+    /// Stylesheets imported by `@import` rules, which have to be loaded before the `rules` apply.
+    pub imports: Vec<ImportRule>,
+    pub rules: Vec<StyleRule>,
+    pub page_rules: Vec<PageRule>
+}
+
+/// A set of declarations applied to all elements matching any of the selectors.
+#[derive(Debug, Clone)]
+pub struct StyleRule {
+    pub selectors: Vec<Selector>,
+    pub declarations: Vec<PropertyDeclaration>,
+
+    /// Media query lists of the `@media` rules (and `@import` rules) the rule is nested in.
+    /// The rule only applies if all of them match.
+    pub media: Vec<MediaQueryList>
+}
+
+/// `@page :first { margin: 2cm }`, a set of declarations applied to all pages matching any of the selectors.
+/// https://www.w3.org/TR/css-page-3/#at-page-rule
+#[derive(Debug, Clone)]
+pub struct PageRule {
+    pub selectors: Vec<PageSelector>,
+    pub declarations: Vec<PropertyDeclaration>,
+
+    /// Rules for the page's margin boxes, like `@top-center { content: "Title" }`.
+    pub margin_rules: Vec<MarginRule>,
+
+    /// Media query lists of the `@media` rules (and `@import` rules) the rule is nested in.
+    pub media: Vec<MediaQueryList>
+}
+
+/// A set of declarations applied to one margin box of the pages an `@page` rule applies to.
+/// https://www.w3.org/TR/css-page-3/#margin-at-rules
+#[derive(Debug, Clone)]
+pub struct MarginRule {
+    pub position: MarginBoxPosition,
+    pub declarations: Vec<PropertyDeclaration>
+}
+
+/// One of the sixteen boxes in the margins of a page, named after the at-rule that styles it.
+/// https://www.w3.org/TR/css-page-3/#margin-boxes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MarginBoxPosition {
+    TopLeftCorner,
+    TopLeft,
+    TopCenter,
+    TopRight,
+    TopRightCorner,
+    RightTop,
+    RightMiddle,
+    RightBottom,
+    BottomRightCorner,
+    BottomRight,
+    BottomCenter,
+    BottomLeft,
+    BottomLeftCorner,
+    LeftBottom,
+    LeftMiddle,
+    LeftTop
+}
+
+/// `@import url(...) media-list;`
+#[derive(Debug, Clone)]
+pub struct ImportRule {
+
+    /// Path of the imported stylesheet, relative to the importing one.
+    pub url: String,
+
+    /// Media queries the imported stylesheet applies to. Empty if it applies to all media.
+    pub media: MediaQueryList
+}
+
+impl MarginBoxPosition {
+
+    /// All margin boxes, clockwise from the top left corner.
+    pub const ALL: [MarginBoxPosition; 16] = [
+        MarginBoxPosition::TopLeftCorner,
+        MarginBoxPosition::TopLeft,
+        MarginBoxPosition::TopCenter,
+        MarginBoxPosition::TopRight,
+        MarginBoxPosition::TopRightCorner,
+        MarginBoxPosition::RightTop,
+        MarginBoxPosition::RightMiddle,
+        MarginBoxPosition::RightBottom,
+        MarginBoxPosition::BottomRightCorner,
+        MarginBoxPosition::BottomRight,
+        MarginBoxPosition::BottomCenter,
+        MarginBoxPosition::BottomLeft,
+        MarginBoxPosition::BottomLeftCorner,
+        MarginBoxPosition::LeftBottom,
+        MarginBoxPosition::LeftMiddle,
+        MarginBoxPosition::LeftTop
+    ];
+
+    /// Returns the name of the at-rule for this margin box, without the `@`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MarginBoxPosition::TopLeftCorner => "top-left-corner",
+            MarginBoxPosition::TopLeft => "top-left",
+            MarginBoxPosition::TopCenter => "top-center",
+            MarginBoxPosition::TopRight => "top-right",
+            MarginBoxPosition::TopRightCorner => "top-right-corner",
+            MarginBoxPosition::RightTop => "right-top",
+            MarginBoxPosition::RightMiddle => "right-middle",
+            MarginBoxPosition::RightBottom => "right-bottom",
+            MarginBoxPosition::BottomRightCorner => "bottom-right-corner",
+            MarginBoxPosition::BottomRight => "bottom-right",
+            MarginBoxPosition::BottomCenter => "bottom-center",
+            MarginBoxPosition::BottomLeft => "bottom-left",
+            MarginBoxPosition::BottomLeftCorner => "bottom-left-corner",
+            MarginBoxPosition::LeftBottom => "left-bottom",
+            MarginBoxPosition::LeftMiddle => "left-middle",
+            MarginBoxPosition::LeftTop => "left-top"
+        }
+    }
+
+    /// Returns the margin box with given at-rule name, without the `@`. Names are case-insensitive.
+    pub fn from_name(name: &str) -> Option<MarginBoxPosition> {
+        return MarginBoxPosition::ALL.iter().find(|position| position.name().eq_ignore_ascii_case(name)).copied();
+    }
+}
+
+impl Stylesheet {
+
+    /// Makes all rules of this stylesheet apply only if `media` matches, as if they were in an `@media` block.
+    /// Used for stylesheets with a `media` attribute and for those imported by an `@import` rule with a media list.
+    pub fn with_media(mut self, media: MediaQueryList) -> Self {
+        if media == MediaQueryList::default() {
+            return self;
+        }
+
+        for rule in self.rules.iter_mut() {
+            rule.media.insert(0, media.clone());
+        }
+
+        for rule in self.page_rules.iter_mut() {
+            rule.media.insert(0, media.clone());
+        }
+
+        return self;
+    }
+
+    pub fn new() -> Self {
+        Self {
+            imports: Vec::new(),
+            rules: Vec::new(),
+            page_rules: Vec::new()
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use crate::css::tokenizer::{Token, TokenKind};
 
+#[derive(Default)]
 pub struct ParserTokenBuffer {
     pos: usize,
     len: usize,
@@ -7,31 +8,41 @@ pub struct ParserTokenBuffer {
 }
 
 impl ParserTokenBuffer {
+
+    /// Consumes the next token. The buffer is not an `Iterator`, as the token is borrowed from it.
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> &Token {
         let token = &self.tokens[self.pos];
         self.pos += 1;
-        return &token;
+        return token;
     }
 
-    pub fn skip_until(&mut self, kind: TokenKind) {
-        while !self.is_out_of_bounds() {
-            let token = self.next();
-            if token.kind == kind {
-                self.pos -= 1;
-                break;
-            }
+    /// Consumes the next token if it is of given kind.
+    /// Returns whether the token was consumed.
+    pub fn expect(&mut self, kind: TokenKind) -> bool {
+        if self.is_out_of_bounds() || self.tokens[self.pos].kind != kind {
+            return false;
         }
+
+        self.pos += 1;
+
+        return true;
     }
 
-    pub fn expect(&mut self, kind: TokenKind) -> &Token {
-        let tok = &self.tokens[self.pos];
-        if tok.kind != kind {
-            println!("syntax error: unexpected token '{}', expected '{}'", tok.kind.to_string(), kind.to_string());
+    /// Returns the next token without consuming it.
+    pub fn peek(&self) -> &Token {
+        if self.is_out_of_bounds() {
+            return &self.tokens[self.len - 1];
         }
 
-        self.pos += 1;
+        return &self.tokens[self.pos];
+    }
 
-        return tok;
+    /// Consumes all whitespace tokens at current position.
+    pub fn skip_whitespace(&mut self) {
+        while !self.is_out_of_bounds() && self.tokens[self.pos].kind == TokenKind::Whitespace {
+            self.pos += 1;
+        }
     }
 
     pub fn current(&self) -> &Token {
@@ -49,12 +60,4 @@ impl ParserTokenBuffer {
             tokens: tokens
         }
     }
-
-    pub fn default() -> Self {
-        Self {
-            pos: 0,
-            len: 0,
-            tokens: Vec::new()
-        }
-    }
 }
\ No newline at end of file
@@ -1,25 +1,46 @@
-#![allow(unused)]
+use crate::diagnostics::SourceLocation;
 
-// This tokenizer tries to be pretty conformant with the specification here:
-// https://www.w3.org/TR/css-syntax-3/#tokenization but full conformance is not the final goal.
+// This tokenizer follows the tokenization algorithm of the specification:
+// https://www.w3.org/TR/css-syntax-3/#tokenization
 //
-// Currently only supports tokenizing simple, inline inputs (no selectors etc.).
+// Whitespace is significant in selectors (descendant combinator), so it is emitted
+// as a single token for each run of whitespace characters. Comments are dropped.
+//
+// Tokenizing never fails: malformed strings and urls become `BadString` and `BadUrl` tokens,
+// which the parser treats as errors.
+//
+// As in the current specification, unicode ranges are not tokens: `u+a` is an identifier,
+// a `+` delimiter and another identifier. Only the parser of the `unicode-range` descriptor
+// would recognize them.
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenKind {
     Identifier,
     Function,
+
+    /// An at-keyword, its value includes the `@`.
     At,
+
+    /// A hash, its value does not include the `#`.
     Hash,
     String,
+
+    /// A string that was ended by a newline.
+    BadString,
+
+    /// An unquoted `url(...)`, its value is the url.
     Url,
+
+    /// An unquoted `url(...)` containing characters that are not allowed in it.
+    BadUrl,
     Delimeter,
     Integer,
     Number,
     Percentage,
     Dimension,
-    CDO,
-    CDC,
+
+    Cdo,
+    Cdc,
     Colon,
     Semicolon,
     Comma,
@@ -29,25 +50,28 @@ pub enum TokenKind {
     ParenthesisClose,
     BracketOpen,
     BracketClose,
+    Whitespace,
     EndOfInput
 }
 
-impl ToString for TokenKind {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let val = match self {
             TokenKind::Identifier => "identifier",
             TokenKind::Function => "function",
             TokenKind::At => "@",
             TokenKind::Hash => "#",
             TokenKind::String => "string",
+            TokenKind::BadString => "unterminated string",
             TokenKind::Url => "url",
+            TokenKind::BadUrl => "invalid url",
             TokenKind::Delimeter => "delimeter",
             TokenKind::Integer => "integer",
             TokenKind::Number => "number",
             TokenKind::Percentage => "%",
             TokenKind::Dimension => "dimension",
-            TokenKind::CDO => "<!--",
-            TokenKind::CDC => "-->",
+            TokenKind::Cdo => "<!--",
+            TokenKind::Cdc => "-->",
             TokenKind::Colon => ":",
             TokenKind::Semicolon => ";",
             TokenKind::Comma => ",",
@@ -57,63 +81,60 @@ impl ToString for TokenKind {
             TokenKind::ParenthesisClose => ")",
             TokenKind::BracketOpen => "{",
             TokenKind::BracketClose => "}",
-            TokenKind::EndOfInput => "end of input",
-            _ => ""
+            TokenKind::Whitespace => "whitespace",
+            TokenKind::EndOfInput => "end of input"
         };
 
-        return val.to_string();
+        return f.write_str(val);
     }
 }
 
+/// Type flag of a hash token, which tells whether its value could be an identifier.
+/// Only hashes of the `Id` type can be ID selectors, `#1a` is just an unrestricted hash.
+/// https://www.w3.org/TR/css-syntax-3/#typedef-hash-token
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HashType {
+    Id,
+    Unrestricted
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub kind: TokenKind,
     pub value: String,
     pub unit: String,
-}
 
-impl Token {
-    pub fn is_int(&self) -> bool {
-        return self.value.contains('.');
-    }
-
-    pub fn simple(kind: TokenKind) -> Token {
-        Token {
-            kind: kind,
-            value: String::new(),
-            unit: String::new()
-        }
-    }
+    /// Type flag of a `Hash` token, `Unrestricted` for all other tokens.
+    pub hash_type: HashType,
 
-    pub fn for_char(kind: TokenKind, value: char) -> Token {
-        Token {
-            kind: kind,
-            value: String::from(value),
-            unit: String::new()
-        }
-    }
+    /// Where the token starts in the tokenized text.
+    pub location: SourceLocation
+}
 
-    pub fn for_string(kind: TokenKind, value: String) -> Token {
+impl Token {
+    pub fn new(kind: TokenKind, value: String, unit: String, location: SourceLocation) -> Token {
         Token {
             kind: kind,
             value: value,
-            unit: String::new()
-        }
-    }
-
-    pub fn for_dimension(value: String, unit: String) -> Token {
-        Token {
-            kind: TokenKind::Dimension,
-            value: value,
-            unit: unit
+            unit: unit,
+            hash_type: HashType::Unrestricted,
+            location: location
         }
     }
 }
 
+/// The replacement character, used in place of invalid code points.
+const REPLACEMENT_CHARACTER: char = '\u{FFFD}';
+
+/// The greatest code point defined by Unicode.
+const MAXIMUM_CODE_POINT: u32 = 0x10FFFF;
+
 pub struct Tokenizer {
-    input: Vec<u8>,
-    buffer_pos: usize,
-    buffer_len: usize
+    input: Vec<char>,
+    pos: usize,
+
+    /// Positions of the first character of each line, used to compute locations of tokens.
+    line_starts: Vec<usize>
 }
 
 impl Tokenizer {
@@ -123,6 +144,12 @@ impl Tokenizer {
         loop {
             let token = self.next();
             let is_end = token.kind == TokenKind::EndOfInput;
+
+            // Whitespace around a comment is a single run of whitespace.
+            if token.kind == TokenKind::Whitespace && tokens.last().is_some_and(|last| last.kind == TokenKind::Whitespace) {
+                continue;
+            }
+
             tokens.push(token);
             if is_end {
                 break;
@@ -132,227 +159,631 @@ impl Tokenizer {
         return tokens;
     }
 
+    /// Consumes a token.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-token
     fn next(&mut self) -> Token {
-        if self.is_out_of_bounds() {
-            return Token::simple(TokenKind::EndOfInput);
-        }
+        self.skip_comments();
 
-        let ch = self.next_char();
+        let location = self.get_location(self.pos);
+        let token = |kind: TokenKind, value: String| Token::new(kind, value, String::new(), location);
 
-        if ch.is_whitespace() {
-            return self.next();
-        }
+        let ch = match self.next_char() {
+            Some(ch) => ch,
+            None => return token(TokenKind::EndOfInput, String::new())
+        };
 
-        // <ident-token>
-        if ch.is_alphabetic() || ch == '-' {
-            if !self.peek_char().is_numeric() {
-                let value = self.read_identifier();
-    
-                // might become <function-token>
-                if self.peek_char() == '(' {
-                    self.next_char();
-                    return Token::for_string(TokenKind::Function, value);
+        match ch {
+            _ if is_whitespace(ch) => {
+                while self.peek_char(0).is_some_and(is_whitespace) {
+                    self.pos += 1;
                 }
-    
-                return Token::for_string(TokenKind::Identifier, value);
+
+                return token(TokenKind::Whitespace, String::from(" "));
             }
-        }
 
-        if ch.is_numeric() || ch == '+' || ch == '-' {
-            let (value, is_int) = self.read_number();
+            '"' | '\'' => {
+                return self.read_string(ch, location);
+            }
 
-            if self.would_start_identifier() {
-                self.next_char();
-                let dimension = self.read_identifier();
-                return Token::for_dimension(value, dimension);
+            '#' if self.peek_char(0).is_some_and(is_name_char) || self.is_valid_escape(0) => {
+                let hash_type = if self.would_start_identifier(0) { HashType::Id } else { HashType::Unrestricted };
+                return Token {
+                    hash_type: hash_type,
+                    ..token(TokenKind::Hash, self.read_name())
+                };
             }
 
-            if self.peek_char() == '%' {
-                self.next_char();
-                return Token::for_string(TokenKind::Percentage, value);
+            '+' | '.' if self.would_start_number(-1) => {
+                self.pos -= 1;
+                return self.read_numeric(location);
             }
 
-            if is_int {
-                return Token::for_string(TokenKind::Integer, value);
-            } else {
-                return Token::for_string(TokenKind::Number, value);
+            '-' if self.would_start_number(-1) => {
+                self.pos -= 1;
+                return self.read_numeric(location);
             }
-        }
 
-        // <string-token>
-        if ch == '"' || ch == '\'' {
-            self.next_char();
-            let value = self.read_string(ch);
-            return Token::for_string(TokenKind::String, value);
-        }
+            '-' if self.peek_char(0) == Some('-') && self.peek_char(1) == Some('>') => {
+                self.pos += 2;
+                return token(TokenKind::Cdc, String::from("-->"));
+            }
 
-        if ch == '@' {
-            let value = self.read_identifier();
-            return Token::for_string(TokenKind::At, value);
-        }
+            '-' if self.would_start_identifier(-1) => {
+                self.pos -= 1;
+                return self.read_identifier_like(location);
+            }
 
-        if ch == '#' {
-            self.next_char();
-            let value = self.read_identifier();
-            return Token::for_string(TokenKind::Hash, value);
-        }
+            '<' if self.peek_char(0) == Some('!') && self.peek_char(1) == Some('-') && self.peek_char(2) == Some('-') => {
+                self.pos += 3;
+                return token(TokenKind::Cdo, String::from("<!--"));
+            }
 
-        if ch == '/' && self.peek_char() == '*' {
-            while !self.is_out_of_bounds() {
-                let ch = self.next_char();
-                if ch == '*' && self.peek_char() == '/' {
-                    self.next_char();
-                    break;
-                }
+            '@' if self.would_start_identifier(0) => {
+                return token(TokenKind::At, format!("@{}", self.read_name()));
+            }
+
+            '\\' if self.is_valid_escape(-1) => {
+                self.pos -= 1;
+                return self.read_identifier_like(location);
             }
 
-            return self.next();
+            _ if ch.is_ascii_digit() => {
+                self.pos -= 1;
+                return self.read_numeric(location);
+            }
+
+            _ if is_name_start_char(ch) => {
+                self.pos -= 1;
+                return self.read_identifier_like(location);
+            }
+
+            ',' => return token(TokenKind::Comma, String::from(ch)),
+            ':' => return token(TokenKind::Colon, String::from(ch)),
+            ';' => return token(TokenKind::Semicolon, String::from(ch)),
+            '[' => return token(TokenKind::SquareParenthesisOpen, String::from(ch)),
+            ']' => return token(TokenKind::SquareParenthesisClose, String::from(ch)),
+            '(' => return token(TokenKind::ParenthesisOpen, String::from(ch)),
+            ')' => return token(TokenKind::ParenthesisClose, String::from(ch)),
+            '{' => return token(TokenKind::BracketOpen, String::from(ch)),
+            '}' => return token(TokenKind::BracketClose, String::from(ch)),
+            _ => return token(TokenKind::Delimeter, String::from(ch))
         }
+    }
 
-        // other possibilities
-        match ch {
-            ',' => Token::for_char(TokenKind::Comma, ch),
-            ':' => Token::for_char(TokenKind::Colon, ch),
-            ';' => Token::for_char(TokenKind::Semicolon, ch),
-            _ => Token::for_char(TokenKind::Delimeter, ch)
+    /// Consumes all comments at current position. An unterminated comment ends at the end of input.
+    fn skip_comments(&mut self) {
+        while self.peek_char(0) == Some('/') && self.peek_char(1) == Some('*') {
+            self.pos += 2;
+
+            while self.pos < self.input.len() && !(self.peek_char(0) == Some('*') && self.peek_char(1) == Some('/')) {
+                self.pos += 1;
+            }
+
+            self.pos = (self.pos + 2).min(self.input.len());
         }
     }
 
-    fn would_start_identifier(&mut self) -> bool {
-        let x1 = self.peek_char();
-        if x1 == '-' {
-            let x2 = self.next_char();
-            if x2.is_alphanumeric() || x2 == '-' {
-                self.previous_char();
-                return true;
+    /// Consumes an identifier, a function or a url.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-ident-like-token
+    fn read_identifier_like(&mut self, location: SourceLocation) -> Token {
+        let name = self.read_name();
+
+        if self.peek_char(0) != Some('(') {
+            return Token::new(TokenKind::Identifier, name, String::new(), location);
+        }
+
+        self.pos += 1;
+
+        // A quoted url is a function whose argument is a string.
+        if name.eq_ignore_ascii_case("url") {
+            while self.peek_char(0).is_some_and(is_whitespace) && self.peek_char(1).is_some_and(is_whitespace) {
+                self.pos += 1;
             }
 
-            self.previous_char();
-        } else {
-            if x1.is_alphanumeric() {
-                return true;
+            let next = if self.peek_char(0).is_some_and(is_whitespace) { self.peek_char(1) } else { self.peek_char(0) };
+            if next != Some('"') && next != Some('\'') {
+                return self.read_url(location);
             }
         }
 
-        return false;
+        return Token::new(TokenKind::Function, name, String::new(), location);
     }
 
-    fn current_char(&mut self) -> char {
-        return self.input[self.buffer_pos - 1] as char;
+    /// Consumes a number, a percentage or a dimension.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-numeric-token
+    fn read_numeric(&mut self, location: SourceLocation) -> Token {
+        let (value, is_integer) = self.read_number();
+
+        if self.would_start_identifier(0) {
+            let unit = self.read_name();
+            return Token::new(TokenKind::Dimension, value, unit, location);
+        }
+
+        if self.peek_char(0) == Some('%') {
+            self.pos += 1;
+            return Token::new(TokenKind::Percentage, value, String::new(), location);
+        }
+
+        let kind = if is_integer { TokenKind::Integer } else { TokenKind::Number };
+        return Token::new(kind, value, String::new(), location);
     }
 
-    fn peek_char(&mut self) -> char {
-        if self.is_out_of_bounds() {
-            return '\0';
+    /// Consumes a number and returns its representation and whether it is an integer.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-number
+    fn read_number(&mut self) -> (String, bool) {
+        let mut value = String::new();
+        let mut is_integer = true;
+
+        if let Some(sign) = self.peek_char(0).filter(|ch| *ch == '+' || *ch == '-') {
+            value.push(sign);
+            self.pos += 1;
+        }
+
+        self.read_digits(&mut value);
+
+        if self.peek_char(0) == Some('.') && self.peek_char(1).is_some_and(|ch| ch.is_ascii_digit()) {
+            value.push('.');
+            self.pos += 1;
+            self.read_digits(&mut value);
+            is_integer = false;
+        }
+
+        if let Some(exponent) = self.peek_char(0).filter(|ch| *ch == 'e' || *ch == 'E') {
+            let has_sign = self.peek_char(1).is_some_and(|ch| ch == '+' || ch == '-');
+            let digit_offset = if has_sign { 2 } else { 1 };
+
+            if self.peek_char(digit_offset).is_some_and(|ch| ch.is_ascii_digit()) {
+                value.push(exponent);
+                if has_sign {
+                    value.push(self.peek_char(1).unwrap());
+                }
+
+                self.pos += digit_offset as usize;
+                self.read_digits(&mut value);
+                is_integer = false;
+            }
         }
 
-        return self.input[self.buffer_pos] as char;
+        return (value, is_integer);
     }
 
-    fn next_char(&mut self) -> char {
-        let c = self.input[self.buffer_pos] as char;
-        self.buffer_pos += 1;
-        return c;
+    fn read_digits(&mut self, value: &mut String) {
+        while let Some(digit) = self.peek_char(0).filter(|ch| ch.is_ascii_digit()) {
+            value.push(digit);
+            self.pos += 1;
+        }
     }
 
-    fn previous_char(&mut self) {
-        self.buffer_pos -= 1;
+    /// Consumes a string whose opening quote has been consumed already.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-string-token
+    fn read_string(&mut self, end_char: char, location: SourceLocation) -> Token {
+        let mut value = String::new();
+
+        // An unterminated string ends at the end of input.
+        while let Some(ch) = self.next_char() {
+            match ch {
+                _ if ch == end_char => break,
+
+                // The newline is not a part of the string, so it is tokenized as whitespace.
+                '\n' => {
+                    self.pos -= 1;
+                    return Token::new(TokenKind::BadString, value, String::new(), location);
+                }
+
+                '\\' => match self.peek_char(0) {
+                    None => {}
+
+                    // An escaped newline continues the string on the next line.
+                    Some('\n') => self.pos += 1,
+                    Some(_) => value.push(self.read_escape())
+                },
+
+                _ => value.push(ch)
+            }
+        }
+
+        return Token::new(TokenKind::String, value, String::new(), location);
     }
 
-    fn read_string(&mut self, end_char: char) -> String {
-        let mut value = String::from(self.current_char());
+    /// Consumes an unquoted url whose `url(` has been consumed already.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-url-token
+    fn read_url(&mut self, location: SourceLocation) -> Token {
+        let mut value = String::new();
 
-        while !self.is_out_of_bounds() {
-            let ch = self.next_char();
+        while self.peek_char(0).is_some_and(is_whitespace) {
+            self.pos += 1;
+        }
 
-            if self.is_newline(ch) || ch == end_char {
-                break;
+        while let Some(ch) = self.next_char() {
+            match ch {
+                ')' => break,
+
+                // Whitespace is only allowed at the end.
+                _ if is_whitespace(ch) => {
+                    while self.peek_char(0).is_some_and(is_whitespace) {
+                        self.pos += 1;
+                    }
+
+                    match self.next_char() {
+                        None | Some(')') => break,
+                        Some(_) => return self.read_bad_url(location)
+                    }
+                }
+
+                '"' | '\'' | '(' => return self.read_bad_url(location),
+                _ if is_non_printable(ch) => return self.read_bad_url(location),
+
+                '\\' if self.is_valid_escape(-1) => value.push(self.read_escape()),
+                '\\' => return self.read_bad_url(location),
+                _ => value.push(ch)
             }
+        }
+
+        return Token::new(TokenKind::Url, value, String::new(), location);
+    }
 
-            value.push(ch);
+    /// Consumes the rest of an invalid url, so that tokenizing can continue after it.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-remnants-of-bad-url
+    fn read_bad_url(&mut self, location: SourceLocation) -> Token {
+        loop {
+            match self.next_char() {
+                None | Some(')') => break,
+                Some('\\') if self.is_valid_escape(-1) => {
+                    self.read_escape();
+                }
+
+                Some(_) => {}
+            }
         }
 
-        return value;
+        return Token::new(TokenKind::BadUrl, String::new(), String::new(), location);
     }
 
-    fn read_number(&mut self) -> (String, bool) {
-        let mut value = String::from(self.current_char());
-        let mut has_dot = false;
-        let mut is_int = true;
-
-        while !self.is_out_of_bounds() {
-            let ch = self.next_char();
-
-            if ch == '.' {
-                if has_dot {
-                    self.previous_char();
-                    break;
-                } else {
-                    is_int = false;
-                    has_dot = true;
+    /// Consumes a name, which may contain escapes.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-name
+    fn read_name(&mut self) -> String {
+        let mut name = String::new();
+
+        loop {
+            match self.peek_char(0) {
+                Some(ch) if is_name_char(ch) => {
+                    name.push(ch);
+                    self.pos += 1;
                 }
-            } else {
-                if !ch.is_numeric() {
-                    self.previous_char();
-                    break;
+
+                Some('\\') if self.is_valid_escape(0) => {
+                    self.pos += 1;
+                    name.push(self.read_escape());
                 }
-            }
 
-            value.push(ch);
+                _ => break
+            }
         }
 
-        return (value, is_int);
+        return name;
     }
-    
-    fn read_identifier(&mut self) -> String {
-        // skip up to 2 dashes at the beginning
-        if self.current_char() == '-' {
-            self.next_char();
 
-            if self.current_char() == '-' {
-                self.next_char();
-            }
+    /// Consumes an escaped code point whose `\` has been consumed already.
+    /// https://www.w3.org/TR/css-syntax-3/#consume-escaped-code-point
+    fn read_escape(&mut self) -> char {
+        let ch = match self.next_char() {
+            Some(ch) => ch,
+            None => return REPLACEMENT_CHARACTER
+        };
+
+        if !ch.is_ascii_hexdigit() {
+            return ch;
         }
 
-        let mut identifier = String::from(self.current_char());
+        let mut digits = String::from(ch);
+        while digits.len() < 6 && self.peek_char(0).is_some_and(|ch| ch.is_ascii_hexdigit()) {
+            digits.push(self.next_char().unwrap());
+        }
 
-        while !self.is_out_of_bounds() {
-            let ch = self.next_char();
+        // A single whitespace ends the escape, so that it can be followed by a hex digit.
+        if self.peek_char(0).is_some_and(is_whitespace) {
+            self.pos += 1;
+        }
 
-            if !self.is_allowed_identifier_char(ch) {
-                self.previous_char();
-                break;
+        let code_point = u32::from_str_radix(&digits, 16).unwrap_or(0);
+        if code_point == 0 || code_point > MAXIMUM_CODE_POINT {
+            return REPLACEMENT_CHARACTER;
+        }
+
+        // Surrogates are not valid chars, so they are replaced too.
+        return char::from_u32(code_point).unwrap_or(REPLACEMENT_CHARACTER);
+    }
+
+    /// Returns whether the character at `offset` from current position and the one after it are a valid escape.
+    /// https://www.w3.org/TR/css-syntax-3/#starts-with-a-valid-escape
+    fn is_valid_escape(&self, offset: isize) -> bool {
+        return self.peek_char(offset) == Some('\\') && self.peek_char(offset + 1).is_some_and(|ch| ch != '\n');
+    }
+
+    /// Returns whether the characters starting at `offset` from current position would start an identifier.
+    /// https://www.w3.org/TR/css-syntax-3/#would-start-an-identifier
+    fn would_start_identifier(&self, offset: isize) -> bool {
+        match self.peek_char(offset) {
+            Some('-') => {
+                return self.peek_char(offset + 1).is_some_and(|ch| is_name_start_char(ch) || ch == '-') || self.is_valid_escape(offset + 1);
             }
 
-            identifier.push(ch);
+            Some('\\') => return self.is_valid_escape(offset),
+            Some(ch) => return is_name_start_char(ch),
+            None => return false
         }
+    }
+
+    /// Returns whether the characters starting at `offset` from current position would start a number.
+    /// https://www.w3.org/TR/css-syntax-3/#starts-with-a-number
+    fn would_start_number(&self, offset: isize) -> bool {
+        let is_digit = |offset: isize| self.peek_char(offset).is_some_and(|ch| ch.is_ascii_digit());
 
-        return identifier;
+        match self.peek_char(offset) {
+            Some('+') | Some('-') => return is_digit(offset + 1) || (self.peek_char(offset + 1) == Some('.') && is_digit(offset + 2)),
+            Some('.') => return is_digit(offset + 1),
+            Some(ch) => return ch.is_ascii_digit(),
+            None => return false
+        }
     }
 
-    fn is_newline(&self, ch: char) -> bool {
-        // specification also uses \f but it's pretty old so skipping
-        // it should not make any problems
-        return ch == '\r' || ch == '\n';
+    /// Returns the character at `offset` from current position, which can be negative to look back.
+    fn peek_char(&self, offset: isize) -> Option<char> {
+        let pos = self.pos as isize + offset;
+        if pos < 0 {
+            return None;
+        }
+
+        return self.input.get(pos as usize).cloned();
     }
 
-    fn is_out_of_bounds(&self) -> bool {
-        return self.buffer_pos >= self.buffer_len;
+    fn next_char(&mut self) -> Option<char> {
+        let ch = self.input.get(self.pos).cloned();
+        if ch.is_some() {
+            self.pos += 1;
+        }
+
+        return ch;
     }
 
-    fn is_allowed_identifier_char(&self, c: char) -> bool {
-        return c.is_alphanumeric() || c == '_' || c == '-';
+    /// Returns the line and column of the character at given position.
+    fn get_location(&self, pos: usize) -> SourceLocation {
+        let line = match self.line_starts.binary_search(&pos) {
+            Ok(line) => line,
+            Err(line) => line - 1
+        };
+
+        return SourceLocation {
+            line: line + 1,
+            column: pos - self.line_starts[line] + 1
+        };
     }
 
     pub fn new(input: &str) -> Self {
+
+        // https://www.w3.org/TR/css-syntax-3/#input-preprocessing
+        let input: Vec<char> = input.replace("\r\n", "\n")
+            .chars()
+            .map(|ch| match ch {
+                '\r' | '\u{C}' => '\n',
+                '\0' => REPLACEMENT_CHARACTER,
+                _ => ch
+            })
+            .collect();
+
+        let line_starts = std::iter::once(0)
+            .chain(input.iter().enumerate().filter(|(_, ch)| **ch == '\n').map(|(pos, _)| pos + 1))
+            .collect();
+
         Self {
-            input: Vec::from(input.as_bytes()),
-            buffer_pos: 0,
-            buffer_len: input.len()
+            input: input,
+            pos: 0,
+            line_starts: line_starts
         }
     }
 }
 
+fn is_whitespace(ch: char) -> bool {
+    return ch == ' ' || ch == '\t' || ch == '\n';
+}
+
+/// https://www.w3.org/TR/css-syntax-3/#name-start-code-point
+fn is_name_start_char(ch: char) -> bool {
+    return ch.is_ascii_alphabetic() || ch == '_' || !ch.is_ascii();
+}
+
+/// https://www.w3.org/TR/css-syntax-3/#name-code-point
+fn is_name_char(ch: char) -> bool {
+    return is_name_start_char(ch) || ch.is_ascii_digit() || ch == '-';
+}
+
+/// https://www.w3.org/TR/css-syntax-3/#non-printable-code-point
+fn is_non_printable(ch: char) -> bool {
+    return ch <= '\u{8}' || ch == '\u{B}' || ('\u{E}'..='\u{1F}').contains(&ch) || ch == '\u{7F}';
+}
+
 pub fn tokenize(input: &str) -> Vec<Token> {
     return Tokenizer::new(input).tokenize();
-}
\ No newline at end of file
+}
+
+/// Returns approximate source text of given tokens.
+pub fn tokens_to_string(tokens: &[Token]) -> String {
+    let mut text = String::new();
+
+    for token in tokens {
+        match token.kind {
+            TokenKind::Hash => text.push('#'),
+            TokenKind::String | TokenKind::BadString => text.push('"'),
+            TokenKind::Url | TokenKind::BadUrl => text.push_str("url("),
+            _ => {}
+        }
+
+        text.push_str(&token.value);
+
+        match token.kind {
+            TokenKind::Dimension => text.push_str(&token.unit),
+            TokenKind::Percentage => text.push('%'),
+            TokenKind::Function => text.push('('),
+            TokenKind::String => text.push('"'),
+            TokenKind::Url | TokenKind::BadUrl => text.push(')'),
+            _ => {}
+        }
+    }
+
+    return text;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns kind, value and unit of all tokens of given input, without the end of input.
+    fn tokenize_parts(input: &str) -> Vec<(TokenKind, String, String)> {
+        return tokenize(input).into_iter()
+            .filter(|token| token.kind != TokenKind::EndOfInput)
+            .map(|token| (token.kind, token.value, token.unit))
+            .collect();
+    }
+
+    fn part(kind: TokenKind, value: &str, unit: &str) -> (TokenKind, String, String) {
+        return (kind, value.to_string(), unit.to_string());
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(tokenize_parts("\\41 B"), vec![part(TokenKind::Identifier, "AB", "")]);
+        assert_eq!(tokenize_parts("a\\:b"), vec![part(TokenKind::Identifier, "a:b", "")]);
+        assert_eq!(tokenize_parts("\\0 \\110000 \\D800"), vec![part(TokenKind::Identifier, "\u{FFFD}\u{FFFD}\u{FFFD}", "")]);
+        assert_eq!(tokenize_parts("'a\\'b'"), vec![part(TokenKind::String, "a'b", "")]);
+        assert_eq!(tokenize_parts("'a\\\nb'"), vec![part(TokenKind::String, "ab", "")]);
+        assert_eq!(tokenize_parts("#\\31 a"), vec![part(TokenKind::Hash, "1a", "")]);
+    }
+
+    #[test]
+    fn hash_types() {
+        let hash_types: Vec<HashType> = tokenize("#a #-b #_1 #\\31 a #1a #-1 #--")
+            .into_iter()
+            .filter(|token| token.kind == TokenKind::Hash)
+            .map(|token| token.hash_type)
+            .collect();
+
+        assert_eq!(hash_types, vec![
+            HashType::Id, HashType::Id, HashType::Id, HashType::Id,
+            HashType::Unrestricted, HashType::Unrestricted, HashType::Id
+        ]);
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(tokenize_parts("3"), vec![part(TokenKind::Integer, "3", "")]);
+        assert_eq!(tokenize_parts("-3.5"), vec![part(TokenKind::Number, "-3.5", "")]);
+        assert_eq!(tokenize_parts("+.5"), vec![part(TokenKind::Number, "+.5", "")]);
+        assert_eq!(tokenize_parts("50%"), vec![part(TokenKind::Percentage, "50", "")]);
+        assert_eq!(tokenize_parts("10px"), vec![part(TokenKind::Dimension, "10", "px")]);
+        assert_eq!(tokenize_parts("1.5e3"), vec![part(TokenKind::Number, "1.5e3", "")]);
+        assert_eq!(tokenize_parts("2E-2em"), vec![part(TokenKind::Dimension, "2E-2", "em")]);
+
+        // Without digits after it, the `e` starts the unit.
+        assert_eq!(tokenize_parts("1e"), vec![part(TokenKind::Dimension, "1", "e")]);
+        assert_eq!(tokenize_parts("1e+"), vec![part(TokenKind::Dimension, "1", "e"), part(TokenKind::Delimeter, "+", "")]);
+    }
+
+    #[test]
+    fn urls() {
+        assert_eq!(tokenize_parts("url( a.png )"), vec![part(TokenKind::Url, "a.png", "")]);
+        assert_eq!(tokenize_parts("URL(a\\).png)"), vec![part(TokenKind::Url, "a).png", "")]);
+        assert_eq!(tokenize_parts("url( 'a.png')"), vec![
+            part(TokenKind::Function, "url", ""),
+            part(TokenKind::Whitespace, " ", ""),
+            part(TokenKind::String, "a.png", ""),
+            part(TokenKind::ParenthesisClose, ")", "")
+        ]);
+    }
+
+    #[test]
+    fn bad_urls() {
+        assert_eq!(tokenize_parts("url(a b) c"), vec![
+            part(TokenKind::BadUrl, "", ""),
+            part(TokenKind::Whitespace, " ", ""),
+            part(TokenKind::Identifier, "c", "")
+        ]);
+
+        assert_eq!(tokenize_parts("url(a\"b\\)c) d"), vec![
+            part(TokenKind::BadUrl, "", ""),
+            part(TokenKind::Whitespace, " ", ""),
+            part(TokenKind::Identifier, "d", "")
+        ]);
+
+        assert_eq!(tokenize_parts("url(a(b)")[0].0, TokenKind::BadUrl);
+        assert_eq!(tokenize_parts("url(a\u{1}b)")[0].0, TokenKind::BadUrl);
+    }
+
+    #[test]
+    fn bad_strings() {
+        assert_eq!(tokenize_parts("'ab\ncd"), vec![
+            part(TokenKind::BadString, "ab", ""),
+            part(TokenKind::Whitespace, " ", ""),
+            part(TokenKind::Identifier, "cd", "")
+        ]);
+
+        // A string ended by the end of input is not bad.
+        assert_eq!(tokenize_parts("\"ab"), vec![part(TokenKind::String, "ab", "")]);
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(tokenize_parts("a/* b */c"), vec![part(TokenKind::Identifier, "a", ""), part(TokenKind::Identifier, "c", "")]);
+        assert_eq!(tokenize_parts("a /* b */ /**/ c"), vec![
+            part(TokenKind::Identifier, "a", ""),
+            part(TokenKind::Whitespace, " ", ""),
+            part(TokenKind::Identifier, "c", "")
+        ]);
+
+        assert_eq!(tokenize_parts("a/* b"), vec![part(TokenKind::Identifier, "a", "")]);
+        assert_eq!(tokenize_parts("'/* a */'"), vec![part(TokenKind::String, "/* a */", "")]);
+    }
+
+    #[test]
+    fn cdo_and_cdc() {
+        assert_eq!(tokenize_parts("<!-- a -->"), vec![
+            part(TokenKind::Cdo, "<!--", ""),
+            part(TokenKind::Whitespace, " ", ""),
+            part(TokenKind::Identifier, "a", ""),
+            part(TokenKind::Whitespace, " ", ""),
+            part(TokenKind::Cdc, "-->", "")
+        ]);
+
+        assert_eq!(tokenize_parts("--a"), vec![part(TokenKind::Identifier, "--a", "")]);
+        assert_eq!(tokenize_parts("<!-"), vec![
+            part(TokenKind::Delimeter, "<", ""),
+            part(TokenKind::Delimeter, "!", ""),
+            part(TokenKind::Delimeter, "-", "")
+        ]);
+    }
+
+    #[test]
+    fn unicode_ranges_are_not_tokens() {
+        assert_eq!(tokenize_parts("u+a"), vec![
+            part(TokenKind::Identifier, "u", ""),
+            part(TokenKind::Delimeter, "+", ""),
+            part(TokenKind::Identifier, "a", "")
+        ]);
+
+        assert_eq!(tokenize_parts("U+4??"), vec![
+            part(TokenKind::Identifier, "U", ""),
+            part(TokenKind::Integer, "+4", ""),
+            part(TokenKind::Delimeter, "?", ""),
+            part(TokenKind::Delimeter, "?", "")
+        ]);
+    }
+
+    #[test]
+    fn source_locations() {
+        let locations: Vec<(usize, usize)> = tokenize("a {\r\n  b: /* c\n */ d }")
+            .iter()
+            .map(|token| (token.location.line, token.location.column))
+            .collect();
+
+        assert_eq!(locations, vec![(1, 1), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (2, 5), (3, 5), (3, 6), (3, 7), (3, 8)]);
+    }
+}
@@ -0,0 +1,653 @@
+use crate::css::PrimitiveValue;
+use crate::css::property_registry::ValueGrammar;
+use crate::css::tokenizer::{Token, TokenKind};
+use crate::css::math::{MathType, is_math_function, parse_math_function};
+use crate::utils::color;
+
+/// Units allowed in `<length>` values.
+pub const LENGTH_UNITS: &[&str] = &["px", "pt", "pc", "mm", "cm", "in", "q", "em", "ex", "rem", "vw", "vh", "vmin", "vmax"];
+
+/// Parses a property's value from given tokens, which have to match the grammar completely.
+/// Returns `None` if the value is invalid.
+pub fn parse_value(grammar: &ValueGrammar, tokens: &[Token]) -> Option<PrimitiveValue> {
+    let tokens: Vec<Token> = tokens.iter()
+        .filter(|token| token.kind != TokenKind::Whitespace)
+        .cloned()
+        .collect();
+
+    let (_, mut values) = match_grammar(grammar, &tokens, 0)
+        .into_iter()
+        .find(|(end, _)| *end == tokens.len())?;
+
+    if values.len() == 1 {
+        return values.pop();
+    }
+
+    return Some(PrimitiveValue::from_list(values, false));
+}
+
+/// A way to match a grammar: the position just past the matched tokens and the matched values.
+type Match = (usize, Vec<PrimitiveValue>);
+
+/// Returns all ways in which `grammar` can match the tokens starting at `pos`.
+/// The preferred matches, which are those consuming the most tokens, come first.
+fn match_grammar(grammar: &ValueGrammar, tokens: &[Token], pos: usize) -> Vec<Match> {
+    match grammar {
+        ValueGrammar::Alternatives(alternatives) => {
+            return alternatives.iter()
+                .flat_map(|alternative| match_grammar(alternative, tokens, pos))
+                .collect();
+        }
+
+        ValueGrammar::Sequence(items) => {
+            let mut matches: Vec<Match> = vec![(pos, Vec::new())];
+
+            for item in items.iter() {
+                let mut next_matches = Vec::new();
+
+                for (item_pos, values) in matches {
+                    for (end, item_values) in match_grammar(item, tokens, item_pos) {
+                        let mut all_values = values.clone();
+                        all_values.extend(item_values);
+                        next_matches.push((end, all_values));
+                    }
+                }
+
+                matches = next_matches;
+            }
+
+            return matches;
+        }
+
+        ValueGrammar::Optional(item) => {
+            let mut matches = match_grammar(item, tokens, pos);
+            matches.push((pos, Vec::new()));
+            return matches;
+        }
+
+        ValueGrammar::Repeat(item, min, max) => {
+            return match_repeated(item, tokens, pos, *min, *max, false);
+        }
+
+        ValueGrammar::CommaList(item) => {
+            return match_repeated(item, tokens, pos, 1, usize::MAX, true)
+                .into_iter()
+                .map(|(end, values)| (end, vec![PrimitiveValue::from_list(values, true)]))
+                .collect();
+        }
+
+        ValueGrammar::AnyOrder(items) => {
+            return match_any_order(items, tokens, pos, &mut vec![false; items.len()]);
+        }
+
+        ValueGrammar::NonNegative(item) => {
+            return match_grammar(item, tokens, pos)
+                .into_iter()
+                .filter(|(_, values)| values.iter().all(|value| value.as_number() >= 0.0))
+                .collect();
+        }
+
+        ValueGrammar::Function(name, arguments) => {
+            let token = match tokens.get(pos) {
+                Some(token) if token.kind == TokenKind::Function && token.value.eq_ignore_ascii_case(name) => token,
+                _ => return Vec::new()
+            };
+
+            let end = match find_closing_parenthesis(tokens, pos + 1) {
+                Some(end) => end,
+                None => return Vec::new()
+            };
+
+            let argument_tokens = &tokens[pos + 1..end];
+            return match_grammar(arguments, argument_tokens, 0)
+                .into_iter()
+                .filter(|(arguments_end, _)| *arguments_end == argument_tokens.len())
+                .take(1)
+                .map(|(_, values)| (end + 1, vec![PrimitiveValue::from_function(&token.value.to_ascii_lowercase(), values)]))
+                .collect();
+        }
+
+        ValueGrammar::Comma => {
+            if tokens.get(pos).is_some_and(|token| token.kind == TokenKind::Comma) {
+                return vec![(pos + 1, Vec::new())];
+            }
+
+            return Vec::new();
+        }
+
+        ValueGrammar::FamilyName => {
+            return match_family_name(tokens, pos).into_iter().collect();
+        }
+
+        ValueGrammar::Color => {
+            return match_color(tokens, pos).into_iter().collect();
+        }
+
+        // A quoted url is not a single token, but a function with a string argument.
+        ValueGrammar::Uri if tokens.get(pos).is_some_and(|token| token.kind == TokenKind::Function && token.value.eq_ignore_ascii_case("url")) => {
+            return match (tokens.get(pos + 1), tokens.get(pos + 2)) {
+                (Some(url), Some(end)) if url.kind == TokenKind::String && end.kind == TokenKind::ParenthesisClose => vec![(pos + 3, vec![PrimitiveValue::from_url(&url.value)])],
+                _ => Vec::new()
+            };
+        }
+
+        ValueGrammar::Length | ValueGrammar::Percentage | ValueGrammar::Number | ValueGrammar::Integer if tokens.get(pos).is_some_and(is_math_function) => {
+            return match_math_function(grammar, tokens, pos).into_iter().collect();
+        }
+
+        _ => {
+            return match_single_token(grammar, tokens.get(pos))
+                .map(|value| (pos + 1, vec![value]))
+                .into_iter()
+                .collect();
+        }
+    }
+}
+
+/// Matches `item` repeated between `min` and `max` times, optionally separated by commas.
+fn match_repeated(item: &ValueGrammar, tokens: &[Token], pos: usize, min: usize, max: usize, comma_separated: bool) -> Vec<Match> {
+    let mut results = Vec::<Match>::new();
+    let mut matches: Vec<Match> = vec![(pos, Vec::new())];
+    let mut count = 0;
+
+    if min == 0 {
+        results.push((pos, Vec::new()));
+    }
+
+    while !matches.is_empty() && count < max {
+        let mut next_matches = Vec::new();
+
+        for (item_pos, values) in matches {
+            let mut item_pos = item_pos;
+            if count > 0 && comma_separated {
+                if !tokens.get(item_pos).is_some_and(|token| token.kind == TokenKind::Comma) {
+                    continue;
+                }
+
+                item_pos += 1;
+            }
+
+            for (end, item_values) in match_grammar(item, tokens, item_pos) {
+
+                // Items that match no tokens would repeat forever.
+                if end == item_pos {
+                    continue;
+                }
+
+                let mut all_values = values.clone();
+                all_values.extend(item_values);
+                next_matches.push((end, all_values));
+            }
+        }
+
+        count += 1;
+        if count >= min {
+            results.splice(0..0, next_matches.iter().cloned());
+        }
+
+        matches = next_matches;
+    }
+
+    return results;
+}
+
+/// Matches one or more of `items` in any order, each at most once.
+fn match_any_order(items: &[ValueGrammar], tokens: &[Token], pos: usize, used: &mut Vec<bool>) -> Vec<Match> {
+    let mut results = Vec::<Match>::new();
+
+    for idx in 0..items.len() {
+        if used[idx] {
+            continue;
+        }
+
+        for (end, values) in match_grammar(&items[idx], tokens, pos) {
+            used[idx] = true;
+            for (rest_end, rest_values) in match_any_order(items, tokens, end, used) {
+                let mut all_values = values.clone();
+                all_values.extend(rest_values);
+                results.push((rest_end, all_values));
+            }
+
+            used[idx] = false;
+            results.push((end, values));
+        }
+    }
+
+    return results;
+}
+
+/// Matches a math function, like `calc()`, whose result is accepted by given grammar.
+/// Mixed lengths and percentages are accepted wherever percentages are, which are always places lengths are accepted too.
+fn match_math_function(grammar: &ValueGrammar, tokens: &[Token], pos: usize) -> Option<Match> {
+    let (end, expression) = parse_math_function(tokens, pos)?;
+
+    let is_accepted = matches!((grammar, expression.get_type()?),
+        (ValueGrammar::Length, MathType::Length) |
+        (ValueGrammar::Percentage, MathType::Percentage) | (ValueGrammar::Percentage, MathType::LengthPercentage) |
+        (ValueGrammar::Number, MathType::Number) | (ValueGrammar::Integer, MathType::Number));
+
+    if !is_accepted {
+        return None;
+    }
+
+    return Some((end, vec![PrimitiveValue::from_math(expression)]));
+}
+
+fn match_single_token(grammar: &ValueGrammar, token: Option<&Token>) -> Option<PrimitiveValue> {
+    let token = token?;
+
+    match (grammar, &token.kind) {
+        (ValueGrammar::Keyword(keyword), TokenKind::Identifier) if token.value.eq_ignore_ascii_case(keyword) => {
+            return Some(PrimitiveValue::from_identifier(keyword));
+        }
+
+        (ValueGrammar::Keywords(keywords), TokenKind::Identifier) => {
+            return keywords.iter()
+                .find(|keyword| token.value.eq_ignore_ascii_case(keyword))
+                .map(|keyword| PrimitiveValue::from_identifier(keyword));
+        }
+
+        (ValueGrammar::Length, TokenKind::Dimension) => {
+            let unit = token.unit.to_ascii_lowercase();
+            if !LENGTH_UNITS.contains(&unit.as_str()) {
+                return None;
+            }
+
+            let value = token.value.parse().ok()?;
+            return Some(PrimitiveValue::from_dimension_value(&format!("{}{}", token.value, unit), value, &unit));
+        }
+
+        // Zero is the only length that does not need a unit.
+        (ValueGrammar::Length, TokenKind::Integer) | (ValueGrammar::Length, TokenKind::Number) => {
+            let value: f64 = token.value.parse().ok()?;
+            if value != 0.0 {
+                return None;
+            }
+
+            return Some(PrimitiveValue::from_dimension_value(&token.value, 0.0, ""));
+        }
+
+        (ValueGrammar::Percentage, TokenKind::Percentage) => {
+            let value = token.value.parse().ok()?;
+            return Some(PrimitiveValue::from_percentage(&format!("{}%", token.value), value));
+        }
+
+        (ValueGrammar::Number, TokenKind::Integer) | (ValueGrammar::Number, TokenKind::Number) | (ValueGrammar::Integer, TokenKind::Integer) => {
+            let value = token.value.parse().ok()?;
+            return Some(PrimitiveValue::from_number(&token.value, value));
+        }
+
+        (ValueGrammar::Uri, TokenKind::Url) => {
+            return Some(PrimitiveValue::from_url(&token.value));
+        }
+
+        (ValueGrammar::String, TokenKind::String) => {
+            return Some(PrimitiveValue::from_string(&token.value));
+        }
+
+        (ValueGrammar::Identifier, TokenKind::Identifier) => {
+            return Some(PrimitiveValue::from_identifier(&token.value));
+        }
+
+        _ => {
+            return None;
+        }
+    }
+}
+
+/// Matches a font family name, which is either a string or one or more identifiers
+/// joined by single spaces, e.g. `Times New Roman`.
+fn match_family_name(tokens: &[Token], pos: usize) -> Option<Match> {
+    let token = tokens.get(pos)?;
+    if token.kind == TokenKind::String {
+        return Some((pos + 1, vec![PrimitiveValue::from_string(&token.value)]));
+    }
+
+    let names: Vec<&String> = tokens[pos..].iter()
+        .take_while(|token| token.kind == TokenKind::Identifier)
+        .map(|token| &token.value)
+        .collect();
+
+    // A single identifier may be a generic family keyword, which is matched separately.
+    if names.len() < 2 {
+        let name = names.first()?;
+        let is_generic = ["serif", "sans-serif", "cursive", "fantasy", "monospace"].iter().any(|generic| name.eq_ignore_ascii_case(generic));
+        if is_generic {
+            return None;
+        }
+    }
+
+    let name = names.iter().map(|name| name.as_str()).collect::<Vec<&str>>().join(" ");
+    return Some((pos + names.len(), vec![PrimitiveValue::from_string(&name)]));
+}
+
+/// Matches a `<color>`: a named color, `currentColor`, a hex code or one of the `rgb()`, `rgba()`,
+/// `hsl()`, `hsla()` and `device-cmyk()` functions. `currentColor` is kept as an identifier,
+/// since it is only known once the `color` property has been computed.
+/// https://www.w3.org/TR/css-color-4/#color-syntax
+fn match_color(tokens: &[Token], pos: usize) -> Option<Match> {
+    let token = tokens.get(pos)?;
+
+    match token.kind {
+        TokenKind::Hash => {
+            let code = format!("#{}", token.value.to_ascii_lowercase());
+            let color = color::parse_hex_color(&token.value)?;
+            return Some((pos + 1, vec![PrimitiveValue::from_color(&code, color)]));
+        }
+
+        TokenKind::Identifier if token.value.eq_ignore_ascii_case("currentcolor") => {
+            return Some((pos + 1, vec![PrimitiveValue::from_identifier("currentcolor")]));
+        }
+
+        TokenKind::Identifier => {
+            let color = color::get_named_color(&token.value)?;
+            return Some((pos + 1, vec![PrimitiveValue::from_color(&token.value.to_ascii_lowercase(), color)]));
+        }
+
+        TokenKind::Function => {
+            let name = token.value.to_ascii_lowercase();
+            let end = find_closing_parenthesis(tokens, pos + 1)?;
+            let component_count = if name == "device-cmyk" { 4 } else { 3 };
+            let arguments = split_color_arguments(&tokens[pos + 1..end], component_count)?;
+
+            let color = match name.as_str() {
+                "rgb" | "rgba" => parse_rgb_components(&arguments)?,
+                "hsl" | "hsla" => parse_hsl_components(&arguments)?,
+                "device-cmyk" => parse_cmyk_components(&arguments)?,
+                _ => return None
+            };
+
+            let text = if let Some(cmyk) = color.cmyk {
+                let alpha = if color.alpha == 255 { String::new() } else { format!(" / {}", (color.alpha as f64 / 255.0 * 1000.0).round() / 1000.0) };
+                format!("device-cmyk({} {} {} {}{})", cmyk.cyan, cmyk.magenta, cmyk.yellow, cmyk.key, alpha)
+            } else if color.alpha == 255 {
+                format!("rgb({}, {}, {})", color.red, color.green, color.blue)
+            } else {
+                format!("rgba({}, {}, {}, {})", color.red, color.green, color.blue, (color.alpha as f64 / 255.0 * 1000.0).round() / 1000.0)
+            };
+
+            return Some((end + 1, vec![PrimitiveValue::from_color(&text, color)]));
+        }
+
+        _ => {
+            return None;
+        }
+    }
+}
+
+/// Arguments of a color function like `rgb()`.
+struct ColorArguments<'a> {
+    components: Vec<&'a Token>,
+    alpha: Option<&'a Token>,
+
+    /// Whether the arguments are separated by commas. The legacy syntax does not allow `none`
+    /// and is more strict about the types of the components.
+    is_legacy: bool
+}
+
+/// Splits arguments of a color function into its components, of which there are `component_count`, and the optional alpha.
+/// Accepts both the legacy syntax, `rgb(255, 0, 0, 0.5)`, and the modern one, `rgb(255 0 0 / 50%)`.
+fn split_color_arguments(arguments: &[Token], component_count: usize) -> Option<ColorArguments<'_>> {
+    let is_legacy = arguments.iter().any(|argument| argument.kind == TokenKind::Comma);
+
+    if is_legacy {
+        let values: Vec<&Token> = arguments.iter().step_by(2).collect();
+        let commas_valid = arguments.iter().skip(1).step_by(2).all(|argument| argument.kind == TokenKind::Comma);
+        if !commas_valid || arguments.len().is_multiple_of(2) || !(component_count..=component_count + 1).contains(&values.len()) {
+            return None;
+        }
+
+        if values.iter().any(|value| value.kind == TokenKind::Identifier) {
+            return None;
+        }
+
+        return Some(ColorArguments {
+            components: values[..component_count].to_vec(),
+            alpha: values.get(component_count).cloned(),
+            is_legacy: true
+        });
+    }
+
+    let slash_position = arguments.iter().position(|argument| argument.kind == TokenKind::Delimeter && argument.value == "/");
+    let (components, alpha) = match slash_position {
+        Some(position) if position + 2 == arguments.len() => (&arguments[..position], Some(&arguments[position + 1])),
+        Some(_) => return None,
+        None => (arguments, None)
+    };
+
+    if components.len() != component_count {
+        return None;
+    }
+
+    return Some(ColorArguments {
+        components: components.iter().collect(),
+        alpha: alpha,
+        is_legacy: false
+    });
+}
+
+/// Returns whether given component of a color function is the `none` keyword, which means zero.
+fn is_none_component(token: &Token) -> bool {
+    return token.kind == TokenKind::Identifier && token.value.eq_ignore_ascii_case("none");
+}
+
+/// Returns the value of an alpha component, between 0 and 255. A missing alpha means an opaque color.
+fn parse_alpha_component(alpha: Option<&Token>) -> Option<u8> {
+    let alpha = match alpha {
+        Some(alpha) => alpha,
+        None => return Some(255)
+    };
+
+    let value = match alpha.kind {
+        TokenKind::Integer | TokenKind::Number => alpha.value.parse::<f64>().ok()?,
+        TokenKind::Percentage => alpha.value.parse::<f64>().ok()? / 100.0,
+        TokenKind::Identifier if is_none_component(alpha) => 0.0,
+        _ => return None
+    };
+
+    return Some((value.clamp(0.0, 1.0) * 255.0).round() as u8);
+}
+
+/// Returns a color from components of the `rgb()` function, each being a number from 0 to 255 or a percentage.
+fn parse_rgb_components(arguments: &ColorArguments) -> Option<color::Color> {
+    let mut channels = [0u8; 3];
+
+    // The legacy syntax does not allow mixing numbers and percentages.
+    let first_is_percentage = arguments.components[0].kind == TokenKind::Percentage;
+
+    for (idx, component) in arguments.components.iter().enumerate() {
+        if arguments.is_legacy && (component.kind == TokenKind::Percentage) != first_is_percentage {
+            return None;
+        }
+
+        let value = match component.kind {
+            TokenKind::Integer | TokenKind::Number => component.value.parse::<f64>().ok()?,
+            TokenKind::Percentage => component.value.parse::<f64>().ok()? * 255.0 / 100.0,
+            TokenKind::Identifier if is_none_component(component) => 0.0,
+            _ => return None
+        };
+
+        channels[idx] = value.round().clamp(0.0, 255.0) as u8;
+    }
+
+    return Some(color::Color::from_rgba(channels[0], channels[1], channels[2], parse_alpha_component(arguments.alpha)?));
+}
+
+/// Returns a color from components of the `hsl()` function: a hue, which is an angle or a number
+/// of degrees, and saturation and lightness, which are percentages, or numbers from 0 to 100 in the modern syntax.
+fn parse_hsl_components(arguments: &ColorArguments) -> Option<color::Color> {
+    let components = &arguments.components;
+    let hue = match components[0].kind {
+        TokenKind::Integer | TokenKind::Number => components[0].value.parse::<f64>().ok()?,
+        TokenKind::Dimension => {
+            let value: f64 = components[0].value.parse().ok()?;
+            value * match components[0].unit.to_ascii_lowercase().as_str() {
+                "deg" => 1.0,
+                "grad" => 360.0 / 400.0,
+                "rad" => 180.0 / std::f64::consts::PI,
+                "turn" => 360.0,
+                _ => return None
+            }
+        }
+
+        TokenKind::Identifier if is_none_component(components[0]) => 0.0,
+        _ => return None
+    };
+
+    let mut percentages = [0.0; 2];
+    for (idx, component) in components[1..].iter().enumerate() {
+        percentages[idx] = match component.kind {
+            TokenKind::Percentage => component.value.parse::<f64>().ok()? / 100.0,
+            TokenKind::Integer | TokenKind::Number if !arguments.is_legacy => component.value.parse::<f64>().ok()? / 100.0,
+            TokenKind::Identifier if is_none_component(component) => 0.0,
+            _ => return None
+        };
+    }
+
+    return Some(color::Color::from_hsl(hue, percentages[0], percentages[1], parse_alpha_component(arguments.alpha)?));
+}
+
+/// Returns a color from components of the `device-cmyk()` function, each being a number from 0 to 1 or a percentage.
+fn parse_cmyk_components(arguments: &ColorArguments) -> Option<color::Color> {
+    let mut components = [0.0; 4];
+
+    for (idx, component) in arguments.components.iter().enumerate() {
+        let value = match component.kind {
+            TokenKind::Integer | TokenKind::Number => component.value.parse::<f64>().ok()?,
+            TokenKind::Percentage => component.value.parse::<f64>().ok()? / 100.0,
+            TokenKind::Identifier if is_none_component(component) => 0.0,
+            _ => return None
+        };
+
+        components[idx] = value.clamp(0.0, 1.0);
+    }
+
+    let cmyk = color::Cmyk {
+        cyan: components[0],
+        magenta: components[1],
+        yellow: components[2],
+        key: components[3]
+    };
+
+    return Some(color::Color::from_cmyk(cmyk, parse_alpha_component(arguments.alpha)?));
+}
+
+/// Returns the index of the parenthesis closing a function or a block whose contents start at `start`.
+pub fn find_closing_parenthesis(tokens: &[Token], start: usize) -> Option<usize> {
+    let mut depth = 1;
+
+    for (idx, token) in tokens.iter().enumerate().skip(start) {
+        match token.kind {
+            TokenKind::Function | TokenKind::ParenthesisOpen => depth += 1,
+            TokenKind::ParenthesisClose => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(idx);
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    return None;
+}
+
+/// Splits tokens into component values, each being either a single token or a function
+/// with all its arguments. Whitespace between them is dropped.
+pub fn split_component_values(tokens: &[Token]) -> Vec<Vec<Token>> {
+    let mut components = Vec::<Vec<Token>>::new();
+    let mut idx = 0;
+
+    while idx < tokens.len() {
+        let end = match tokens[idx].kind {
+            TokenKind::Whitespace => {
+                idx += 1;
+                continue;
+            }
+
+            TokenKind::Function | TokenKind::ParenthesisOpen => find_closing_parenthesis(tokens, idx + 1).unwrap_or(tokens.len() - 1),
+            _ => idx
+        };
+
+        components.push(tokens[idx..=end].to_vec());
+        idx = end + 1;
+    }
+
+    return components;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::css::parser::parse_property_value;
+    use crate::css::tokenizer::{tokenize, TokenKind};
+
+    /// Parses given value of the `color` property, returning its red, green, blue and alpha channels.
+    fn parse_color(value: &str) -> Option<(u8, u8, u8, u8)> {
+        let tokens: Vec<_> = tokenize(value).into_iter().filter(|token| token.kind != TokenKind::EndOfInput).collect();
+        let values = parse_property_value("color", &tokens)?;
+        let color = values[0].1.as_color();
+        return Some((color.red, color.green, color.blue, color.alpha));
+    }
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(parse_color("#f00"), Some((255, 0, 0, 255)));
+        assert_eq!(parse_color("#F008"), Some((255, 0, 0, 136)));
+        assert_eq!(parse_color("#FF8000"), Some((255, 128, 0, 255)));
+        assert_eq!(parse_color("#ff800080"), Some((255, 128, 0, 128)));
+
+        for invalid in &["#", "#f", "#ff", "#fffff", "#fffffff", "#fffffffff", "#ggg", "#\u{e9}a", "#\u{e9}\u{e9}\u{e9}"] {
+            assert_eq!(parse_color(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn named_colors() {
+        assert_eq!(parse_color("red"), Some((255, 0, 0, 255)));
+        assert_eq!(parse_color("RebeccaPurple"), Some((102, 51, 153, 255)));
+        assert_eq!(parse_color("LIGHTGOLDENRODYELLOW"), Some((250, 250, 210, 255)));
+        assert_eq!(parse_color("transparent"), Some((0, 0, 0, 0)));
+        assert_eq!(parse_color("reddish"), None);
+    }
+
+    #[test]
+    fn rgb_functions() {
+        assert_eq!(parse_color("rgb(255, 128, 0)"), Some((255, 128, 0, 255)));
+        assert_eq!(parse_color("rgba(255, 128, 0, 0.5)"), Some((255, 128, 0, 128)));
+        assert_eq!(parse_color("rgb(255 128 0 / 50%)"), Some((255, 128, 0, 128)));
+        assert_eq!(parse_color("RGBA(255 128 0)"), Some((255, 128, 0, 255)));
+        assert_eq!(parse_color("rgb(100%, 50%, 0%)"), Some((255, 128, 0, 255)));
+        assert_eq!(parse_color("rgb(none 50% 0)"), Some((0, 128, 0, 255)));
+
+        // Values out of range are clamped.
+        assert_eq!(parse_color("rgb(300, -20, 127.6)"), Some((255, 0, 128, 255)));
+        assert_eq!(parse_color("rgb(0 0 0 / 2)"), Some((0, 0, 0, 255)));
+        assert_eq!(parse_color("rgb(0 0 0 / -50%)"), Some((0, 0, 0, 0)));
+
+        // The legacy syntax does not allow mixing numbers and percentages, or `none`.
+        assert_eq!(parse_color("rgb(100%, 0, 0)"), None);
+        assert_eq!(parse_color("rgb(100% 0 0)"), Some((255, 0, 0, 255)));
+        assert_eq!(parse_color("rgb(none, 0, 0)"), None);
+
+        assert_eq!(parse_color("rgb(255, 0 0)"), None);
+        assert_eq!(parse_color("rgb(255, 0, 0, 1, 1)"), None);
+        assert_eq!(parse_color("rgb(255 0 0 / 1 / 1)"), None);
+        assert_eq!(parse_color("rgb(255 0)"), None);
+    }
+
+    #[test]
+    fn hsl_functions() {
+        assert_eq!(parse_color("hsl(120, 100%, 50%)"), Some((0, 255, 0, 255)));
+        assert_eq!(parse_color("hsla(120, 100%, 50%, 0.25)"), Some((0, 255, 0, 64)));
+        assert_eq!(parse_color("hsl(120deg 100% 50% / 25%)"), Some((0, 255, 0, 64)));
+        assert_eq!(parse_color("hsl(0.5turn 100 50)"), Some((0, 255, 255, 255)));
+        assert_eq!(parse_color("hsl(-120, 100%, 50%)"), Some((0, 0, 255, 255)));
+        assert_eq!(parse_color("hsl(0, 150%, 50%)"), Some((255, 0, 0, 255)));
+        assert_eq!(parse_color("hsl(0, 0%, 150%)"), Some((255, 255, 255, 255)));
+
+        // Saturation and lightness have to be percentages in the legacy syntax.
+        assert_eq!(parse_color("hsl(120, 100, 50)"), None);
+        assert_eq!(parse_color("hsl(120px 100% 50%)"), None);
+    }
+}
@@ -0,0 +1,65 @@
+use crate::css::tokenizer::{Token, TokenKind};
+use crate::css::value_parser::find_closing_parenthesis;
+
+// Custom properties and var() substitution.
+// https://www.w3.org/TR/css-variables-1/
+
+/// Returns whether given name is a name of a custom property, like `--brand-color`.
+pub fn is_custom_property(name: &str) -> bool {
+    return name.starts_with("--");
+}
+
+/// Returns whether given tokens contain a `var()` function.
+pub fn contains_variable(tokens: &[Token]) -> bool {
+    return tokens.iter().any(is_variable_function);
+}
+
+fn is_variable_function(token: &Token) -> bool {
+    return token.kind == TokenKind::Function && token.value.eq_ignore_ascii_case("var");
+}
+
+/// Replaces every `var(--name)` or `var(--name, fallback)` in given tokens with the value
+/// of the custom property returned by `lookup`, or with the fallback if there is no such property.
+///
+/// Returns `None` if a property is not defined and the `var()` has no fallback, or if a `var()` is malformed.
+/// https://www.w3.org/TR/css-variables-1/#substitute-a-var
+pub fn substitute_variables(tokens: &[Token], lookup: &mut dyn FnMut(&str) -> Option<Vec<Token>>) -> Option<Vec<Token>> {
+    let mut result = Vec::<Token>::new();
+    let mut idx = 0;
+
+    while idx < tokens.len() {
+        if !is_variable_function(&tokens[idx]) {
+            result.push(tokens[idx].clone());
+            idx += 1;
+            continue;
+        }
+
+        let end = find_closing_parenthesis(tokens, idx + 1)?;
+        let mut arguments = tokens[idx + 1..end].iter().skip_while(|token| token.kind == TokenKind::Whitespace);
+
+        let name = match arguments.next() {
+            Some(token) if token.kind == TokenKind::Identifier && is_custom_property(&token.value) => &token.value,
+            _ => return None
+        };
+
+        let mut arguments = arguments.skip_while(|token| token.kind == TokenKind::Whitespace);
+        let fallback: Option<Vec<Token>> = match arguments.next() {
+            None => None,
+            Some(token) if token.kind == TokenKind::Comma => Some(arguments.cloned().collect()),
+            Some(_) => return None
+        };
+
+        let value = match (lookup(name), fallback) {
+            (Some(value), _) => value,
+
+            // The fallback can contain variables too.
+            (None, Some(fallback)) => substitute_variables(&fallback, lookup)?,
+            (None, None) => return None
+        };
+
+        result.extend(value);
+        idx = end + 1;
+    }
+
+    return Some(result);
+}
@@ -0,0 +1,157 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+pub type DiagnosticsRef = Rc<RefCell<Diagnostics>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+
+    /// Something was ignored or replaced with a fallback, but the document could still be converted.
+    Warning,
+
+    /// The converted document is known to be incomplete or incorrect.
+    Error
+}
+
+/// Position in a source text (e.g. a stylesheet). Both values start at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub line: usize,
+    pub column: usize
+}
+
+/// A single problem found during the conversion.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+
+    /// Name of the file the problem was found in (e.g. an external stylesheet),
+    /// unless it is the converted document itself.
+    pub source: Option<String>,
+
+    /// Where in the source text the problem was found, if known.
+    pub location: Option<SourceLocation>,
+
+    /// Index of the HTML element the problem relates to, if any.
+    pub element: Option<usize>
+}
+
+/// List of all problems found during the conversion.
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    entries: Vec<Diagnostic>
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: String) -> Self {
+        Self {
+            severity: severity,
+            message: message,
+            source: None,
+            location: None,
+            element: None
+        }
+    }
+
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    pub fn with_location(mut self, location: SourceLocation) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    pub fn with_element(mut self, element: usize) -> Self {
+        self.element = Some(element);
+        self
+    }
+}
+
+impl Diagnostics {
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.entries.push(diagnostic);
+    }
+
+    pub fn warning(&mut self, message: String) {
+        self.push(Diagnostic::new(Severity::Warning, message));
+    }
+
+    pub fn error(&mut self, message: String) {
+        self.push(Diagnostic::new(Severity::Error, message));
+    }
+
+    pub fn warning_at(&mut self, location: SourceLocation, message: String) {
+        self.push(Diagnostic::new(Severity::Warning, message).with_location(location));
+    }
+
+    /// Moves all diagnostics from `other` into this list.
+    pub fn append(&mut self, other: &mut Diagnostics) {
+        self.entries.append(&mut other.entries);
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        return self.entries.iter();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.entries.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.entries.is_empty();
+    }
+
+    /// Returns whether any diagnostic has the `Error` severity.
+    pub fn has_errors(&self) -> bool {
+        return self.entries.iter().any(|d| d.severity == Severity::Error);
+    }
+
+    /// Returns whether the conversion should be considered failed, that is
+    /// if there are any errors or, when `warnings_as_errors` is set, any diagnostics at all.
+    pub fn is_failure(&self, warnings_as_errors: bool) -> bool {
+        if warnings_as_errors {
+            return !self.is_empty();
+        }
+
+        return self.has_errors();
+    }
+
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new()
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error")
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.severity)?;
+
+        if let Some(source) = &self.source {
+            write!(f, " in {}", source)?;
+        }
+
+        if let Some(location) = &self.location {
+            write!(f, " at {}:{}", location.line, location.column)?;
+        }
+
+        if let Some(element) = self.element {
+            write!(f, " (element #{})", element)?;
+        }
+
+        return write!(f, ": {}", self.message);
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+use std::io;
+use crate::diagnostics::Diagnostics;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Reasons why a conversion can fail.
+#[derive(Debug)]
+pub enum Error {
+
+    /// Reading the input or writing the output failed.
+    Io(io::Error),
+
+    /// No input document was given to the converter.
+    MissingInput,
+
+    /// The HTML document could not be parsed.
+    Html(String),
+
+    /// A font could not be loaded.
+    Font(String),
+
+    /// The PDF document could not be generated or saved.
+    Pdf(String),
+
+    /// The conversion reported errors (or warnings, when they are treated as errors).
+    Diagnostics(Diagnostics)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::MissingInput => f.write_str("no input document was specified"),
+            Error::Html(message) => write!(f, "unable to parse HTML: {}", message),
+            Error::Font(message) => write!(f, "unable to load font: {}", message),
+            Error::Pdf(message) => write!(f, "unable to generate PDF: {}", message),
+            Error::Diagnostics(diagnostics) => {
+                write!(f, "conversion failed with {} problem(s)", diagnostics.len())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<printpdf::Error> for Error {
+    fn from(e: printpdf::Error) -> Self {
+        Error::Pdf(e.to_string())
+    }
+}
+
+impl From<lopdf::Error> for Error {
+    fn from(e: lopdf::Error) -> Self {
+        Error::Pdf(e.to_string())
+    }
+}
@@ -1,6 +1,8 @@
 use crate::html;
 use crate::layout;
 use crate::generator::ResourcesManagerRef;
+use crate::generator::ConversionOptions;
+use crate::diagnostics::DiagnosticsRef;
 
 /// Provides a storage for all conversion-related things.
 pub struct ConversionContext {
@@ -8,10 +10,16 @@ pub struct ConversionContext {
     /// The HTML document that is being converted.
     pub document: html::DocumentRef,
 
-    /// Output from the layout engine.
-    pub root_element: Option<layout::Element>,
+    /// Output from the layout engine, the laid out pages.
+    pub pages: Option<Vec<layout::Page>>,
 
     /// External resources manager.
     pub resources_manager: Option<ResourcesManagerRef>,
 
+    /// User-provided conversion settings.
+    pub options: ConversionOptions,
+
+    /// Collects problems found during the conversion.
+    pub diagnostics: DiagnosticsRef,
+
 }
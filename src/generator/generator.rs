@@ -1,15 +1,83 @@
 #![allow(unused)]
 
+use std::io::{BufWriter, Write};
 use crate::html;
+use crate::error::{Error, Result};
 use crate::generator::context;
 use crate::generator::render_engine;
+use crate::generator::ConversionOptions;
+use crate::generator::transparency::{GraphicsStateChanges, apply_graphics_state_changes};
 use printpdf::*;
 
-pub fn generate_pdf(context: context::ConversionContext) -> PdfDocumentReference {
-    let pdf = PdfDocument::empty("Title");
+/// A rendered PDF document which has not been saved yet.
+pub struct RenderedPdf {
+    pub pdf: PdfDocumentReference,
 
-    let mut renderer = render_engine::Engine::new(context);
-    renderer.render(&pdf);
+    /// Transparency that has to be applied once the document is saved, see `apply_graphics_state_changes`.
+    pub graphics_state_changes: GraphicsStateChanges
+}
+
+pub fn generate_pdf(context: context::ConversionContext) -> Result<RenderedPdf> {
+    let pdf = PdfDocument::empty(context.options.title.as_str());
+
+    let mut renderer = render_engine::Engine::new(context)?;
+    renderer.render(&pdf)?;
+
+    return Ok(RenderedPdf {
+        pdf: pdf,
+        graphics_state_changes: renderer.take_graphics_state_changes()
+    });
+}
+
+/// Serializes given document into `target`.
+/// Things that printpdf has no API for (e.g. the author or transparency) are filled in afterwards.
+pub fn save_pdf(rendered: RenderedPdf, options: &ConversionOptions, mut target: &mut dyn Write) -> Result<()> {
+    let has_transparency = rendered.graphics_state_changes.iter().any(|changes| !changes.is_empty());
+    if options.author.is_none() && !has_transparency {
+        let mut writer = BufWriter::new(target);
+        rendered.pdf.save(&mut writer)?;
+        writer.flush()?;
+        return Ok(());
+    }
+
+    // PDF/X-3, which printpdf makes documents conform to, does not allow transparency. Without the
+    // conformance the document has neither the output intent nor the XMP metadata that claim it.
+    let pdf = if has_transparency {
+        rendered.pdf.with_conformance(PdfConformance::Custom(CustomPdfConformance::default()))
+    } else {
+        rendered.pdf
+    };
+
+    let mut bytes = Vec::new();
+    pdf.save(&mut BufWriter::new(&mut bytes))?;
+
+    // printpdf has no API for these, so the saved document is parsed again to fill them in.
+    let mut document = lopdf::Document::load_mem(&bytes)?;
+    let info_id = document.trailer.get(b"Info")?.as_reference()?;
+
+    if has_transparency {
+        apply_graphics_state_changes(&mut document, &rendered.graphics_state_changes)?;
+
+        // printpdf writes the version even for documents that do not conform to any PDF/X standard.
+        document.get_object_mut(info_id)?.as_dict_mut()?.remove(b"GTS_PDFXVersion");
+    }
+
+    if let Some(author) = &options.author {
+        document.get_object_mut(info_id)?
+            .as_dict_mut()?
+            .set("Author", lopdf::Object::String(encode_text_string(author), lopdf::StringFormat::Hexadecimal));
+    }
+
+    document.save_to(&mut target)?;
+    return Ok(());
+}
+
+/// Encodes text for a PDF text string, as UTF-16BE with a byte order mark so any character can be used.
+fn encode_text_string(text: &str) -> Vec<u8> {
+    let mut bytes = vec![0xFE, 0xFF];
+    for unit in text.encode_utf16() {
+        bytes.extend_from_slice(&unit.to_be_bytes());
+    }
 
-    return pdf;
+    return bytes;
 }
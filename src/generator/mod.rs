@@ -1,8 +1,16 @@
 pub mod context;
+pub mod options;
+
+// The entry points of PDF generation, which this module re-exports.
+#[allow(clippy::module_inception)]
 pub mod generator;
 pub mod preprocessor;
 pub mod resources_manager;
+pub mod resource_resolver;
 pub mod render_engine;
+pub mod transparency;
 
-pub use self::generator::generate_pdf;
-pub use self::resources_manager::{ResourcesManager, ResourcesManagerRef};
\ No newline at end of file
+pub use self::generator::{generate_pdf, save_pdf};
+pub use self::options::{ColorMode, ConversionOptions, PageMargins, PageOrientation, PageSize};
+pub use self::resources_manager::{ResourcesManager, ResourcesManagerRef};
+pub use self::resource_resolver::{ResourceResolver, FileResourceResolver, resolve_relative_path};
\ No newline at end of file
@@ -0,0 +1,139 @@
+/// Dimensions of a page in millimeters, in portrait orientation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageSize {
+    pub width: f64,
+    pub height: f64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageOrientation {
+    Portrait,
+    Landscape
+}
+
+/// Page margins in millimeters.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct PageMargins {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64
+}
+
+/// Color space colors are written to the PDF in.
+///
+/// Colors are only written in the device RGB and device CMYK color spaces.
+/// Spot colors (`Separation` color spaces) are not supported: CSS has no way to give them yet,
+/// and printpdf can only write them as process CMYK colors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorMode {
+
+    /// Colors given with `device-cmyk()` are written in CMYK, all other colors in RGB.
+    AsSpecified,
+
+    /// All colors are converted to CMYK, as print production usually requires.
+    Cmyk
+}
+
+/// Settings that control how a document is converted.
+#[derive(Debug, Clone)]
+pub struct ConversionOptions {
+    pub page_size: PageSize,
+    pub orientation: PageOrientation,
+    pub margins: PageMargins,
+
+    /// Name of the font used by elements that do not specify one, a family or a generic family like `serif`.
+    pub default_font: String,
+
+    /// Title written into PDF's metadata.
+    pub title: String,
+
+    /// Author written into PDF's metadata.
+    pub author: Option<String>,
+    pub color_mode: ColorMode
+}
+
+impl PageSize {
+    pub const A3: PageSize = PageSize { width: 297.0, height: 420.0 };
+    pub const A4: PageSize = PageSize { width: 210.0, height: 297.0 };
+    pub const A5: PageSize = PageSize { width: 148.0, height: 210.0 };
+    pub const B4: PageSize = PageSize { width: 250.0, height: 353.0 };
+    pub const B5: PageSize = PageSize { width: 176.0, height: 250.0 };
+    pub const JIS_B4: PageSize = PageSize { width: 257.0, height: 364.0 };
+    pub const JIS_B5: PageSize = PageSize { width: 182.0, height: 257.0 };
+    pub const LETTER: PageSize = PageSize { width: 215.9, height: 279.4 };
+    pub const LEGAL: PageSize = PageSize { width: 215.9, height: 355.6 };
+    pub const LEDGER: PageSize = PageSize { width: 279.4, height: 431.8 };
+
+    /// Returns one of the predefined page sizes by its (case-insensitive) name,
+    /// which are the names CSS uses: https://www.w3.org/TR/css-page-3/#typedef-page-size-page-size
+    pub fn from_name(name: &str) -> Option<PageSize> {
+        match name.to_ascii_lowercase().as_str() {
+            "a3" => Some(PageSize::A3),
+            "a4" => Some(PageSize::A4),
+            "a5" => Some(PageSize::A5),
+            "b4" => Some(PageSize::B4),
+            "b5" => Some(PageSize::B5),
+            "jis-b4" => Some(PageSize::JIS_B4),
+            "jis-b5" => Some(PageSize::JIS_B5),
+            "letter" => Some(PageSize::LETTER),
+            "legal" => Some(PageSize::LEGAL),
+            "ledger" => Some(PageSize::LEDGER),
+            _ => None
+        }
+    }
+}
+
+impl PageMargins {
+    pub fn uniform(value: f64) -> Self {
+        Self {
+            top: value,
+            right: value,
+            bottom: value,
+            left: value
+        }
+    }
+}
+
+impl ConversionOptions {
+
+    /// Returns the width of a page, taking orientation into account.
+    pub fn page_width(&self) -> f64 {
+        match self.orientation {
+            PageOrientation::Portrait => self.page_size.width,
+            PageOrientation::Landscape => self.page_size.height
+        }
+    }
+
+    /// Returns the height of a page, taking orientation into account.
+    pub fn page_height(&self) -> f64 {
+        match self.orientation {
+            PageOrientation::Portrait => self.page_size.height,
+            PageOrientation::Landscape => self.page_size.width
+        }
+    }
+
+    /// Returns the width of the page area, which is the page without its left and right margins.
+    pub fn content_width(&self) -> f64 {
+        return self.page_width() - self.margins.left - self.margins.right;
+    }
+
+    /// Returns the height of the page area, which is the page without its top and bottom margins.
+    pub fn content_height(&self) -> f64 {
+        return self.page_height() - self.margins.top - self.margins.bottom;
+    }
+}
+
+impl Default for ConversionOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::A4,
+            orientation: PageOrientation::Portrait,
+            margins: PageMargins::default(),
+            default_font: String::from("sans-serif"),
+            title: String::from("Title"),
+            author: None,
+            color_mode: ColorMode::AsSpecified
+        }
+    }
+}
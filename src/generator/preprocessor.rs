@@ -1,62 +1,124 @@
 use std::rc::Rc;
 use std::cell::RefCell;
-use font_kit;
+use std::collections::HashSet;
 use crate::html;
-use crate::generator::resources_manager::{ResourcesManager, ResourcesManagerRef, FontResource};
-use crate::utils::font;
+use crate::diagnostics::{Diagnostic, DiagnosticsRef, Severity};
+use crate::generator::resources_manager::ResourcesManagerRef;
+use crate::generator::ConversionOptions;
 
 /// Preprocessor's responsibility is to go through each node in an HTML document
 /// and load all external resources like fonts, images etc. so they can be immediately used
 /// by the layout and render engines.
 pub struct Preprocessor {
-    document: html::DocumentRef
+    document: html::DocumentRef,
+    resources_manager: ResourcesManagerRef,
+    options: ConversionOptions,
+    diagnostics: DiagnosticsRef
 }
 
 impl Preprocessor {
     pub fn process_document(&self) -> ResourcesManagerRef {
-        let mut manager = Rc::new(RefCell::new(ResourcesManager::new()));
+        let mut manager = Rc::clone(&self.resources_manager);
+        let mut attempted_fonts = HashSet::new();
         let document = self.document.borrow();
-        self.process_element(document.get_root_immutable(), &mut manager);
+        self.process_element(document.get_root_immutable(), &mut manager, &mut attempted_fonts);
+        self.process_page_rules(&document, &mut manager, &mut attempted_fonts);
         return manager;
     }
 
-    fn process_element(&self, element: &html::Element, manager: &mut ResourcesManagerRef) {
+    /// Loads fonts of the margin boxes, which get their `font-family` from the margin rules
+    /// or from the `@page` rules they are in.
+    fn process_page_rules(&self, document: &html::Document, manager: &mut ResourcesManagerRef, attempted_fonts: &mut HashSet<String>) {
+        let has_margin_boxes = document.get_page_rules().iter().any(|rule| !rule.margin_rules.is_empty());
+        if !has_margin_boxes {
+            return;
+        }
+
+        let declarations = document.get_page_rules().iter()
+            .flat_map(|rule| rule.declarations.iter().chain(rule.margin_rules.iter().flat_map(|margin_rule| margin_rule.declarations.iter())))
+            .filter(|declaration| declaration.name == "font-family" && declaration.value.is_list());
+
+        let mut font_lists: Vec<Vec<String>> = declarations
+            .map(|declaration| declaration.value.as_items().iter().map(|family| family.as_string().clone()).collect())
+            .collect();
+
+        // Margin boxes without a `font-family` use the default font.
+        font_lists.push(Vec::new());
+
+        for font_names in font_lists {
+            self.load_first_font(font_names, None, manager, attempted_fonts);
+        }
+    }
+
+    fn process_element(&self, element: &html::Element, manager: &mut ResourcesManagerRef, attempted_fonts: &mut HashSet<String>) {
         let document = self.document.borrow();
 
+        // Elements that are not displayed do not need any resources.
+        if !element.is_displayed() {
+            return;
+        }
+
         if element.is_text_node() {
-            let font_name = self.get_font_name(element);
-            let mut font_file_path = String::from("");
-            font_file_path.push_str(".\\test_font\\");
-            font_file_path.push_str(&font_name);
-            font_file_path.push_str(".ttf");
-
-            if let Ok(_font) = font_kit::loaders::default::Font::from_path(&font_file_path, 0) {
-                manager.borrow_mut().add_font(font_name.clone(), FontResource {
-                    name: font_name,
-                    font: font::Font::wrap(_font),
-                    data: std::fs::File::open(font_file_path).unwrap()
-                });
-            } else {
-                println!("error: unable to load font {}", font_file_path);
-            }
+            self.load_first_font(element.get_font_families(), Some(element.index), manager, attempted_fonts);
         }
 
         for child_index in &element.children {
-            self.process_element(document.get_element_immutable(*child_index), manager);
+            self.process_element(document.get_element_immutable(*child_index), manager, attempted_fonts);
         }
     }
 
-    fn get_font_name(&self, element: &html::Element) -> String {
-        if let Some(font_prop) = element.get_style_property("font") {
-            return font_prop.as_string().clone();
+    /// Loads the first of given font families that is available, or the default font.
+    /// Fonts that have failed to load before are not attempted again.
+    fn load_first_font(&self, mut font_names: Vec<String>, element_index: Option<usize>, manager: &mut ResourcesManagerRef, attempted_fonts: &mut HashSet<String>) {
+        font_names.push(self.options.default_font.clone());
+
+        for font_name in font_names {
+            if manager.borrow().has_font(&font_name) {
+                break;
+            }
+
+            if attempted_fonts.insert(font_name.clone()) && self.load_font(font_name, element_index, manager) {
+                break;
+            }
         }
+    }
+
+    /// Loads the font file of given font, through the resource resolver, and registers it in the resources manager.
+    /// Returns whether the font has been loaded. Failures are reported for the element
+    /// at `element_index`, if the font is used by one. Text has no metrics without the default font,
+    /// so failing to load it is an error.
+    fn load_font(&self, font_name: String, element_index: Option<usize>, manager: &mut ResourcesManagerRef) -> bool {
+        let font_data = manager.borrow().load_font(&font_name);
+        let is_default_font = font_name == self.options.default_font;
+
+        let diagnostic = match font_data {
+            Some(data) => {
+                if manager.borrow_mut().add_font_data(font_name.clone(), data) {
+                    return true;
+                }
 
-        return String::from("Arial");
+                Diagnostic::new(Severity::Error, format!("the font file of {} is not a valid font", font_name))
+            }
+
+            None if is_default_font => Diagnostic::new(Severity::Error, format!("unable to find the default font {}", font_name)),
+            None => Diagnostic::new(Severity::Warning, format!("unable to find font {}, using a fallback font", font_name))
+        };
+
+        let diagnostic = match element_index {
+            Some(element_index) => diagnostic.with_element(element_index),
+            None => diagnostic
+        };
+
+        self.diagnostics.borrow_mut().push(diagnostic);
+        return false;
     }
 
-    pub fn new(document: Rc<RefCell<html::Document>>) -> Preprocessor {
+    pub fn new(document: Rc<RefCell<html::Document>>, resources_manager: ResourcesManagerRef, options: ConversionOptions, diagnostics: DiagnosticsRef) -> Preprocessor {
         Preprocessor {
-            document: document
+            document: document,
+            resources_manager: resources_manager,
+            options: options,
+            diagnostics: diagnostics
         }
     }
-}
\ No newline at end of file
+}
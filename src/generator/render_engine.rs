@@ -3,117 +3,330 @@
 use crate::html;
 use crate::layout;
 use crate::generator::context;
+use crate::generator::{ColorMode, ConversionOptions};
 use crate::generator::resources_manager::{ResourcesManagerRef, FontResource};
-use crate::utils::{font, color};
+use crate::generator::transparency::{GraphicsStateChange, GraphicsStateChanges};
+use crate::utils::{font, color, units};
+use crate::diagnostics::{Diagnostic, DiagnosticsRef, Severity};
+use crate::error::{Error, Result};
 use printpdf::*;
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 pub struct Engine {
     document: html::DocumentRef,
     resource_manager: ResourcesManagerRef,
-    root_element: layout::Element,
+    layout_pages: Vec<layout::Page>,
+    options: ConversionOptions,
+    diagnostics: DiagnosticsRef,
     pages: Vec<DrawTargetPage>,
     fonts: HashMap<String, IndirectFontRef>,
-    fallback_font: Option<IndirectFontRef>
+    fallback_font: Option<IndirectFontRef>,
+
+    /// Transparency used on each page, applied after the document is saved.
+    graphics_state_changes: RefCell<GraphicsStateChanges>,
+
+    /// Border styles that were drawn as solid borders, each is reported once.
+    reported_border_styles: RefCell<HashSet<String>>
 }
 
 struct DrawTargetPage {
     page: PdfPageReference,
     layer: PdfLayerReference,
+    geometry: layout::PageGeometry
 }
 
 impl Engine {
-    fn add_page(&mut self, pdf: &PdfDocumentReference) -> &DrawTargetPage {
-        let (index, layer_index) = pdf.add_page(Mm(210.0), Mm(297.0), "MainLayer");
+    fn add_page(&mut self, pdf: &PdfDocumentReference, geometry: layout::PageGeometry) {
+        let (index, layer_index) = pdf.add_page(Mm(self.pt_to_mm(geometry.width)), Mm(self.pt_to_mm(geometry.height)), "MainLayer");
         let page = DrawTargetPage {
             page: pdf.get_page(index),
-            layer: pdf.get_page(index).get_layer(layer_index)
+            layer: pdf.get_page(index).get_layer(layer_index),
+            geometry: geometry
         };
 
         self.pages.push(page);
-        return self.pages.last().unwrap();
+        self.graphics_state_changes.borrow_mut().push(Vec::new());
     }
 
     fn get_page(&self, index: usize) -> &DrawTargetPage {
         return &self.pages[index];
     }
 
-    pub fn render(&mut self, pdf: &PdfDocumentReference) {
+    pub fn render(&mut self, pdf: &PdfDocumentReference) -> Result<()> {
 
-        // Prepare all fonts.
+        // Prepare all fonts. Text using fonts that could not be embedded
+        // is drawn with the fallback font instead.
         for font in self.resource_manager.borrow().fonts.values() {
-            self.fonts.insert(font.name.clone(), pdf.add_external_font(&font.data).unwrap());
+            match pdf.add_external_font(font.data.as_slice()) {
+                Ok(font_ref) => {
+                    self.fonts.insert(font.name.clone(), font_ref);
+                }
+
+                Err(e) => {
+                    self.diagnostics.borrow_mut().error(format!("unable to embed font {}: {}", font.name, e));
+                }
+            }
+        }
+
+        self.fallback_font = Some(pdf.add_builtin_font(BuiltinFont::TimesRoman)?);
+
+        for page_index in 0..self.layout_pages.len() {
+            self.add_page(pdf, self.layout_pages[page_index].geometry);
+            self.draw_element(page_index, &self.layout_pages[page_index].root_element);
+
+            for margin_box in &self.layout_pages[page_index].margin_boxes {
+                self.draw_margin_box(page_index, margin_box);
+            }
+        }
+
+        return Ok(());
+    }
+
+    /// Returns the changes of the graphics state made on each page, see `transparency::apply_graphics_state_changes`.
+    pub fn take_graphics_state_changes(&mut self) -> GraphicsStateChanges {
+        return self.graphics_state_changes.replace(Vec::new());
+    }
+
+    /// Changes the graphics state of given page in a way printpdf does not support.
+    /// A `gs` operator is added to the page's content and replaced when the document is saved.
+    fn add_graphics_state_change(&self, page_index: usize, change: GraphicsStateChange) {
+        self.get_page(page_index).layer.set_blend_mode(BlendMode::Seperable(SeperableBlendMode::Normal));
+        self.graphics_state_changes.borrow_mut()[page_index].push(change);
+    }
+
+    /// Calls `draw` to fill something with given color, applying the color's alpha.
+    fn fill_with_alpha<F: FnOnce()>(&self, page_index: usize, color: &color::Color, draw: F) {
+        let page = self.get_page(page_index);
+        page.layer.set_fill_color(self.color_to_printpdf_color(color));
+
+        if color.alpha == 255 {
+            draw();
+            return;
         }
 
-        self.fallback_font = Some(pdf.add_builtin_font(BuiltinFont::TimesRoman).unwrap());
+        page.layer.save_graphics_state();
+        self.add_graphics_state_change(page_index, GraphicsStateChange::Alpha { fill: color.alpha as f64 / 255.0, stroke: 1.0 });
+        draw();
+        page.layer.restore_graphics_state();
+    }
+
+    /// Calls `draw` to stroke something with given color, applying the color's alpha.
+    fn stroke_with_alpha<F: FnOnce()>(&self, page_index: usize, color: &color::Color, draw: F) {
+        let page = self.get_page(page_index);
+        page.layer.set_outline_color(self.color_to_printpdf_color(color));
 
-        let page = self.add_page(pdf);
+        if color.alpha == 255 {
+            draw();
+            return;
+        }
 
-        self.draw_element(&self.root_element, pdf);
+        page.layer.save_graphics_state();
+        self.add_graphics_state_change(page_index, GraphicsStateChange::Alpha { fill: 1.0, stroke: color.alpha as f64 / 255.0 });
+        draw();
+        page.layer.restore_graphics_state();
     }
 
-    fn draw_element(&self, element: &layout::Element, pdf: &PdfDocumentReference) {
-        let page = self.get_page(0);
+    fn draw_element(&self, page_index: usize, element: &layout::Element) {
+        let page = self.get_page(page_index);
         let doc = self.document.borrow();
         let html_element = doc.get_element_immutable(element.element);
-        let resource_manager = self.resource_manager.borrow();
+
+        match &element.kind {
+            // A line box itself is not drawn, its element is the block container it is in.
+            layout::ElementKind::Line => {
+                for child in &element.children {
+                    self.draw_element(page_index, child);
+                }
+
+                return;
+            }
+
+            layout::ElementKind::Text { text, baseline } => {
+                self.fill_text(page_index, element.x, element.y, text, html_element.get_style_properties(), Some(*baseline));
+                return;
+            }
+
+            layout::ElementKind::Box => {}
+        }
+
+        // An element with opacity is drawn, together with its descendants, into a transparency group.
+        let opacity = html_element.get_style_property("opacity").map_or(1.0, |opacity| {
+            let value = if opacity.is_percentage() { opacity.as_number() / 100.0 } else { opacity.as_number() };
+            return value.clamp(0.0, 1.0);
+        });
+
+        if opacity == 0.0 {
+            return;
+        }
+
+        if opacity < 1.0 {
+            self.add_graphics_state_change(page_index, GraphicsStateChange::BeginGroup { opacity: opacity });
+        }
 
         // Draw background.
         if let Some(background_color) = html_element.get_style_property("background-color") {
             let background_color = background_color.as_color();
             if background_color.alpha > 0 {
-                page.layer.set_fill_color(self.color_to_printpdf_color(&background_color));
+                self.fill_with_alpha(page_index, background_color, || self.draw_rect(page, element.x, element.y, element.width, element.height));
             }
+        }
+
+        self.draw_borders(page_index, element, html_element);
+
+        for child in &element.children {
+            self.draw_element(page_index, child);
+        }
 
-            self.draw_rect(page, element.x, element.y, element.width, element.height);
+        if opacity < 1.0 {
+            self.add_graphics_state_change(page_index, GraphicsStateChange::EndGroup);
         }
+    }
 
-        // Draw the text, if this is a text node.
-        if html_element.is_text_node() {
-            let fallback_font = self.fallback_font.as_ref().unwrap();
-            let font_name = self.get_font_name(&html_element);
-            let mut should_render = true;
+    /// Draws the borders of given element, inside its box.
+    /// Dashed and dotted borders are stroked with a dash pattern, other styles are drawn as solid borders.
+    /// https://www.w3.org/TR/css-backgrounds-3/#border-style
+    fn draw_borders(&self, page_index: usize, element: &layout::Element, html_element: &html::Element) {
+        let page = self.get_page(page_index);
+        let (x, y, width, height) = (element.x, element.y, element.width, element.height);
+        let border = &element.border;
 
-            if let Some(color) = html_element.get_style_property("color") {
-                if color.as_color().alpha <= 0 {
-                    should_render = false;
-                }
+        // The left and the right border are between the top and the bottom one.
+        let sides = [
+            ("top", x, y, width, border.top),
+            ("right", x + width - border.right, y + border.top, border.right, height - border.top - border.bottom),
+            ("bottom", x, y + height - border.bottom, width, border.bottom),
+            ("left", x, y + border.top, border.left, height - border.top - border.bottom)
+        ];
 
-                page.layer.set_fill_color(self.color_to_printpdf_color(&color.as_color()));
-            } else {
-                page.layer.set_fill_color(self.color_to_printpdf_color(&color::Color::from_rgb(0, 0, 0)));
+        for (side, side_x, side_y, side_width, side_height) in sides.iter() {
+            if *side_width <= 0.0 || *side_height <= 0.0 {
+                continue;
             }
 
-            if should_render {
-                if let Some(external_font) = self.fonts.get(&font_name) {
-                    let font_resource = &resource_manager.get_font(&font_name).unwrap().font;
-                    let text_bb = font_resource.get_text_bounding_box(&html_element.text, 12.0, true);
-                    self.draw_text(page, element.x, element.y + text_bb.height - text_bb.y, &html_element.text, &external_font);
-                } else {
-                    self.draw_text(page, element.x, element.y, &html_element.text, &fallback_font);
+            // Borders without a color are in the color of the element's text.
+            let color = html_element.get_style_property(&format!("border-{}-color", side))
+                .filter(|color| color.is_color())
+                .or_else(|| html_element.get_style_property("color"))
+                .map_or(color::Color::from_rgb(0, 0, 0), |color| *color.as_color());
+
+            if color.alpha == 0 {
+                continue;
+            }
+
+            let style = html_element.get_style_property(&format!("border-{}-style", side));
+            let is_dotted = match style {
+                Some(style) if style.is_keyword("dashed") => false,
+                Some(style) if style.is_keyword("dotted") => true,
+                Some(style) => {
+                    if !style.is_keyword("solid") {
+                        self.report_unsupported_border_style(style.as_string(), html_element.index);
+                    }
+
+                    self.fill_with_alpha(page_index, &color, || self.draw_rect(page, *side_x, *side_y, *side_width, *side_height));
+                    continue;
                 }
+
+                None => continue
+            };
+
+            // The line goes along the middle of the border, dots are as large as the border is wide and as far apart.
+            let is_horizontal = *side == "top" || *side == "bottom";
+            let thickness = if is_horizontal { *side_height } else { *side_width };
+            let inset = if is_dotted { thickness / 2.0 } else { 0.0 };
+            let (start, end) = if is_horizontal {
+                ((side_x + inset, side_y + thickness / 2.0), (side_x + side_width - inset, side_y + thickness / 2.0))
+            } else {
+                ((side_x + thickness / 2.0, side_y + inset), (side_x + thickness / 2.0, side_y + side_height - inset))
+            };
+
+            let (cap, dash, gap) = if is_dotted {
+                (LineCapStyle::Round, 0, (thickness * 2.0).round().max(1.0) as i64)
+            } else {
+                (LineCapStyle::Butt, (thickness * 3.0).round().max(1.0) as i64, (thickness * 3.0).round().max(1.0) as i64)
+            };
+
+            page.layer.save_graphics_state();
+            page.layer.set_outline_thickness(thickness);
+            page.layer.set_line_cap_style(cap);
+            page.layer.set_line_dash_pattern(LineDashPattern::new(0, Some(dash), Some(gap), None, None, None, None));
+            self.stroke_with_alpha(page_index, &color, || self.draw_line(page, start, end));
+            page.layer.restore_graphics_state();
+        }
+    }
+
+    /// Warns that a border style is drawn as a solid border, once for each style.
+    fn report_unsupported_border_style(&self, style: &str, element_index: usize) {
+        if !self.reported_border_styles.borrow_mut().insert(style.to_string()) {
+            return;
+        }
+
+        let diagnostic = Diagnostic::new(Severity::Warning, format!("border style {} is not supported, it is drawn as solid", style))
+            .with_element(element_index);
+
+        self.diagnostics.borrow_mut().push(diagnostic);
+    }
+
+    fn draw_margin_box(&self, page_index: usize, margin_box: &layout::MarginBox) {
+        let page = self.get_page(page_index);
+
+        if let Some(background_color) = margin_box.style.get("background-color") {
+            let background_color = background_color.as_color();
+            if background_color.alpha > 0 {
+                self.fill_with_alpha(page_index, background_color, || self.draw_rect(page, margin_box.x, margin_box.y, margin_box.width, margin_box.height));
             }
         }
 
-        for child in &element.children {
-            self.draw_element(&child, pdf);
+        match &margin_box.element {
+            Some(element) => self.draw_element(page_index, element),
+            None => self.fill_text(page_index, margin_box.text_x, margin_box.text_y, &margin_box.text, &margin_box.style, None)
+        }
+    }
+
+    /// Draws text whose box starts at given position, using the font and color from given properties.
+    /// `baseline` is the distance from the top of the box to the baseline, it is measured on the text itself if not given.
+    fn fill_text(&self, page_index: usize, x: f64, y: f64, text: &String, style: &html::ElementStyleProperties, baseline: Option<f64>) {
+        let fallback_font = match &self.fallback_font {
+            Some(fallback_font) => fallback_font,
+            None => return
+        };
+
+        let page = self.get_page(page_index);
+        let resource_manager = self.resource_manager.borrow();
+        let font_name = resource_manager.select_font(&style.get_font_families(), &self.options.default_font);
+        let font_size = style.get_font_size();
+        let color = style.get("color")
+            .map_or(color::Color::from_rgb(0, 0, 0), |color| *color.as_color());
+
+        if color.alpha > 0 {
+            self.fill_with_alpha(page_index, &color, || {
+                if let (Some(external_font), Some(font_resource)) = (self.fonts.get(&font_name), resource_manager.get_font(&font_name)) {
+                    let baseline = baseline.unwrap_or_else(|| {
+                        let text_bb = font_resource.font.get_text_bounding_box(text, font_size, true);
+                        return text_bb.height - text_bb.y;
+                    });
+
+                    self.draw_text(page, x, y + baseline, text, font_size, external_font);
+                } else {
+                    self.draw_text(page, x, y, text, font_size, fallback_font);
+                }
+            });
         }
     }
 
     fn draw_rect(&self, page: &DrawTargetPage, x: f64, y: f64, width: f64, height: f64) {
-        let ww = self.px_to_mm(width);
-        let hh = self.px_to_mm(height);
-        let xx = self.px_to_mm(x);
-        let yy = self.px_to_mm(y);
+        let ww = self.pt_to_mm(width);
+        let hh = self.pt_to_mm(height);
+        let xx = self.to_page_x(page, x);
+        let yy = self.to_page_y(page, y);
 
         let shape = Line {
             points: vec![
-                (Point::new(Mm(xx), Mm(self.flip_y(yy))), false),
-                (Point::new(Mm(xx + ww), Mm(self.flip_y(yy))), false),
-                (Point::new(Mm(xx + ww), Mm(self.flip_y(yy + hh))), false),
-                (Point::new(Mm(xx), Mm(self.flip_y(yy + hh))), false),
+                (Point::new(Mm(xx), Mm(self.flip_y(page, yy))), false),
+                (Point::new(Mm(xx + ww), Mm(self.flip_y(page, yy))), false),
+                (Point::new(Mm(xx + ww), Mm(self.flip_y(page, yy + hh))), false),
+                (Point::new(Mm(xx), Mm(self.flip_y(page, yy + hh))), false),
             ],
             is_closed: true,
             has_fill: true,
@@ -124,42 +337,155 @@ impl Engine {
         page.layer.add_shape(shape);
     }
 
-    fn draw_text(&self, page: &DrawTargetPage, x: f64, y: f64, text: &String, font: &IndirectFontRef) {
-        let xx = self.px_to_mm(x);
-        let yy = self.px_to_mm(y);
-        page.layer.use_text(text, 12, Mm(xx), Mm(self.flip_y(yy)), font);
+    /// Strokes a straight line between two points.
+    fn draw_line(&self, page: &DrawTargetPage, start: (f64, f64), end: (f64, f64)) {
+        let point = |(x, y): (f64, f64)| Point::new(Mm(self.to_page_x(page, x)), Mm(self.flip_y(page, self.to_page_y(page, y))));
+        let shape = Line {
+            points: vec![(point(start), false), (point(end), false)],
+            is_closed: false,
+            has_fill: false,
+            has_stroke: true,
+            is_clipping_path: false
+        };
+
+        page.layer.add_shape(shape);
     }
 
-    fn px_to_mm(&self, val: f64) -> f64 {
-        let dpi = 72.0;
-        let to_mm = 25.4 / dpi;
-        return val * to_mm;
+    fn draw_text(&self, page: &DrawTargetPage, x: f64, y: f64, text: &String, font_size: f64, font: &IndirectFontRef) {
+        let xx = self.to_page_x(page, x);
+        let yy = self.to_page_y(page, y);
+        page.layer.use_text(text, font_size, Mm(xx), Mm(self.flip_y(page, yy)), font);
     }
 
-    fn flip_y(&self, y: f64) -> f64 {
-        297.0 - y
+    fn pt_to_mm(&self, val: f64) -> f64 {
+        return units::pt_to_mm(val);
     }
 
-    fn get_font_name(&self, element: &html::Element) -> String {
-        if let Some(font_prop) = element.get_style_property("font") {
-            return font_prop.as_string().clone();
-        }
+    /// Converts a horizontal position from layout space to millimeters on the page.
+    fn to_page_x(&self, page: &DrawTargetPage, x: f64) -> f64 {
+        self.pt_to_mm(page.geometry.margin_left + x)
+    }
+
+    /// Converts a vertical position from layout space to millimeters on the page,
+    /// measured from the top edge.
+    fn to_page_y(&self, page: &DrawTargetPage, y: f64) -> f64 {
+        self.pt_to_mm(page.geometry.margin_top + y)
+    }
 
-        return String::from("Arial");
+    fn flip_y(&self, page: &DrawTargetPage, y: f64) -> f64 {
+        self.pt_to_mm(page.geometry.height) - y
     }
 
+    /// Converts a color to printpdf's color, in CMYK if it was given as one or if all colors should be.
     fn color_to_printpdf_color(&self, color: &color::Color) -> printpdf::Color {
+        let cmyk = match self.options.color_mode {
+            ColorMode::AsSpecified => color.cmyk,
+            ColorMode::Cmyk => Some(color.to_cmyk())
+        };
+
+        if let Some(cmyk) = cmyk {
+            return printpdf::Color::Cmyk(Cmyk::new(cmyk.cyan, cmyk.magenta, cmyk.yellow, cmyk.key, None));
+        }
+
         return printpdf::Color::Rgb(Rgb::new(color.red as f64 / 255.0, color.green as f64 / 255.0, color.blue as f64 / 255.0, None));
     }
 
-    pub fn new(context: context::ConversionContext) -> Engine {
-        Engine {
+    pub fn new(context: context::ConversionContext) -> Result<Engine> {
+        let layout_pages = context.pages
+            .ok_or_else(|| Error::Pdf(String::from("the document has not been laid out")))?;
+
+        let resource_manager = context.resources_manager
+            .ok_or_else(|| Error::Pdf(String::from("the document's resources have not been loaded")))?;
+
+        return Ok(Engine {
             document: context.document,
-            root_element: context.root_element.unwrap(),
-            resource_manager: context.resources_manager.unwrap(),
+            layout_pages: layout_pages,
+            resource_manager: resource_manager,
+            options: context.options,
+            diagnostics: context.diagnostics,
             pages: Vec::new(),
             fonts: HashMap::default(),
-            fallback_font: None
+            fallback_font: None,
+            graphics_state_changes: RefCell::new(Vec::new()),
+            reported_border_styles: RefCell::new(HashSet::new())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ColorMode, Converter};
+    use lopdf::content::Content;
+
+    /// Converts a document and returns the operators that draw its first page, with their operands,
+    /// e.g. `d [3 3] 0`, and the messages of the diagnostics.
+    fn convert(converter: Converter) -> (Vec<String>, Vec<String>) {
+        fn format_operand(operand: &lopdf::Object) -> String {
+            return match operand {
+                lopdf::Object::Integer(value) => value.to_string(),
+                lopdf::Object::Real(value) => format!("{}", (value * 100.0).round() / 100.0),
+                lopdf::Object::Name(name) => String::from_utf8_lossy(name).to_string(),
+                lopdf::Object::Array(items) => format!("[{}]", items.iter().map(format_operand).collect::<Vec<String>>().join(" ")),
+                _ => String::from("?")
+            };
         }
+
+        let output = converter.convert().expect("test document should convert");
+        let document = lopdf::Document::load_mem(&output.pdf).expect("PDF should load");
+        let page_id = *document.get_pages().values().next().expect("PDF should have a page");
+        let content = Content::decode(&document.get_page_content(page_id).unwrap()).expect("content should decode");
+
+        let operations = content.operations.iter()
+            .map(|operation| {
+                let mut parts = vec![operation.operator.clone()];
+                parts.extend(operation.operands.iter().map(format_operand));
+                return parts.join(" ");
+            })
+            .collect();
+
+        return (operations, output.diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect());
+    }
+
+    #[test]
+    fn dashed_and_dotted_borders_are_stroked_with_a_dash_pattern() {
+        let (operations, diagnostics) = convert(Converter::new().html("<div style='height: 20pt; border-top: 2pt dashed red; border-left: 1pt dotted'></div>"));
+        let strokes: Vec<&[String]> = operations.windows(3).filter(|window| window[0].starts_with("w ")).collect();
+
+        assert_eq!(strokes, vec![
+            [String::from("w 2"), String::from("J 0"), String::from("d [6 6] 0")],
+            [String::from("w 1"), String::from("J 1"), String::from("d [0 2] 0")]
+        ]);
+
+        assert_eq!(operations.iter().filter(|operation| *operation == "S").count(), 2);
+        assert!(operations.iter().all(|operation| operation != "f"));
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn unsupported_border_styles_are_drawn_solid_and_reported_once() {
+        let (operations, diagnostics) = convert(Converter::new().html("<div style='height: 20pt; border: 1pt double'></div><div style='border-top: 1pt groove'></div>"));
+
+        assert_eq!(operations.iter().filter(|operation| *operation == "f").count(), 5);
+        assert!(operations.iter().all(|operation| operation != "S"));
+        assert_eq!(diagnostics, vec![
+            "border style double is not supported, it is drawn as solid",
+            "border style groove is not supported, it is drawn as solid"
+        ]);
+    }
+
+    #[test]
+    fn colors_are_written_in_cmyk_if_given_as_one_or_if_all_colors_should_be() {
+        let html = "<div style='height: 10pt; background-color: device-cmyk(0 50% 1 0); border-top: 1pt dashed rgb(0 0 255)'></div>\
+            <div style='height: 10pt; background-color: red'></div>";
+
+        let color_operations = |color_mode: ColorMode| -> Vec<String> {
+            let (operations, _) = convert(Converter::new().html(html).color_mode(color_mode));
+            return operations.into_iter()
+                .filter(|operation| ["rg", "RG", "k", "K"].contains(&operation.split(' ').next().unwrap()))
+                .collect();
+        };
+
+        assert_eq!(color_operations(ColorMode::AsSpecified), vec!["k 0 0.5 1 0", "RG 0 0 1", "rg 1 0 0"]);
+        assert_eq!(color_operations(ColorMode::Cmyk), vec!["k 0 0.5 1 0", "K 1 1 0 0", "k 0 1 1 0"]);
     }
 }
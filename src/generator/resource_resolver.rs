@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+use font_kit::family_name::FamilyName;
+use font_kit::handle::Handle;
+use font_kit::properties::Properties;
+use font_kit::source::SystemSource;
+
+/// Provides the contents of external resources (fonts, images, stylesheets)
+/// referenced by a document.
+pub trait ResourceResolver {
+
+    /// Returns the contents of the resource at given path, or `None` if it cannot be found.
+    /// The path is exactly as it appears in the document, so it may be relative.
+    fn resolve(&self, path: &str) -> Option<Vec<u8>>;
+
+    /// Returns the contents of the font file of given font family, or `None` if it cannot be found.
+    /// By default, the font is looked up among the fonts installed on the system.
+    fn resolve_font(&self, family: &str) -> Option<Vec<u8>> {
+        return resolve_system_font(family);
+    }
+}
+
+/// Loads resources from the file system, resolving relative paths
+/// against a base directory.
+pub struct FileResourceResolver {
+    base_path: PathBuf
+}
+
+impl FileResourceResolver {
+
+    /// Resolves given resource path against the base path.
+    /// Absolute paths are returned unchanged.
+    pub fn resolve_path(&self, path: &str) -> PathBuf {
+        let path = Path::new(path);
+        if path.is_absolute() {
+            return path.to_path_buf();
+        }
+
+        return self.base_path.join(path);
+    }
+
+    pub fn new(base_path: PathBuf) -> Self {
+        Self {
+            base_path: base_path
+        }
+    }
+}
+
+impl ResourceResolver for FileResourceResolver {
+    fn resolve(&self, path: &str) -> Option<Vec<u8>> {
+        return std::fs::read(self.resolve_path(path)).ok();
+    }
+}
+
+/// Resolves `path` of a resource referenced from the resource at `base`, e.g. an `@import`
+/// inside a stylesheet. Both paths are relative to the resolver's base path, unless absolute.
+/// The result is normalized, so the same resource always gets the same path.
+pub fn resolve_relative_path(base: &str, path: &str) -> String {
+    if path.starts_with('/') || path.contains("://") {
+        return path.to_string();
+    }
+
+    let base_directory = match base.rfind('/') {
+        Some(position) => &base[..position + 1],
+        None => ""
+    };
+
+    let joined_path = format!("{}{}", base_directory, path);
+    let mut segments = Vec::<&str>::new();
+    for segment in joined_path.split('/') {
+        match segment {
+            "." => {}
+            ".." if !segments.is_empty() && segments.last() != Some(&"..") && segments.last() != Some(&"") => {
+                segments.pop();
+            }
+
+            _ => segments.push(segment)
+        }
+    }
+
+    return segments.join("/");
+}
+
+/// Finds the font file of given font family among the fonts installed on the system.
+/// Generic families (e.g. `serif`) resolve to the system's default font of that kind.
+/// https://www.w3.org/TR/css-fonts-4/#generic-font-families
+fn resolve_system_font(family: &str) -> Option<Vec<u8>> {
+    let family_name = match family.to_ascii_lowercase().as_str() {
+        "serif" => FamilyName::Serif,
+        "sans-serif" => FamilyName::SansSerif,
+        "monospace" => FamilyName::Monospace,
+        "cursive" => FamilyName::Cursive,
+        "fantasy" => FamilyName::Fantasy,
+        _ => FamilyName::Title(family.to_string())
+    };
+
+    match SystemSource::new().select_best_match(&[family_name], &Properties::new()).ok()? {
+        Handle::Path { path, .. } => std::fs::read(path).ok(),
+        Handle::Memory { bytes, .. } => Some(bytes.to_vec())
+    }
+}
@@ -3,11 +3,17 @@
 use std::collections::HashMap;
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::sync::Arc;
 use crate::utils::font;
+use crate::generator::ResourceResolver;
 
 pub type ResourcesManagerRef = Rc<RefCell<ResourcesManager>>;
 
 pub struct ResourcesManager {
+
+    /// Provides the contents of resources referenced by the document.
+    resolver: Box<dyn ResourceResolver>,
+
     pub fonts: HashMap<String, FontResource>,
     pub images: HashMap<String, ImageResource>,
 }
@@ -16,7 +22,7 @@ pub struct ResourcesManager {
 pub struct FontResource {
     pub name: String,
     pub font: font::Font,
-    pub data: std::fs::File,
+    pub data: Vec<u8>,
 }
 
 #[derive(Debug, Default)]
@@ -29,10 +35,42 @@ impl ResourcesManager {
         self.fonts.insert(name, font);
     }
 
+    /// Parses given font file and registers it under specified name.
+    /// Returns whether the data contained a valid font.
+    pub fn add_font_data(&mut self, name: String, data: Vec<u8>) -> bool {
+        match font_kit::loaders::default::Font::from_bytes(Arc::new(data.clone()), 0) {
+            Ok(loaded_font) => {
+                self.add_font(name.clone(), FontResource {
+                    name: name,
+                    font: font::Font::wrap(loaded_font),
+                    data: data
+                });
+
+                return true;
+            }
+
+            Err(_) => {
+                return false;
+            }
+        }
+    }
+
     pub fn get_font(&self, name: &String) -> Option<&FontResource> {
         return self.fonts.get(name);
     }
 
+    pub fn has_font(&self, name: &String) -> bool {
+        return self.fonts.contains_key(name);
+    }
+
+    /// Returns the first of given font families that has been loaded, or `default_font` if none of them has.
+    pub fn select_font(&self, families: &[String], default_font: &String) -> String {
+        return families.iter()
+            .find(|family| self.has_font(family))
+            .unwrap_or(default_font)
+            .clone();
+    }
+
     pub fn add_image(&mut self, path: String, image: ImageResource) {
         self.images.insert(path, image);
     }
@@ -41,8 +79,19 @@ impl ResourcesManager {
         return self.images.get(path);
     }
 
-    pub fn new() -> Self {
+    /// Loads the contents of an external resource.
+    pub fn load(&self, path: &str) -> Option<Vec<u8>> {
+        return self.resolver.resolve(path);
+    }
+
+    /// Loads the contents of the font file of given font family.
+    pub fn load_font(&self, family: &str) -> Option<Vec<u8>> {
+        return self.resolver.resolve_font(family);
+    }
+
+    pub fn new(resolver: Box<dyn ResourceResolver>) -> Self {
         Self {
+            resolver: resolver,
             fonts: HashMap::default(),
             images: HashMap::default()
         }
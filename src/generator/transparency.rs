@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use lopdf::content::{Content, Operation};
+use crate::error::{Error, Result};
+
+// printpdf can neither set the alpha constants of the graphics state nor create transparency groups,
+// so the render engine marks where they are needed with `gs` operators (see `Engine::add_graphics_state_change`)
+// and the saved document is rewritten to use them here.
+//
+// https://www.w3.org/TR/css-color-3/#transparency
+
+/// A change of the graphics state that needs transparency, in the order of the page's `gs` operators.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphicsStateChange {
+
+    /// Sets the constant alpha (0 to 1) of fills (`ca`) and strokes (`CA`).
+    Alpha { fill: f64, stroke: f64 },
+
+    /// Starts a transparency group, which is composited with given opacity once it ends.
+    BeginGroup { opacity: f64 },
+    EndGroup
+}
+
+/// Changes of the graphics state, for each page.
+pub type GraphicsStateChanges = Vec<Vec<GraphicsStateChange>>;
+
+/// Operations of a transparency group that is being collected.
+struct Group {
+    opacity: f64,
+    operations: Vec<Operation>
+}
+
+/// Rewrites the content of page `page_id` so that its `gs` operators do what given changes say.
+struct PageRewriter<'a> {
+    document: &'a mut Document,
+    page_id: ObjectId,
+
+    /// Graphics states to add to the page's resources, by name.
+    graphics_states: Vec<(String, Dictionary)>,
+
+    /// Transparency groups to add to the page's resources, by name.
+    groups: Vec<(String, ObjectId)>,
+
+    /// Names of the graphics states created so far, by the alpha constants they set.
+    graphics_state_names: HashMap<(u64, u64), String>,
+
+    /// Names of the graphics states that were used as markers, which are not needed anymore.
+    markers: Vec<Vec<u8>>
+}
+
+impl<'a> PageRewriter<'a> {
+    fn rewrite(&mut self, changes: &[GraphicsStateChange]) -> Result<()> {
+        let resources_id = self.get_resources_id()?;
+        let content = Content::decode(&self.document.get_page_content(self.page_id)?)?;
+        let mut changes = changes.iter();
+
+        // Operations go into the innermost group, the page itself being the outermost one.
+        let mut group = Group { opacity: 1.0, operations: Vec::new() };
+        let mut outer_groups = Vec::<Group>::new();
+
+        for operation in content.operations {
+            if operation.operator != "gs" {
+                group.operations.push(operation);
+                continue;
+            }
+
+            let change = match changes.next() {
+                Some(change) => change,
+                None => {
+                    group.operations.push(operation);
+                    continue;
+                }
+            };
+
+            if let Some(Ok(name)) = operation.operands.first().map(Object::as_name) {
+                self.markers.push(name.to_vec());
+            }
+
+            match *change {
+                GraphicsStateChange::Alpha { fill, stroke } => {
+                    let name = self.get_alpha_state(fill, stroke);
+                    group.operations.push(Operation::new("gs", vec![Object::Name(name.into_bytes())]));
+                }
+
+                GraphicsStateChange::BeginGroup { opacity } => {
+                    outer_groups.push(std::mem::replace(&mut group, Group { opacity: opacity, operations: Vec::new() }));
+                }
+
+                GraphicsStateChange::EndGroup => {
+                    let outer_group = outer_groups.pop()
+                        .ok_or_else(|| Error::Pdf(String::from("transparency group ended without being started")))?;
+
+                    let ended_group = std::mem::replace(&mut group, outer_group);
+                    let operations = self.add_group(ended_group, resources_id)?;
+                    group.operations.extend(operations);
+                }
+            }
+        }
+
+        if !outer_groups.is_empty() {
+            return Err(Error::Pdf(String::from("transparency group was not ended")));
+        }
+
+        let content = Content { operations: group.operations };
+        self.document.change_page_content(self.page_id, content.encode()?)?;
+        return self.add_resources(resources_id);
+    }
+
+    /// Returns the page's resource dictionary, moving it into its own object if it is not one already,
+    /// so that transparency groups can share it.
+    fn get_resources_id(&mut self) -> Result<ObjectId> {
+        let resources = match self.document.get_dictionary(self.page_id)?.get(b"Resources") {
+            Ok(Object::Reference(id)) => return Ok(*id),
+            Ok(Object::Dictionary(resources)) => resources.clone(),
+            _ => Dictionary::new()
+        };
+
+        let resources_id = self.document.add_object(resources);
+        self.document.get_object_mut(self.page_id)?.as_dict_mut()?.set("Resources", Object::Reference(resources_id));
+        return Ok(resources_id);
+    }
+
+    /// Returns name of a graphics state with given alpha constants, creating it if it does not exist yet.
+    fn get_alpha_state(&mut self, fill: f64, stroke: f64) -> String {
+        let key = (fill.to_bits(), stroke.to_bits());
+        if let Some(name) = self.graphics_state_names.get(&key) {
+            return name.clone();
+        }
+
+        let name = format!("MpdfAlpha{}", self.graphics_state_names.len());
+        let mut state = Dictionary::new();
+        state.set("Type", Object::Name(b"ExtGState".to_vec()));
+        state.set("ca", Object::Real(fill));
+        state.set("CA", Object::Real(stroke));
+
+        self.graphics_states.push((name.clone(), state));
+        self.graphics_state_names.insert(key, name.clone());
+        return name;
+    }
+
+    /// Turns operations of a group into a form XObject with a transparency group, and returns
+    /// the operations that paint it with the group's opacity.
+    /// https://www.w3.org/TR/PDF/ 11.6.6 Transparency group XObjects
+    fn add_group(&mut self, group: Group, resources_id: ObjectId) -> Result<Vec<Operation>> {
+        let bounding_box = self.document.get_dictionary(self.page_id)?.get(b"MediaBox")?.clone();
+
+        let mut transparency_group = Dictionary::new();
+        transparency_group.set("Type", Object::Name(b"Group".to_vec()));
+        transparency_group.set("S", Object::Name(b"Transparency".to_vec()));
+
+        // The form uses the same resources as the page, including the groups nested in it.
+        let mut form = Dictionary::new();
+        form.set("Type", Object::Name(b"XObject".to_vec()));
+        form.set("Subtype", Object::Name(b"Form".to_vec()));
+        form.set("BBox", bounding_box);
+        form.set("Group", Object::Dictionary(transparency_group));
+        form.set("Resources", Object::Reference(resources_id));
+
+        let content = Content { operations: group.operations };
+        let form_id = self.document.add_object(Stream::new(form, content.encode()?));
+
+        let name = format!("MpdfGroup{}", self.groups.len());
+        self.groups.push((name.clone(), form_id));
+
+        let alpha_state = self.get_alpha_state(group.opacity, group.opacity);
+        return Ok(vec![
+            Operation::new("q", Vec::new()),
+            Operation::new("gs", vec![Object::Name(alpha_state.into_bytes())]),
+            Operation::new("Do", vec![Object::Name(name.into_bytes())]),
+            Operation::new("Q", Vec::new())
+        ]);
+    }
+
+    /// Adds the graphics states and groups that were created to the page's resources.
+    fn add_resources(&mut self, resources_id: ObjectId) -> Result<()> {
+        let resources = self.document.get_object_mut(resources_id)?.as_dict_mut()?;
+
+        if !resources.has(b"ExtGState") {
+            resources.set("ExtGState", Dictionary::new());
+        }
+
+        let graphics_states = resources.get_mut(b"ExtGState")?.as_dict_mut()?;
+        for marker in self.markers.drain(..) {
+            graphics_states.remove(&marker);
+        }
+
+        for (name, state) in self.graphics_states.drain(..) {
+            graphics_states.set(name, state);
+        }
+
+        if !resources.has(b"XObject") {
+            resources.set("XObject", Dictionary::new());
+        }
+
+        let xobjects = resources.get_mut(b"XObject")?.as_dict_mut()?;
+        for (name, form_id) in self.groups.drain(..) {
+            xobjects.set(name, Object::Reference(form_id));
+        }
+
+        return Ok(());
+    }
+
+    fn new(document: &'a mut Document, page_id: ObjectId) -> Self {
+        Self {
+            document: document,
+            page_id: page_id,
+            graphics_states: Vec::new(),
+            groups: Vec::new(),
+            graphics_state_names: HashMap::new(),
+            markers: Vec::new()
+        }
+    }
+}
+
+/// Replaces the `gs` operators the render engine used as markers with the graphics states and
+/// transparency groups described by `changes`.
+pub fn apply_graphics_state_changes(document: &mut Document, changes: &[Vec<GraphicsStateChange>]) -> Result<()> {
+    let page_ids: Vec<ObjectId> = document.get_pages().values().cloned().collect();
+
+    for (page_id, page_changes) in page_ids.into_iter().zip(changes) {
+        if page_changes.is_empty() {
+            continue;
+        }
+
+        PageRewriter::new(document, page_id).rewrite(page_changes)?;
+    }
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a document with one page with given content, whose resources have a graphics state for each marker.
+    fn document_with_page(content: &str, markers: &[&str]) -> (Document, ObjectId) {
+        let mut document = Document::with_version("1.3");
+        let pages_id = document.new_object_id();
+        let content_id = document.add_object(Stream::new(Dictionary::new(), content.as_bytes().to_vec()));
+
+        let mut graphics_states = Dictionary::new();
+        for marker in markers {
+            graphics_states.set(*marker, Dictionary::new());
+        }
+
+        let mut resources = Dictionary::new();
+        resources.set("ExtGState", graphics_states);
+
+        let mut page = Dictionary::new();
+        page.set("Type", Object::Name(b"Page".to_vec()));
+        page.set("Parent", Object::Reference(pages_id));
+        page.set("MediaBox", vec![0.into(), 0.into(), 100.into(), 100.into()]);
+        page.set("Contents", Object::Reference(content_id));
+        page.set("Resources", resources);
+        let page_id = document.add_object(page);
+
+        let mut pages = Dictionary::new();
+        pages.set("Type", Object::Name(b"Pages".to_vec()));
+        pages.set("Kids", vec![Object::Reference(page_id)]);
+        pages.set("Count", 1);
+        document.objects.insert(pages_id, Object::Dictionary(pages));
+
+        let mut catalog = Dictionary::new();
+        catalog.set("Type", Object::Name(b"Catalog".to_vec()));
+        catalog.set("Pages", Object::Reference(pages_id));
+        let catalog_id = document.add_object(catalog);
+        document.trailer.set("Root", Object::Reference(catalog_id));
+
+        return (document, page_id);
+    }
+
+    /// Returns the operators of given content, with the names they use, e.g. `gs MpdfAlpha0`.
+    fn operations(content: &[u8]) -> Vec<String> {
+        return Content::decode(content).expect("content should decode").operations.iter()
+            .map(|operation| match operation.operands.first().map(Object::as_name) {
+                Some(Ok(name)) => format!("{} {}", operation.operator, String::from_utf8_lossy(name)),
+                _ => operation.operator.clone()
+            })
+            .collect();
+    }
+
+    fn resource<'a>(document: &'a Document, page_id: ObjectId, category: &[u8], name: &[u8]) -> Option<&'a Object> {
+        let resources_id = document.get_dictionary(page_id).unwrap().get(b"Resources").unwrap().as_reference().unwrap();
+        return document.get_dictionary(resources_id).unwrap().get(category).unwrap().as_dict().unwrap().get(name).ok();
+    }
+
+    #[test]
+    fn markers_are_replaced_with_alpha_states() {
+        let (mut document, page_id) = document_with_page("/M0 gs 0 0 10 10 re f /M1 gs /M2 gs", &["M0", "M1", "M2", "Other"]);
+        let changes = vec![vec![
+            GraphicsStateChange::Alpha { fill: 0.5, stroke: 1.0 },
+            GraphicsStateChange::Alpha { fill: 1.0, stroke: 1.0 },
+            GraphicsStateChange::Alpha { fill: 0.5, stroke: 1.0 }
+        ]];
+
+        apply_graphics_state_changes(&mut document, &changes).unwrap();
+
+        let content = document.get_page_content(page_id).unwrap();
+        assert_eq!(operations(&content), vec!["gs MpdfAlpha0", "re", "f", "gs MpdfAlpha1", "gs MpdfAlpha0"]);
+
+        // The markers are gone, states that are not markers are kept.
+        for marker in &[b"M0", b"M1", b"M2"] {
+            assert!(resource(&document, page_id, b"ExtGState", *marker).is_none());
+        }
+
+        assert!(resource(&document, page_id, b"ExtGState", b"Other").is_some());
+
+        let state = resource(&document, page_id, b"ExtGState", b"MpdfAlpha0").unwrap().as_dict().unwrap();
+        assert_eq!(state.get(b"ca").unwrap().as_f64().unwrap(), 0.5);
+        assert_eq!(state.get(b"CA").unwrap().as_f64().unwrap(), 1.0);
+    }
+
+    #[test]
+    fn nested_groups_are_painted_by_the_groups_they_are_in() {
+        let (mut document, page_id) = document_with_page("/M0 gs 0 0 1 1 re f /M1 gs 1 1 1 1 re f /M2 gs /M3 gs 2 2 1 1 re f", &["M0", "M1", "M2", "M3"]);
+        let changes = vec![vec![
+            GraphicsStateChange::BeginGroup { opacity: 0.5 },
+            GraphicsStateChange::BeginGroup { opacity: 0.25 },
+            GraphicsStateChange::EndGroup,
+            GraphicsStateChange::EndGroup
+        ]];
+
+        apply_graphics_state_changes(&mut document, &changes).unwrap();
+
+        // The inner group ends first, so it is the first one created.
+        let content = document.get_page_content(page_id).unwrap();
+        assert_eq!(operations(&content), vec!["q", "gs MpdfAlpha1", "Do MpdfGroup1", "Q", "re", "f"]);
+
+        let group_content = |name: &[u8]| {
+            let form_id = resource(&document, page_id, b"XObject", name).unwrap().as_reference().unwrap();
+            let form = document.get_object(form_id).unwrap().as_stream().unwrap();
+            assert_eq!(form.dict.get(b"Group").unwrap().as_dict().unwrap().get(b"S").unwrap().as_name().unwrap(), b"Transparency");
+            return operations(&form.content);
+        };
+
+        assert_eq!(group_content(b"MpdfGroup1"), vec!["re", "f", "q", "gs MpdfAlpha0", "Do MpdfGroup0", "Q"]);
+        assert_eq!(group_content(b"MpdfGroup0"), vec!["re", "f"]);
+
+        let inner_state = resource(&document, page_id, b"ExtGState", b"MpdfAlpha0").unwrap().as_dict().unwrap();
+        assert_eq!(inner_state.get(b"ca").unwrap().as_f64().unwrap(), 0.25);
+    }
+
+    #[test]
+    fn groups_share_the_resources_of_the_page() {
+        let (mut document, page_id) = document_with_page("/M0 gs /M1 gs 0 0 1 1 re f /M2 gs", &["M0", "M1", "M2"]);
+        let changes = vec![vec![
+            GraphicsStateChange::BeginGroup { opacity: 0.5 },
+            GraphicsStateChange::Alpha { fill: 0.75, stroke: 0.75 },
+            GraphicsStateChange::EndGroup
+        ]];
+
+        apply_graphics_state_changes(&mut document, &changes).unwrap();
+
+        // The page's resources were moved into their own object, which the group refers to as well.
+        let resources_id = document.get_dictionary(page_id).unwrap().get(b"Resources").unwrap().as_reference().unwrap();
+        let form_id = resource(&document, page_id, b"XObject", b"MpdfGroup0").unwrap().as_reference().unwrap();
+        let form = document.get_object(form_id).unwrap().as_stream().unwrap();
+        assert_eq!(form.dict.get(b"Resources").unwrap().as_reference().unwrap(), resources_id);
+
+        // The state set in the group is in the shared resources, as is the group's opacity.
+        assert_eq!(operations(&form.content), vec!["gs MpdfAlpha0", "re", "f"]);
+        assert!(resource(&document, page_id, b"ExtGState", b"MpdfAlpha0").is_some());
+        assert!(resource(&document, page_id, b"ExtGState", b"MpdfAlpha1").is_some());
+    }
+
+    #[test]
+    fn unbalanced_groups_are_errors() {
+        let (mut document, _) = document_with_page("/M0 gs", &["M0"]);
+        assert!(apply_graphics_state_changes(&mut document, &[vec![GraphicsStateChange::EndGroup]]).is_err());
+
+        let (mut document, _) = document_with_page("/M0 gs", &["M0"]);
+        assert!(apply_graphics_state_changes(&mut document, &[vec![GraphicsStateChange::BeginGroup { opacity: 0.5 }]]).is_err());
+    }
+}
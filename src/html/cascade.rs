@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use crate::css::{PropertyDeclaration, PrimitiveValue, CssWideKeyword, Specificity, Stylesheet, PageRule, MarginBoxPosition, MediaEnvironment};
+use crate::html::{Document, PageContext, matches_selector, matches_page_selector};
+use crate::html::user_agent_stylesheet::user_agent_stylesheet;
+
+/// Where a declaration comes from.
+/// https://www.w3.org/TR/css-cascade-4/#cascading-origins
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Origin {
+
+    /// Default styles of HTML elements, see `user_agent.css`.
+    UserAgent,
+
+    /// Stylesheets and `style` attributes of the document.
+    Author
+}
+
+/// A declaration that applies to an element, along with everything needed to sort it.
+struct MatchedDeclaration<'a> {
+    origin: Origin,
+
+    /// Specificity of the most specific selector of the rule that matched the element.
+    specificity: Specificity,
+
+    /// Whether the declaration comes from the element's `style` attribute.
+    is_inline: bool,
+    declaration: &'a PropertyDeclaration
+}
+
+impl MatchedDeclaration<'_> {
+
+    /// Returns the precedence of declaration's origin and importance.
+    /// Important declarations reverse the order of origins, so user agent's
+    /// important declarations beat all others.
+    fn precedence(&self) -> u8 {
+        match (self.origin, self.declaration.important) {
+            (Origin::UserAgent, false) => 0,
+            (Origin::Author, false) => 1,
+            (Origin::Author, true) => 2,
+            (Origin::UserAgent, true) => 3
+        }
+    }
+}
+
+/// Adds declarations of all rules from `stylesheet` that match element at `element_index` to `matched`.
+/// Rules whose media queries do not match `media_environment` are skipped.
+fn match_rules<'a>(document: &Document, element_index: usize, stylesheet: &'a Stylesheet, origin: Origin, media_environment: &MediaEnvironment, matched: &mut Vec<MatchedDeclaration<'a>>) {
+    for rule in &stylesheet.rules {
+        if !rule.media.iter().all(|media| media.matches(media_environment)) {
+            continue;
+        }
+
+        let specificity = rule.selectors.iter()
+            .filter(|selector| matches_selector(document, element_index, selector))
+            .map(|selector| selector.specificity())
+            .max();
+
+        if let Some(specificity) = specificity {
+            for declaration in &rule.declarations {
+                matched.push(MatchedDeclaration {
+                    origin: origin,
+                    specificity: specificity,
+                    is_inline: false,
+                    declaration: declaration
+                });
+            }
+        }
+    }
+}
+
+/// Returns all declarations that apply to element at `element_index`, in cascade order.
+/// Applying them one by one lets the winning declaration of every property overwrite the rest.
+///
+/// Declarations are ordered by origin and importance first. Within the same origin,
+/// declarations from the element's `style` attribute beat all rules, and declarations from
+/// rules with a more specific selector win. Between equally specific rules, the one that
+/// appears later in the document wins.
+/// https://www.w3.org/TR/css-cascade-4/#cascade-sort
+///
+/// `revert` values are replaced by what the user agent origin alone would give,
+/// all other CSS-wide keywords are left to be resolved during inheritance.
+pub fn cascade_declarations(document: &Document, element_index: usize, media_environment: &MediaEnvironment) -> Vec<PropertyDeclaration> {
+    let element = document.get_element_immutable(element_index);
+    let mut matched = Vec::<MatchedDeclaration>::new();
+
+    match_rules(document, element_index, user_agent_stylesheet(), Origin::UserAgent, media_environment, &mut matched);
+    for stylesheet in document.get_stylesheets() {
+        match_rules(document, element_index, stylesheet, Origin::Author, media_environment, &mut matched);
+    }
+
+    for declaration in element.get_inline_style() {
+        matched.push(MatchedDeclaration {
+            origin: Origin::Author,
+            specificity: Specificity::default(),
+            is_inline: true,
+            declaration: declaration
+        });
+    }
+
+    // The sort is stable, so declarations that are otherwise equal stay in source order.
+    matched.sort_by_key(|declaration| (declaration.precedence(), declaration.is_inline, declaration.specificity));
+
+    // `revert` rolls the cascade back to the user agent origin, so it needs to know
+    // the value each property would have if there were no author declarations.
+    let mut user_agent_values = HashMap::<&String, &PrimitiveValue>::new();
+    for declaration in matched.iter().filter(|declaration| declaration.origin == Origin::UserAgent) {
+        user_agent_values.insert(&declaration.declaration.name, &declaration.declaration.value);
+    }
+
+    let mut declarations = Vec::<PropertyDeclaration>::new();
+    for declaration in &matched {
+        let mut declaration_copy = declaration.declaration.clone();
+
+        if declaration_copy.value.as_css_wide_keyword() == Some(CssWideKeyword::Revert) {
+            let reverted_value = match declaration.origin {
+                Origin::Author => user_agent_values.get(&declaration_copy.name).cloned(),
+                Origin::UserAgent => None
+            };
+
+            declaration_copy.value = match reverted_value {
+                Some(value) if value.as_css_wide_keyword() != Some(CssWideKeyword::Revert) => value.clone(),
+                _ => PrimitiveValue::from_css_wide_keyword(CssWideKeyword::Unset)
+            };
+        }
+
+        declarations.push(declaration_copy);
+    }
+
+    return declarations;
+}
+
+/// Returns all declarations of `@page` rules that apply to given page, in cascade order.
+/// Like for elements, declarations are ordered by importance, then by specificity of the page selectors
+/// and then by their order in the document. There are no user agent `@page` rules, so `revert` means `unset`.
+/// https://www.w3.org/TR/css-page-3/#cascading-and-page-context
+pub fn cascade_page_declarations(document: &Document, page: &PageContext) -> Vec<PropertyDeclaration> {
+    return cascade_page_rules(document, page, |rule| rule.declarations.iter().collect());
+}
+
+/// Returns all declarations of the margin rules for given margin box, in the `@page` rules
+/// that apply to given page, in cascade order. Margin rules take the specificity of their `@page` rule.
+pub fn cascade_margin_box_declarations(document: &Document, page: &PageContext, position: MarginBoxPosition) -> Vec<PropertyDeclaration> {
+    return cascade_page_rules(document, page, |rule| {
+        rule.margin_rules.iter()
+            .filter(|margin_rule| margin_rule.position == position)
+            .flat_map(|margin_rule| margin_rule.declarations.iter())
+            .collect()
+    });
+}
+
+/// Sorts declarations that `declarations` picks from each `@page` rule matching given page into cascade order.
+fn cascade_page_rules<F>(document: &Document, page: &PageContext, declarations: F) -> Vec<PropertyDeclaration>
+    where F: Fn(&PageRule) -> Vec<&PropertyDeclaration> {
+    let mut matched = Vec::<MatchedDeclaration>::new();
+
+    for rule in document.get_page_rules() {
+        let specificity = rule.selectors.iter()
+            .filter(|selector| matches_page_selector(selector, page))
+            .map(|selector| selector.specificity())
+            .max();
+
+        if let Some(specificity) = specificity {
+            for declaration in declarations(rule) {
+                matched.push(MatchedDeclaration {
+                    origin: Origin::Author,
+                    specificity: specificity,
+                    is_inline: false,
+                    declaration: declaration
+                });
+            }
+        }
+    }
+
+    matched.sort_by_key(|declaration| (declaration.precedence(), declaration.specificity));
+
+    return matched.into_iter()
+        .map(|declaration| {
+            let mut declaration = declaration.declaration.clone();
+            if declaration.value.as_css_wide_keyword() == Some(CssWideKeyword::Revert) {
+                declaration.value = PrimitiveValue::from_css_wide_keyword(CssWideKeyword::Unset);
+            }
+
+            declaration
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html::test_support::{parse_document, find_element, MEDIA_ENVIRONMENT};
+
+    /// Returns the value of given property that wins the cascade for the element with given id,
+    /// as written in the stylesheet.
+    fn cascaded_value(html: &str, id: &str, property: &str) -> Option<String> {
+        let document = parse_document(html);
+        let document = document.borrow();
+
+        return cascade_declarations(&document, find_element(&document, id), &MEDIA_ENVIRONMENT)
+            .into_iter()
+            .rfind(|declaration| declaration.name == property)
+            .map(|declaration| declaration.value.as_string().clone());
+    }
+
+    #[test]
+    fn author_declarations_beat_user_agent_ones() {
+        assert_eq!(cascaded_value("<div id=a></div>", "a", "display"), Some(String::from("block")));
+        assert_eq!(cascaded_value("<style>* { display: inline }</style><div id=a></div>", "a", "display"), Some(String::from("inline")));
+    }
+
+    #[test]
+    fn more_specific_and_later_rules_win() {
+        let html = "<style>#a { text-align: right } div.b { text-align: center } div { text-align: left }</style><div id=a class=b></div>";
+        assert_eq!(cascaded_value(html, "a", "text-align"), Some(String::from("right")));
+
+        let html = "<style>.b { text-align: right } .c { text-align: center }</style><div id=a class='c b'></div>";
+        assert_eq!(cascaded_value(html, "a", "text-align"), Some(String::from("center")));
+
+        // Of all selectors of a rule, the most specific one that matches counts.
+        let html = "<style>div, #a { text-align: right } .b { text-align: center }</style><div id=a class=b></div>";
+        assert_eq!(cascaded_value(html, "a", "text-align"), Some(String::from("right")));
+    }
+
+    #[test]
+    fn inline_style_beats_rules() {
+        let html = "<style>#a { text-align: right }</style><div id=a style='text-align: center'></div>";
+        assert_eq!(cascaded_value(html, "a", "text-align"), Some(String::from("center")));
+    }
+
+    #[test]
+    fn important_declarations_win() {
+        let html = "<style>div { text-align: left !important } #a { text-align: right }</style><div id=a style='text-align: center'></div>";
+        assert_eq!(cascaded_value(html, "a", "text-align"), Some(String::from("left")));
+
+        let html = "<style>#a { text-align: left !important }</style><div id=a style='text-align: center !important'></div>";
+        assert_eq!(cascaded_value(html, "a", "text-align"), Some(String::from("center")));
+    }
+
+    #[test]
+    fn revert_rolls_back_to_the_user_agent_origin() {
+        let html = "<style>* { display: inline } #a { display: revert }</style><div id=a></div><span id=b style='display: revert'></span>";
+        assert_eq!(cascaded_value(html, "a", "display"), Some(String::from("block")));
+
+        // Without a user agent declaration, the property is unset.
+        assert_eq!(cascaded_value(html, "b", "display"), Some(String::from("unset")));
+    }
+
+    #[test]
+    fn rules_apply_if_their_media_queries_match() {
+        let html = "<style media='(orientation: landscape)'>#a { text-align: right }</style>\
+            <style>@media (min-width: 700pt) { #b { text-align: center } }</style><div id=a></div><div id=b></div>";
+        let document = parse_document(html);
+        let document = document.borrow();
+        let landscape = MediaEnvironment { width: MEDIA_ENVIRONMENT.height, height: MEDIA_ENVIRONMENT.width };
+
+        let text_align = |id: &str, environment: &MediaEnvironment| {
+            return cascade_declarations(&document, find_element(&document, id), environment)
+                .into_iter()
+                .any(|declaration| declaration.name == "text-align");
+        };
+
+        assert!(!text_align("a", &MEDIA_ENVIRONMENT));
+        assert!(!text_align("b", &MEDIA_ENVIRONMENT));
+        assert!(text_align("a", &landscape));
+        assert!(text_align("b", &landscape));
+    }
+}
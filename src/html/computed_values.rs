@@ -0,0 +1,282 @@
+use crate::css::{PrimitiveValue, MathExpression, MathType};
+use crate::html::ElementStyleProperties;
+use crate::utils::units::absolute_unit_to_pt;
+use crate::utils::Color;
+
+/// Font size of `medium`, which is the initial value of `font-size`, in points.
+pub const DEFAULT_FONT_SIZE: f64 = 12.0;
+
+/// Width of a `medium` border, which is the initial width of borders, in points.
+pub const MEDIUM_BORDER_WIDTH: f64 = 2.25;
+
+/// Scaling factor between neighbouring font size keywords, also used by `larger` and `smaller`.
+const FONT_SIZE_SCALE: f64 = 1.2;
+
+/// The area viewport-percentage lengths (`vw`, `vh`, `vmin` and `vmax`) are relative to.
+/// When printing, that is the page area - the page without its margins. Both sizes are in points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub width: f64,
+    pub height: f64
+}
+
+/// Sizes that relative lengths are resolved against, in points.
+struct LengthContext {
+
+    /// Computed font size of the element, used by `em` and `ex`.
+    font_size: f64,
+
+    /// Computed font size of the root element, used by `rem`.
+    root_font_size: f64,
+    viewport: Viewport
+}
+
+impl LengthContext {
+
+    /// Converts a length to points. Returns `None` for an unknown unit.
+    fn to_pt(&self, value: f64, unit: &str) -> Option<f64> {
+        if let Some(size) = absolute_unit_to_pt(unit) {
+            return Some(value * size);
+        }
+
+        let size = match unit {
+
+            // Only zero can be written without a unit.
+            "" => 0.0,
+            "em" => self.font_size,
+
+            // Fonts do not tell their x-height, so use the usual approximation of half an em.
+            "ex" => self.font_size / 2.0,
+            "rem" => self.root_font_size,
+            "vw" => self.viewport.width / 100.0,
+            "vh" => self.viewport.height / 100.0,
+            "vmin" => self.viewport.width.min(self.viewport.height) / 100.0,
+            "vmax" => self.viewport.width.max(self.viewport.height) / 100.0,
+            _ => return None
+        };
+
+        return Some(value * size);
+    }
+
+    /// Replaces all lengths in given value, including those in lists and function arguments, with points.
+    fn resolve_lengths(&self, value: &PrimitiveValue) -> PrimitiveValue {
+        if value.is_dimension_value() {
+            let dimension = value.as_dimension_value();
+            return match self.to_pt(dimension.value, &dimension.dimension.to_ascii_lowercase()) {
+                Some(points) => length(points),
+                None => value.clone()
+            };
+        }
+
+        if value.is_list() {
+            let items = value.as_items().iter().map(|item| self.resolve_lengths(item)).collect();
+            return PrimitiveValue::from_list(items, value.is_comma_separated());
+        }
+
+        if let Some(expression) = value.as_math() {
+            return match expression.map_lengths(&|value, unit| self.to_pt(value, unit)) {
+                Some(expression) => compute_math(expression, None),
+                None => value.clone()
+            };
+        }
+
+        if value.is_function() {
+            let arguments = value.as_items().iter().map(|argument| self.resolve_lengths(argument)).collect();
+            return PrimitiveValue::from_function(value.as_string(), arguments);
+        }
+
+        return value.clone();
+    }
+
+    fn new(font_size: f64, root_font_size: f64, viewport: Viewport) -> Self {
+        Self {
+            font_size: font_size,
+            root_font_size: root_font_size,
+            viewport: viewport
+        }
+    }
+}
+
+/// Returns a length of given number of points.
+fn length(points: f64) -> PrimitiveValue {
+    return PrimitiveValue::from_dimension_value(&format!("{}pt", points), points, "pt");
+}
+
+/// Returns computed value of a math expression whose lengths are in points. Expressions
+/// with percentages are kept, unless they can be resolved against `percentage_reference`.
+fn compute_math(expression: MathExpression, percentage_reference: Option<f64>) -> PrimitiveValue {
+    if expression.has_percentage() && percentage_reference.is_none() {
+        return PrimitiveValue::from_math(expression);
+    }
+
+    let result = match expression.evaluate(percentage_reference) {
+        Some(result) => result,
+        None => return PrimitiveValue::from_math(expression)
+    };
+
+    if expression.get_type() == Some(MathType::Number) {
+        return PrimitiveValue::from_number(&result.to_string(), result);
+    }
+
+    return length(result);
+}
+
+/// Returns computed value of the `font-size` property in points.
+/// Relative sizes are resolved against the parent's font size, except `rem`, which uses `root_font_size`.
+fn compute_font_size(value: Option<&PrimitiveValue>, parent_font_size: f64, root_font_size: f64, viewport: Viewport) -> f64 {
+    let value = match value {
+        Some(value) => value,
+        None => return parent_font_size
+    };
+
+    if value.is_percentage() {
+        return parent_font_size * value.as_number() / 100.0;
+    }
+
+    let context = LengthContext::new(parent_font_size, root_font_size, viewport);
+
+    if value.is_dimension_value() {
+        let dimension = value.as_dimension_value();
+        return context.to_pt(dimension.value, &dimension.dimension.to_ascii_lowercase()).unwrap_or(parent_font_size);
+    }
+
+    // Percentages of `font-size` refer to the parent's font size too.
+    if let Some(expression) = value.as_math() {
+        return expression.map_lengths(&|value, unit| context.to_pt(value, unit))
+            .and_then(|expression| expression.evaluate(Some(parent_font_size)))
+            .unwrap_or(parent_font_size);
+    }
+
+    // https://www.w3.org/TR/css-fonts-3/#absolute-size-value
+    let scale = match value.as_string().to_ascii_lowercase().as_str() {
+        "xx-small" => 3.0 / 5.0,
+        "x-small" => 3.0 / 4.0,
+        "small" => 8.0 / 9.0,
+        "medium" => 1.0,
+        "large" => 6.0 / 5.0,
+        "x-large" => 3.0 / 2.0,
+        "xx-large" => 2.0,
+        "larger" => return parent_font_size * FONT_SIZE_SCALE,
+        "smaller" => return parent_font_size / FONT_SIZE_SCALE,
+        _ => return parent_font_size
+    };
+
+    return DEFAULT_FONT_SIZE * scale;
+}
+
+/// Returns width of a border or an outline in points, for the `thin`, `medium` and `thick` keywords.
+fn border_width_keyword_to_pt(value: &PrimitiveValue) -> Option<f64> {
+    match value.as_string().to_ascii_lowercase().as_str() {
+        "thin" => Some(0.75),
+        "medium" => Some(MEDIUM_BORDER_WIDTH),
+        "thick" => Some(3.75),
+        _ => None
+    }
+}
+
+/// Computes values of element's properties, which already contain the values inherited from its parent.
+/// All lengths are converted to points, `em`, `rem` and viewport-percentage lengths included.
+/// Percentages are left to the layout, which knows the size of the containing block.
+///
+/// `font-size` is always set, so that relative lengths of descendants can be resolved.
+/// `root_font_size` is `None` for the root element, whose `rem` refers to the initial font size.
+///
+/// https://www.w3.org/TR/css-cascade-4/#computed
+pub fn compute_values(properties: &ElementStyleProperties, parent_font_size: f64, root_font_size: Option<f64>, viewport: Viewport) -> ElementStyleProperties {
+    let font_size = compute_font_size(properties.get("font-size"), parent_font_size, root_font_size.unwrap_or(DEFAULT_FONT_SIZE), viewport);
+    let context = LengthContext::new(font_size, root_font_size.unwrap_or(font_size), viewport);
+    let mut result = ElementStyleProperties::new();
+
+    // `currentColor` computes to the value of `color`, which is inherited, so it is known already.
+    let current_color = properties.get("color").cloned().unwrap_or_else(|| PrimitiveValue::from_color("black", Color::from_rgb(0, 0, 0)));
+
+    for (name, value) in properties.get_entries() {
+        let computed_value = match name.as_str() {
+            "font-size" => continue,
+            _ if value.is_keyword("currentcolor") => current_color.clone(),
+
+            // Percentages of `line-height` are relative to the element's own font size, so they can be computed here.
+            "line-height" if value.is_percentage() => length(font_size * value.as_number() / 100.0),
+            "line-height" if value.is_math() => {
+                match value.as_math().and_then(|expression| expression.map_lengths(&|value, unit| context.to_pt(value, unit))) {
+                    Some(expression) => compute_math(expression, Some(font_size)),
+                    None => value.clone()
+                }
+            }
+
+            "border-top-width" | "border-right-width" | "border-bottom-width" | "border-left-width" | "outline-width" => {
+                match border_width_keyword_to_pt(&value) {
+                    Some(width) => length(width),
+                    None => context.resolve_lengths(&value)
+                }
+            }
+
+            _ => context.resolve_lengths(&value)
+        };
+
+        result.set(&name, computed_value);
+    }
+
+    // Computed values of custom properties are their tokens, with variables substituted already.
+    for (name, value) in properties.get_custom_properties() {
+        result.set(name, value.clone());
+    }
+
+    result.set("font-size", length(font_size));
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Diagnostics;
+    use crate::html::test_support::{parse_document, find_element};
+
+    #[test]
+    fn current_color_is_the_value_of_color() {
+        let document = parse_document("<div style='color: red'>\
+            <p id=a style='color: blue; border-top-color: currentColor'></p>\
+            <p id=b style='color: currentColor; border-top-color: CURRENTCOLOR'></p>\
+            <p id=c style='border-top-color: currentcolor'></p></div>");
+        let mut document = document.borrow_mut();
+        document.compute_styles(Viewport { width: 500.0, height: 800.0 }, &mut Diagnostics::new());
+
+        let rgb = |id: &str, property: &str| {
+            let color = document.get_element_immutable(find_element(&document, id)).get_style_properties().get(property).unwrap().as_color();
+            return (color.red, color.green, color.blue);
+        };
+
+        assert_eq!(rgb("a", "border-top-color"), (0, 0, 255));
+
+        // `color: currentColor` is the parent's color.
+        assert_eq!(rgb("b", "color"), (255, 0, 0));
+        assert_eq!(rgb("b", "border-top-color"), (255, 0, 0));
+        assert_eq!(rgb("c", "border-top-color"), (255, 0, 0));
+    }
+
+    #[test]
+    fn initial_and_unset_give_initial_values() {
+        let document = parse_document("<div style='color: red; text-align: center; margin-top: 5pt; font-family: Courier'>\
+            <p id=a style='color: initial; display: initial; margin-top: inherit; text-align: unset; font-size: initial'></p>\
+            <p id=b style='display: unset; margin-top: unset; font-family: initial'></p></div>");
+        let mut document = document.borrow_mut();
+        document.compute_styles(Viewport { width: 500.0, height: 800.0 }, &mut Diagnostics::new());
+
+        let style = |id: &str| document.get_element_immutable(find_element(&document, id)).get_style_properties().clone();
+        let (a, b) = (style("a"), style("b"));
+
+        let color = a.get("color").unwrap().as_color();
+        assert_eq!((color.red, color.green, color.blue), (0, 0, 0));
+        assert!(a.get("display").unwrap().is_keyword("inline"));
+        assert_eq!(a.get("margin-top").unwrap().as_dimension_value().value, 5.0);
+        assert!(a.get("text-align").unwrap().is_keyword("center"));
+        assert_eq!(a.get_font_size(), DEFAULT_FONT_SIZE);
+
+        // `unset` is the initial value of properties that are not inherited, even if the user agent sets them.
+        assert!(b.get("display").unwrap().is_keyword("inline"));
+        assert_eq!(b.get("margin-top").unwrap().as_dimension_value().value, 0.0);
+
+        // The initial value of `font-family` depends on the user agent, so the layout's default is used.
+        assert!(b.get("font-family").is_none());
+    }
+}
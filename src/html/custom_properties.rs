@@ -0,0 +1,221 @@
+use std::collections::{HashMap, HashSet};
+use crate::css::{PrimitiveValue, CssWideKeyword, Token, is_inherited_property, parse_property_value, get_initial_value, substitute_variables};
+use crate::diagnostics::{Diagnostic, Diagnostics, Severity};
+use crate::html::ElementStyleProperties;
+
+/// Substitutes `var()` references in the values of an element's custom properties.
+/// https://www.w3.org/TR/css-variables-1/#cycles
+struct CustomPropertyResolver<'a> {
+    properties: &'a HashMap<String, PrimitiveValue>,
+
+    /// Values of the properties resolved so far, `None` for those that are invalid.
+    resolved: HashMap<String, Option<Vec<Token>>>,
+
+    /// Properties that are being resolved, each referencing the next one.
+    stack: Vec<String>,
+
+    /// Properties that reference themselves, directly or through other properties.
+    cyclic: HashSet<String>
+}
+
+impl<'a> CustomPropertyResolver<'a> {
+
+    /// Returns the value of given custom property with all variables substituted,
+    /// or `None` if it is not defined or is invalid.
+    fn resolve(&mut self, name: &str) -> Option<Vec<Token>> {
+        if let Some(value) = self.resolved.get(name) {
+            return value.clone();
+        }
+
+        // All properties in a cycle are invalid, even if they have a fallback.
+        if let Some(position) = self.stack.iter().position(|property| property == name) {
+            self.cyclic.extend(self.stack[position..].iter().cloned());
+            return None;
+        }
+
+        let tokens = self.properties.get(name)?.as_unparsed()?.tokens.clone();
+
+        self.stack.push(name.to_string());
+        let value = substitute_variables(&tokens, &mut |reference| self.resolve(reference));
+        self.stack.pop();
+
+        let value = if self.cyclic.contains(name) { None } else { value };
+        self.resolved.insert(name.to_string(), value.clone());
+        return value;
+    }
+
+    fn new(properties: &'a HashMap<String, PrimitiveValue>) -> Self {
+        Self {
+            properties: properties,
+            resolved: HashMap::new(),
+            stack: Vec::new(),
+            cyclic: HashSet::new()
+        }
+    }
+}
+
+/// Returns what a property is when its value is `unset`: the parent's value
+/// for inherited properties, the initial value for the others.
+fn unset_value(name: &str, parent: &ElementStyleProperties) -> Option<PrimitiveValue> {
+    if !is_inherited_property(name) {
+        return get_initial_value(name);
+    }
+
+    return parent.get(name).cloned();
+}
+
+/// Parses the value of property `name` after its variables have been substituted.
+/// If the value was given to a shorthand, the shorthand is expanded and the longhand's value is picked.
+fn parse_substituted_value(name: &str, shorthand: &Option<String>, tokens: &[Token], parent: &ElementStyleProperties) -> Option<Option<PrimitiveValue>> {
+    let property_name = shorthand.as_deref().unwrap_or(name);
+    let value = parse_property_value(property_name, tokens)?
+        .into_iter()
+        .find(|(longhand, _)| longhand == name)?
+        .1;
+
+    let value = match value.as_css_wide_keyword() {
+        None => Some(value),
+        Some(CssWideKeyword::Inherit) => parent.get(name).cloned(),
+        Some(CssWideKeyword::Initial) => get_initial_value(name),
+        Some(CssWideKeyword::Unset) | Some(CssWideKeyword::Revert) => unset_value(name, parent)
+    };
+
+    return Some(value);
+}
+
+/// Substitutes `var()` references in element's properties, which have to be merged with the parent's
+/// properties already, and parses the values that contained them.
+///
+/// Custom properties that are part of a reference cycle or reference an undefined property
+/// without a fallback are left out. Other properties whose value cannot be substituted, or
+/// is invalid after substitution, behave as if they were `unset`.
+/// https://www.w3.org/TR/css-variables-1/#invalid-at-computed-value-time
+pub fn substitute_custom_properties(properties: &ElementStyleProperties, parent: &ElementStyleProperties, element_index: usize, diagnostics: &mut Diagnostics) -> ElementStyleProperties {
+    let mut resolver = CustomPropertyResolver::new(properties.get_custom_properties());
+    let mut result = ElementStyleProperties::new();
+
+    let mut custom_property_names: Vec<&String> = properties.get_custom_properties().keys().collect();
+    custom_property_names.sort();
+
+    for name in custom_property_names {
+        match resolver.resolve(name) {
+            Some(tokens) => result.set(name, PrimitiveValue::from_unparsed(tokens, None)),
+            None => {
+                let message = if resolver.cyclic.contains(name) {
+                    format!("custom property '{}' references itself, it is ignored", name)
+                } else {
+                    format!("custom property '{}' references an undefined variable, it is ignored", name)
+                };
+
+                diagnostics.push(Diagnostic::new(Severity::Warning, message).with_element(element_index));
+            }
+        }
+    }
+
+    // Sorted, so that problems are reported in the same order every time.
+    let mut entries: Vec<(String, PrimitiveValue)> = properties.get_entries().into_iter().collect();
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    for (name, value) in entries {
+        let unparsed = match value.as_unparsed() {
+            Some(unparsed) => unparsed,
+            None => {
+                result.set(&name, value);
+                continue;
+            }
+        };
+
+        let substituted_value = substitute_variables(&unparsed.tokens, &mut |reference| resolver.resolve(reference))
+            .and_then(|tokens| parse_substituted_value(&name, &unparsed.shorthand, &tokens, parent));
+
+        let value = match substituted_value {
+            Some(value) => value,
+            None => {
+                let message = format!("invalid value of '{}' property after substituting variables: {}", name, value.as_string());
+                diagnostics.push(Diagnostic::new(Severity::Warning, message).with_element(element_index));
+                unset_value(&name, parent)
+            }
+        };
+
+        if let Some(value) = value {
+            result.set(&name, value);
+        }
+    }
+
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::Diagnostics;
+    use crate::html::{Document, Viewport};
+    use crate::html::test_support::{parse_document, find_element};
+
+    /// Computes styles of given document and returns the messages of all problems found.
+    fn compute_styles(document: &mut Document) -> Vec<String> {
+        let mut diagnostics = Diagnostics::new();
+        document.compute_styles(Viewport { width: 500.0, height: 800.0 }, &mut diagnostics);
+        return diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect();
+    }
+
+    /// Returns the computed values of given properties of the element with id `x`, `None` for those that are not set.
+    fn computed_values(html: &str, properties: &[&str]) -> Vec<Option<String>> {
+        let document = parse_document(html);
+        let mut document = document.borrow_mut();
+        compute_styles(&mut document);
+
+        let style = document.get_element_immutable(find_element(&document, "x")).get_style_properties();
+        return properties.iter()
+            .map(|property| {
+                let value = if property.starts_with("--") {
+                    style.get_custom_properties().get(*property)
+                } else {
+                    style.get(property)
+                };
+
+                value.map(|value| value.as_string().trim().to_string())
+            })
+            .collect();
+    }
+
+    fn some(value: &str) -> Option<String> {
+        return Some(value.to_string());
+    }
+
+    #[test]
+    fn variables_are_substituted() {
+        let html = "<div style='--align: right'><p id=x style='--inner: var(--align); text-align: var(--inner)'></p></div>";
+        assert_eq!(computed_values(html, &["--inner", "text-align"]), vec![some("right"), some("right")]);
+    }
+
+    #[test]
+    fn fallback_is_used_for_undefined_variables() {
+        let html = "<p id=x style='text-align: var(--undefined, center); --a: var(--b, var(--c, left)); --d: var(--undefined)'></p>";
+        assert_eq!(computed_values(html, &["text-align", "--a", "--d"]), vec![some("center"), some("left"), None]);
+
+        // The fallback is not used when the variable is defined, even if it is empty.
+        let html = "<p id=x style='--empty: ; --a: x var(--empty, y) z'></p>";
+        assert_eq!(computed_values(html, &["--a"]), vec![some("x  z")]);
+    }
+
+    #[test]
+    fn cycles_make_all_properties_in_them_invalid() {
+        let html = "<p id=x style='--a: var(--b); --b: var(--a, 1px); --c: var(--c, 2px); --d: var(--a, 3px); text-align: var(--b, center)'></p>";
+        assert_eq!(
+            computed_values(html, &["--a", "--b", "--c", "--d", "text-align"]),
+            vec![None, None, None, some("3px"), some("center")]
+        );
+
+        let document = parse_document("<p style='--a: var(--a)'></p>");
+        let messages = compute_styles(&mut document.borrow_mut());
+        assert_eq!(messages, vec![String::from("custom property '--a' references itself, it is ignored")]);
+    }
+
+    #[test]
+    fn invalid_values_after_substitution_are_unset() {
+        let html = "<div style='text-align: center'><p id=x style='--a: 5px; text-align: var(--a); display: var(--a)'></p></div>";
+
+        // `text-align` is inherited and `display` is not, so it gets its initial value.
+        assert_eq!(computed_values(html, &["text-align", "display"]), vec![some("center"), some("inline")]);
+    }
+}
@@ -3,8 +3,12 @@
 use std::ops::IndexMut;
 use std::rc::Rc;
 use std::cell::RefCell;
-use crate::css::PrimitiveValue;
-use crate::html::{Element, ElementStyleProperties};
+use crate::css::{PrimitiveValue, Stylesheet, PageRule, MarginBoxPosition, MediaEnvironment};
+use crate::html::{Element, ElementStyleProperties, PageContext, cascade_declarations, cascade_page_declarations, cascade_margin_box_declarations};
+use crate::html::computed_values::{compute_values, Viewport, DEFAULT_FONT_SIZE};
+use crate::html::custom_properties::substitute_custom_properties;
+use crate::diagnostics::Diagnostics;
+use crate::error::{Error, Result};
 
 pub type DocumentRef = Rc<RefCell<Document>>;
 
@@ -19,60 +23,149 @@ pub struct Document {
     /// A flat list of all elements that have ever been created
     /// by this document.
     elements: Vec<Element>,
-    
+
+    /// Stylesheets from the document's `<style>` elements, in document order.
+    stylesheets: Vec<Stylesheet>,
+
+    /// `@page` rules of all stylesheets whose media queries match, in document order.
+    page_rules: Vec<PageRule>
+
 }
 
 impl Document {
 
     /// Sets element at `element_index` to be a child of element at `parent_index`.
-    pub fn add_element(&mut self, element_index: usize, parent_index: usize) {
-        assert!(element_index != parent_index, "circular reference encountered");
-        assert!(element_index < self.elements.len(), "attempted to add an unknown element");
-        assert!(parent_index < self.elements.len(), "attempted to add an element to an unknown parent");
+    /// Does nothing if the element already is a child of that parent.
+    pub fn add_element(&mut self, element_index: usize, parent_index: usize) -> Result<()> {
+        if element_index == parent_index {
+            return Err(Error::Html(String::from("circular reference encountered")));
+        }
+
+        if element_index >= self.elements.len() || parent_index >= self.elements.len() {
+            return Err(Error::Html(String::from("attempted to add an unknown element or to add an element to an unknown parent")));
+        }
 
         let parent = self.get_element(parent_index);
         if parent.children.contains(&element_index) {
-            println!("warning: attempted to add an element to its parent multiple times");
-            return;
+            return Ok(());
         }
 
         parent.children.push(element_index);
 
-        let mut child = self.get_element(element_index);
+        let child = self.get_element(element_index);
         child.parent = parent_index;
         child.has_parent = true;
+        return Ok(());
+    }
+
+    pub fn add_element_to_root(&mut self, element_index: usize) -> Result<()> {
+        return self.add_element(element_index, self.get_root_index());
+    }
+
+    pub fn add_stylesheet(&mut self, stylesheet: Stylesheet) {
+        self.stylesheets.push(stylesheet);
     }
 
-    pub fn add_element_to_root(&mut self, element_index: usize) {
-        self.add_element(element_index, self.get_root_index());
+    pub fn get_stylesheets(&self) -> &Vec<Stylesheet> {
+        return &self.stylesheets;
+    }
+
+    pub fn get_page_rules(&self) -> &Vec<PageRule> {
+        return &self.page_rules;
+    }
+
+    /// Replaces style properties of every element with the winning declarations
+    /// from the rules that match it and its inline `style` attribute, see `cascade_declarations`.
+    /// Rules in `@media` blocks only apply if their media queries match `media_environment`.
+    pub fn apply_stylesheets(&mut self, media_environment: &MediaEnvironment) {
+        for element_index in 0..self.elements.len() {
+            let mut properties = ElementStyleProperties::new();
+            for declaration in cascade_declarations(self, element_index, media_environment) {
+                properties.set(&declaration.name, declaration.value);
+            }
+
+            self.get_element(element_index).set_style_properties(properties);
+        }
+
+        self.page_rules = self.stylesheets.iter()
+            .flat_map(|stylesheet| stylesheet.page_rules.iter())
+            .filter(|rule| rule.media.iter().all(|media| media.matches(media_environment)))
+            .cloned()
+            .collect();
+    }
+
+    /// Returns computed values of the properties that `@page` rules set for given page.
+    /// Nothing is inherited into the page context, so properties that are not set
+    /// (or are set to a CSS-wide keyword) are left for the caller to default.
+    pub fn compute_page_style(&self, page: &PageContext, viewport: Viewport) -> ElementStyleProperties {
+        let mut properties = ElementStyleProperties::new();
+        for declaration in cascade_page_declarations(self, page) {
+            properties.set(&declaration.name, declaration.value);
+        }
+
+        return compute_values(&properties, DEFAULT_FONT_SIZE, None, viewport);
     }
 
-    /// Applies element's styling properties to all its descendants.
-    /// Not all properties will be applied - only those, which are
-    /// supposed to be shared between parents and their children (e.g. text color).
-    pub fn cascade_element_styles(&mut self, element_index: usize) {
-        let mut doc = self.clone();
-        let element = self.elements.index_mut(element_index);
-        let parent_props = element.get_style_properties();
-        let children_num = element.children.len();
+    /// Returns computed values of the properties of given margin box of given page.
+    /// Margin boxes inherit from the page context, whose computed values are `page_properties`.
+    /// https://www.w3.org/TR/css-page-3/#margin-dimension
+    pub fn compute_margin_box_style(&self, page: &PageContext, position: MarginBoxPosition, page_properties: &ElementStyleProperties, viewport: Viewport) -> ElementStyleProperties {
+        let mut properties = ElementStyleProperties::new();
+
+        // Content of each margin box is aligned towards the page area by default,
+        // as if the user agent had a margin rule for each box.
+        let (text_align, vertical_align) = default_margin_box_alignment(position);
+        properties.set("text-align", PrimitiveValue::from_identifier(text_align));
+        properties.set("vertical-align", PrimitiveValue::from_identifier(vertical_align));
 
-        for child_index_i in 0..children_num {
-            let child_index = element.children[child_index_i];
-            let child = doc.get_element(child_index);
-            child.set_style_properties(ElementStyleProperties::merge(child.get_style_properties(), parent_props));
-            doc.cascade_element_styles(child_index);
+        for declaration in cascade_margin_box_declarations(self, page, position) {
+            properties.set(&declaration.name, declaration.value);
         }
 
-        self.elements = doc.elements;
+        let page_font_size = page_properties.get("font-size")
+            .map(|font_size| font_size.as_dimension_value().value)
+            .unwrap_or(DEFAULT_FONT_SIZE);
+
+        let properties = ElementStyleProperties::merge(&properties, page_properties);
+        return compute_values(&properties, page_font_size, None, viewport);
+    }
+
+    /// Replaces the cascaded style properties of every element with their computed values,
+    /// which include the values inherited from the element's parent, see `compute_values`.
+    /// Expects `apply_stylesheets` to have been called first.
+    ///
+    /// Values that turn out to be invalid once their variables are substituted are reported to `diagnostics`.
+    pub fn compute_styles(&mut self, viewport: Viewport, diagnostics: &mut Diagnostics) {
+        self.compute_element_styles(self.root, &ElementStyleProperties::new(), None, viewport, diagnostics);
+    }
+
+    fn compute_element_styles(&mut self, element_index: usize, parent_properties: &ElementStyleProperties, root_font_size: Option<f64>, viewport: Viewport, diagnostics: &mut Diagnostics) {
+        let parent_font_size = parent_properties.get("font-size")
+            .map(|font_size| font_size.as_dimension_value().value)
+            .unwrap_or(DEFAULT_FONT_SIZE);
+
+        let element = self.get_element(element_index);
+        let properties = ElementStyleProperties::merge(element.get_style_properties(), parent_properties);
+        let properties = substitute_custom_properties(&properties, parent_properties, element_index, diagnostics);
+        let properties = compute_values(&properties, parent_font_size, root_font_size, viewport);
+        element.set_style_properties(properties.clone());
+
+        // The document itself is not an element, `rem` refers to the font size of its child, the `<html>` element.
+        let root_font_size = match (root_font_size, element_index == self.root) {
+            (None, false) => properties.get("font-size").map(|font_size| font_size.as_dimension_value().value),
+            _ => root_font_size
+        };
+
+        for child_index in self.get_element_immutable(element_index).children.clone() {
+            self.compute_element_styles(child_index, &properties, root_font_size, viewport, diagnostics);
+        }
     }
 
     /// Attempts to find the value of specified style property in given element or one of its ancestors.
     pub fn get_element_style_property(&self, element_index: usize, property_name: &str) -> Option<&PrimitiveValue> {
         let mut element = Some(self.get_element_immutable(element_index));
 
-        while element.is_some() {
-            let elem = element.unwrap();
-
+        while let Some(elem) = element {
             if let Some(prop) = elem.get_style_property(property_name) {
                 return Some(prop);
             }
@@ -87,13 +180,32 @@ impl Document {
         return None;
     }
 
+    /// Returns the text of all text nodes in given element, in document order. Text nodes lose
+    /// their surrounding white space when parsed, so they are separated by a single space.
+    pub fn get_text_content(&self, element_index: usize) -> String {
+        let mut texts = Vec::<&str>::new();
+        self.collect_text(element_index, &mut texts);
+        return texts.join(" ");
+    }
+
+    fn collect_text<'a>(&'a self, element_index: usize, texts: &mut Vec<&'a str>) {
+        let element = self.get_element_immutable(element_index);
+        if element.is_text_node() {
+            texts.push(&element.text);
+            return;
+        }
+
+        for child_index in &element.children {
+            self.collect_text(*child_index, texts);
+        }
+    }
+
     /// Creates a new Element and returns its index.
     pub fn create_element(&mut self, tag: &str) -> usize {
         let idx = self.elements.len();
         let mut element = Element::default();
         element.index = idx;
         element.tag = String::from(tag);
-        element.add_default_style_properties();
 
         self.elements.push(element);
         return idx;
@@ -106,7 +218,6 @@ impl Document {
         element.index = idx;
         element.tag = String::from("#text");
         element.text = String::from(text);
-        element.add_default_style_properties();
 
         self.elements.push(element);
         return idx;
@@ -122,7 +233,7 @@ impl Document {
         return &self.elements[index];
     }
 
-    pub fn get_elements(&self) -> std::slice::Iter<Element> {
+    pub fn get_elements(&self) -> std::slice::Iter<'_, Element> {
         return self.elements.iter();
     }
 
@@ -144,7 +255,9 @@ impl Document {
     pub fn new() -> Rc<RefCell<Document>> {
         let mut document = Document {
             root: 0,
-            elements: Vec::new()
+            elements: Vec::new(),
+            stylesheets: Vec::new(),
+            page_rules: Vec::new()
         };
 
         document.create_element("root");
@@ -152,3 +265,18 @@ impl Document {
         return Rc::new(RefCell::new(document))
     }
 }
+
+/// Returns the initial `text-align` and `vertical-align` of given margin box.
+/// https://www.w3.org/TR/css-page-3/#margin-text-alignment
+fn default_margin_box_alignment(position: MarginBoxPosition) -> (&'static str, &'static str) {
+    match position {
+        MarginBoxPosition::TopLeftCorner | MarginBoxPosition::BottomLeftCorner => ("right", "middle"),
+        MarginBoxPosition::TopLeft | MarginBoxPosition::BottomLeft => ("left", "middle"),
+        MarginBoxPosition::TopCenter | MarginBoxPosition::BottomCenter => ("center", "middle"),
+        MarginBoxPosition::TopRight | MarginBoxPosition::BottomRight => ("right", "middle"),
+        MarginBoxPosition::TopRightCorner | MarginBoxPosition::BottomRightCorner => ("left", "middle"),
+        MarginBoxPosition::LeftTop | MarginBoxPosition::RightTop => ("center", "top"),
+        MarginBoxPosition::LeftMiddle | MarginBoxPosition::RightMiddle => ("center", "middle"),
+        MarginBoxPosition::LeftBottom | MarginBoxPosition::RightBottom => ("center", "bottom")
+    }
+}
@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 
 use std::collections::HashMap;
-use crate::css::{parse_inline, PrimitiveValue};
+use crate::css::{PrimitiveValue, PropertyDeclaration};
 use crate::html::ElementStyleProperties;
 
 #[derive(Debug, Clone)]
@@ -26,6 +26,10 @@ pub struct Element {
     /// Contains all attributes of this element.
     attributes: HashMap<String, String>,
 
+    /// Declarations from element's `style` attribute.
+    /// They take precedence over all stylesheets of the same origin and importance.
+    inline_style: Vec<PropertyDeclaration>,
+
     /// Contains all style properties.
     /// This list contains only those properties, which are actually active
     /// meaning that if someone overwrites a property from a CSS file using
@@ -38,13 +42,14 @@ impl Element {
 
     /// Adds specified attribute to the node.
     /// An attribute's name must not contains spaces.
-    pub fn add_attribute(&mut self, name: &str, value: &str) {
-        if name.contains(" ") {
-            println!("error: attribute's name must not contain spaces (found \"{}\")", name);
-            return;
+    /// Returns whether the attribute was added.
+    pub fn add_attribute(&mut self, name: &str, value: &str) -> bool {
+        if name.contains(' ') {
+            return false;
         }
 
         self.attributes.insert(String::from(name), String::from(value));
+        return true;
     }
 
     /// Returns value of specified attribute, if added to the node.
@@ -63,24 +68,19 @@ impl Element {
         return self.attributes.contains_key(name);
     }
 
-    pub fn add_default_style_properties(&mut self) {
-        let mut default_styles = "";
-
-        match self.tag.as_str() {
-            "html" => {
-                
-            },
-
-            "div" => {
-                default_styles = "display: block; color: black;";
-            },
+    pub fn set_inline_style(&mut self, declarations: Vec<PropertyDeclaration>) {
+        self.inline_style = declarations;
+    }
 
-            _ => {}
-        }
+    pub fn get_inline_style(&self) -> &Vec<PropertyDeclaration> {
+        return &self.inline_style;
+    }
 
-        let styles = parse_inline(default_styles);
-        for prop in styles {
-            self.style.set_default(&prop.name, prop.value);
+    /// Returns whether the element has given class in its `class` attribute.
+    pub fn has_class(&self, name: &str) -> bool {
+        match self.get_attribute("class") {
+            Some(classes) => classes.split_whitespace().any(|class| class == name),
+            None => false
         }
     }
 
@@ -107,12 +107,33 @@ impl Element {
         return self.style.has(name);
     }
 
+    /// Returns whether the element generates any boxes, that is whether its `display` is not `none`.
+    /// Descendants of elements that are not displayed are not displayed either.
+    pub fn is_displayed(&self) -> bool {
+        match self.get_style_property("display") {
+            Some(display) => !display.as_string().eq_ignore_ascii_case("none"),
+            None => true
+        }
+    }
+
+    /// Returns names of the font families from the `font-family` property, in order of preference.
+    pub fn get_font_families(&self) -> Vec<String> {
+        return self.style.get_font_families();
+    }
+
+    /// Returns the computed font size in points.
+    pub fn get_font_size(&self) -> f64 {
+        return self.style.get_font_size();
+    }
+
     /// Returns whether this is a text node.
     pub fn is_text_node(&self) -> bool {
         return self.tag == "#text";
     }
+}
 
-    pub fn default() -> Element {
+impl Default for Element {
+    fn default() -> Element {
         Element {
             index: 0,
             tag: String::default(),
@@ -121,6 +142,7 @@ impl Element {
             has_parent: false,
             children: Vec::new(),
             attributes: HashMap::new(),
+            inline_style: Vec::new(),
             style: ElementStyleProperties::new()
         }
     }
@@ -1,76 +1,103 @@
 #![allow(dead_code)]
 
 use std::collections::HashMap;
-use crate::css::PrimitiveValue;
+use crate::css::{PrimitiveValue, CssWideKeyword, is_inherited_property, is_custom_property, get_initial_value};
+use crate::html::DEFAULT_FONT_SIZE;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ElementStyleProperties {
     properties: HashMap<String, PrimitiveValue>,
-    default_properties: HashMap<String, PrimitiveValue>
+
+    /// Custom properties, like `--brand-color`. Their values are unparsed tokens.
+    custom_properties: HashMap<String, PrimitiveValue>
 }
 
 impl ElementStyleProperties {
     pub fn set(&mut self, name: &str, value: PrimitiveValue) {
-        self.properties.insert(name.to_string(), value);
-    }
-
-    pub fn set_default(&mut self, name: &str, value: PrimitiveValue) {
-        self.default_properties.insert(name.to_string(), value);
+        if is_custom_property(name) {
+            self.custom_properties.insert(name.to_string(), value);
+        } else {
+            self.properties.insert(name.to_string(), value);
+        }
     }
 
     pub fn get(&self, name: &str) -> Option<&PrimitiveValue> {
-        if self.properties.contains_key(name) {
-            return self.properties.get(name);
+        if is_custom_property(name) {
+            return self.custom_properties.get(name);
         }
 
-        return self.default_properties.get(name);
+        return self.properties.get(name);
     }
 
-    pub fn get_default(&self, name: &str) -> Option<&PrimitiveValue> {
-        return self.default_properties.get(name);
+    pub fn has(&self, name: &str) -> bool {
+        return self.get(name).is_some();
     }
 
-    pub fn has(&self, name: &str) -> bool {
-        return self.properties.contains_key(name);
+    /// Returns all properties except the custom ones.
+    pub fn get_entries(&self) -> HashMap<String, PrimitiveValue> {
+        return self.properties.clone();
     }
 
-    pub fn has_default(&self, name: &str) -> bool {
-        return self.default_properties.contains_key(name);
+    pub fn get_custom_properties(&self) -> &HashMap<String, PrimitiveValue> {
+        return &self.custom_properties;
     }
 
-    pub fn get_entries(&self) -> HashMap<String, PrimitiveValue> {
-        return self.properties.clone();
+    /// Returns names of the font families from the `font-family` property, in order of preference.
+    pub fn get_font_families(&self) -> Vec<String> {
+        match self.get("font-family") {
+            Some(families) if families.is_list() => families.as_items().iter().map(|family| family.as_string().clone()).collect(),
+            _ => Vec::new()
+        }
     }
 
-    pub fn get_default_entries(&self) -> HashMap<String, PrimitiveValue> {
-        return self.default_properties.clone();
+    /// Returns the computed font size in points.
+    pub fn get_font_size(&self) -> f64 {
+        match self.get("font-size") {
+            Some(font_size) if font_size.is_dimension_value() => font_size.as_dimension_value().value,
+            _ => DEFAULT_FONT_SIZE
+        }
     }
 
-    /// Merges two style properties and returns the result.
+    /// Merges child's properties with the properties it inherits from its parent and returns the result.
+    /// The parent's properties are expected to be merged already, so they contain no CSS-wide keywords.
+    ///
+    /// A property whose value is `initial` gets its initial value, or is left out if the initial value is not known.
+    /// A property that is not set is inherited if it is an inherited property, `unset` also gives the others their initial value.
+    /// Custom properties are always inherited.
     pub fn merge(child: &ElementStyleProperties, parent: &ElementStyleProperties) -> ElementStyleProperties {
         let mut result = ElementStyleProperties::new();
 
-        for (name, value) in parent.get_entries() {
-            if can_be_inherited(&name) {
+        let child_entries = child.get_entries().into_iter().chain(child.custom_properties.clone());
+        for (name, value) in child_entries {
+            let inherit = match value.as_css_wide_keyword() {
+                None => {
+                    result.set(&name, value);
+                    continue;
+                }
+
+                Some(CssWideKeyword::Inherit) => true,
+                Some(CssWideKeyword::Initial) => false,
+
+                // The cascade replaces `revert` with the user agent's value,
+                // unless there is none - it behaves like `unset` then.
+                Some(CssWideKeyword::Unset) | Some(CssWideKeyword::Revert) => is_inherited_property(&name) || is_custom_property(&name)
+            };
+
+            let value = if inherit { parent.get(&name).cloned() } else { get_initial_value(&name) };
+            if let Some(value) = value {
                 result.set(&name, value);
             }
         }
 
-        for (name, value) in child.get_entries() {
-            result.set(&name, value);
-        }
-
-        for (name, value) in parent.get_default_entries() {
-            if can_be_inherited(&name) {
-                if !result.has(&name) {
-                    result.set(&name, value);
-                }
+        for (name, value) in parent.get_entries() {
+            if is_inherited_property(&name) && !child.has(&name) {
+                result.set(&name, value);
             }
         }
 
-        for (name, value) in child.get_default_entries() {
-            if !result.has(&name) {
-                result.set(&name, value);
+        for (name, value) in &parent.custom_properties {
+            if !child.has(name) {
+                result.set(name, value.clone());
             }
         }
 
@@ -80,45 +107,7 @@ impl ElementStyleProperties {
     pub fn new() -> Self {
         Self {
             properties: HashMap::new(),
-            default_properties: HashMap::new()
+            custom_properties: HashMap::new()
         }
     }
 }
-
-lazy_static! {
-    // https://drafts.csswg.org/css2/#property-index
-    static ref INHERITED_PROPERTIES: Vec<&'static str> = vec![
-        "border-collapse",
-        "border-spacing",
-        "caption-side",
-        "color",
-        "cursor",
-        "direction",
-        "empty-cells",
-        "font",
-        "font-family",
-        "font-size",
-        "font-style",
-        "font-variant",
-        "font-weight",
-        "letter-spacing",
-        "line-height",
-        "list-style",
-        "list-style-image",
-        "list-style-position",
-        "list-style-type",
-        "orphans",
-        "quotes",
-        "text-align",
-        "text-ident",
-        "text-transform",
-        "visibility",
-        "white-space",
-        "widows",
-        "word-spacing"
-    ];
-}
-
-fn can_be_inherited(name: &str) -> bool {
-    return INHERITED_PROPERTIES.contains(&name);
-}
\ No newline at end of file
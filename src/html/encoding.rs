@@ -0,0 +1,135 @@
+use encoding_rs::{Encoding, UTF_8, UTF_16BE, UTF_16LE};
+
+/// Number of bytes the `<meta charset>` pre-scan looks at.
+/// https://html.spec.whatwg.org/multipage/parsing.html#prescan-a-byte-stream-to-determine-its-encoding
+const PRESCAN_LENGTH: usize = 1024;
+
+/// Decodes raw bytes of an HTML document into a string.
+///
+/// The encoding is determined (in order) from the byte order mark,
+/// a `<meta charset>` or `<meta http-equiv="Content-Type">` declaration
+/// found near the beginning of the document and, if none of them are
+/// present, UTF-8 is assumed.
+pub fn decode_html_bytes(bytes: &[u8]) -> String {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+        return text.into_owned();
+    }
+
+    let encoding = prescan_meta_charset(bytes).unwrap_or(UTF_8);
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    return text.into_owned();
+}
+
+/// Looks for a charset declaration in `<meta>` tags at the beginning of the document.
+fn prescan_meta_charset(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = &bytes[..bytes.len().min(PRESCAN_LENGTH)];
+    let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+    let mut search_from = 0;
+
+    while let Some(tag_start) = head[search_from..].find("<meta") {
+        let tag_start = search_from + tag_start;
+        let tag_end = match head[tag_start..].find('>') {
+            Some(end) => tag_start + end,
+            None => break
+        };
+
+        if let Some(label) = find_charset_label(&head[tag_start..tag_end]) {
+            if let Some(encoding) = Encoding::for_label(label.as_bytes()) {
+                // A document that was decoded as ASCII-compatible text cannot
+                // really be UTF-16, so the specification says to use UTF-8 instead.
+                if encoding == UTF_16BE || encoding == UTF_16LE {
+                    return Some(UTF_8);
+                }
+
+                return Some(encoding);
+            }
+        }
+
+        search_from = tag_end;
+    }
+
+    return None;
+}
+
+/// Extracts the value following `charset=` from the contents of a single `<meta>` tag.
+fn find_charset_label(tag: &str) -> Option<String> {
+    let value_start = tag.find("charset")? + "charset".len();
+    let value = tag[value_start..].trim_start();
+    let value = value.strip_prefix('=')?.trim_start();
+    let value = value.trim_start_matches(['"', '\'']);
+    let label: String = value
+        .chars()
+        .take_while(|c| !c.is_whitespace() && *c != '"' && *c != '\'' && *c != ';' && *c != '/')
+        .collect();
+
+    if label.is_empty() {
+        return None;
+    }
+
+    return Some(label);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `café` in windows-1252, which is not valid UTF-8.
+    const CAFE_WINDOWS_1252: &[u8] = b"caf\xe9";
+
+    fn document(head: &str, body: &[u8]) -> Vec<u8> {
+        let mut bytes = format!("<html><head>{}</head><body>", head).into_bytes();
+        bytes.extend_from_slice(body);
+        return bytes;
+    }
+
+    #[test]
+    fn byte_order_marks_win() {
+        assert_eq!(decode_html_bytes(b"\xef\xbb\xbfcaf\xc3\xa9"), "café");
+        assert_eq!(decode_html_bytes(b"\xff\xfec\x00a\x00f\x00\xe9\x00"), "café");
+        assert_eq!(decode_html_bytes(b"\xfe\xff\x00c\x00a\x00f\x00\xe9"), "café");
+
+        // The byte order mark is used even if a `<meta>` declares another encoding.
+        let mut bytes = b"\xef\xbb\xbf".to_vec();
+        bytes.extend(document("<meta charset=windows-1252>", "café".as_bytes()));
+        assert!(decode_html_bytes(&bytes).ends_with("<body>café"));
+    }
+
+    #[test]
+    fn meta_charset() {
+        assert!(decode_html_bytes(&document("<meta charset=windows-1252>", CAFE_WINDOWS_1252)).ends_with("café"));
+        assert!(decode_html_bytes(&document("<META CharSet = 'latin1' >", CAFE_WINDOWS_1252)).ends_with("café"));
+        assert!(decode_html_bytes(&document("<meta charset=\"iso-8859-1\"/>", CAFE_WINDOWS_1252)).ends_with("café"));
+
+        // Unknown labels are skipped, and UTF-8 is the default.
+        assert!(decode_html_bytes(&document("<meta charset=unknown><meta charset=windows-1252>", CAFE_WINDOWS_1252)).ends_with("café"));
+        assert!(decode_html_bytes(&document("<meta charset=unknown>", "café".as_bytes())).ends_with("café"));
+        assert!(decode_html_bytes(&document("", CAFE_WINDOWS_1252)).ends_with("caf\u{fffd}"));
+    }
+
+    #[test]
+    fn meta_http_equiv() {
+        let head = "<meta http-equiv=\"Content-Type\" content=\"text/html; charset=windows-1252\">";
+        assert!(decode_html_bytes(&document(head, CAFE_WINDOWS_1252)).ends_with("café"));
+
+        let head = "<meta content='text/html;charset=ISO-8859-1' http-equiv='content-type'>";
+        assert!(decode_html_bytes(&document(head, CAFE_WINDOWS_1252)).ends_with("café"));
+    }
+
+    #[test]
+    fn utf_16_labels_mean_utf_8() {
+        assert!(decode_html_bytes(&document("<meta charset=utf-16>", "café".as_bytes())).ends_with("café"));
+        assert!(decode_html_bytes(&document("<meta charset=utf-16be>", "café".as_bytes())).ends_with("café"));
+    }
+
+    #[test]
+    fn only_the_beginning_of_the_document_is_scanned() {
+        let padding = " ".repeat(PRESCAN_LENGTH - "<html><head>".len() - "<meta charset=windows-1252>".len());
+        let bytes = document(&format!("{}<meta charset=windows-1252>", padding), CAFE_WINDOWS_1252);
+        assert!(decode_html_bytes(&bytes).ends_with("café"));
+
+        // One byte more, and the end of the declaration is past the scanned part.
+        let bytes = document(&format!(" {}<meta charset=windows-1252>", padding), CAFE_WINDOWS_1252);
+        assert!(decode_html_bytes(&bytes).ends_with("caf\u{fffd}"));
+    }
+}
@@ -1,13 +1,26 @@
 mod parser;
+mod encoding;
 mod document;
 mod element;
 mod element_style_properties;
+mod selector_matching;
+mod cascade;
+mod computed_values;
+mod custom_properties;
+mod stylesheet_loader;
+mod user_agent_stylesheet;
 
-pub use self::parser::parse_text;
+#[cfg(test)]
+mod test_support;
+
+pub use self::parser::{parse_text, parse_bytes};
 pub use self::document::{
     Document,
     DocumentRef,
 };
 
 pub use self::element::Element;
-pub use self::element_style_properties::ElementStyleProperties;
\ No newline at end of file
+pub use self::element_style_properties::ElementStyleProperties;
+pub use self::selector_matching::{PageContext, matches_selector, matches_page_selector};
+pub use self::cascade::{cascade_declarations, cascade_page_declarations, cascade_margin_box_declarations};
+pub use self::computed_values::{Viewport, DEFAULT_FONT_SIZE, MEDIUM_BORDER_WIDTH};
@@ -1,12 +1,18 @@
 use crate::html;
-use crate::css::{parse_inline as parse_inline_css};
+use crate::html::encoding::decode_html_bytes;
+use crate::html::stylesheet_loader::StylesheetLoader;
+use crate::css::Parser as CssParser;
+use crate::css::{MediaEnvironment, MediaQueryList, parse_media_list};
+use crate::generator::{ResourcesManager, resolve_relative_path};
+use crate::diagnostics::Diagnostics;
+use crate::error::{Error, Result};
 use html5ever::driver::ParseOpts;
 use html5ever::tendril::TendrilSink;
 use html5ever::tree_builder::TreeBuilderOpts;
 use html5ever::{parse_document};
 use markup5ever_rcdom as rcdom;
 
-fn walk(handle: &rcdom::Handle, target: &mut html::DocumentRef) -> Option<usize> {
+fn walk(handle: &rcdom::Handle, target: &mut html::DocumentRef, loader: &mut StylesheetLoader, diagnostics: &mut Diagnostics) -> Result<Option<usize>> {
     let node = handle;
     let mut result: Option<usize> = None;
 
@@ -25,7 +31,7 @@ fn walk(handle: &rcdom::Handle, target: &mut html::DocumentRef) -> Option<usize>
 
         rcdom::NodeData::Element { ref name, ref attrs, .. } => {
             let mut doc = target.borrow_mut();
-            let element_idx = doc.create_element(&name.local.to_string());
+            let element_idx = doc.create_element(name.local.as_ref());
             let element = doc.get_element(element_idx);
 
             for attribute in attrs.borrow().iter() {
@@ -33,12 +39,34 @@ fn walk(handle: &rcdom::Handle, target: &mut html::DocumentRef) -> Option<usize>
                 let attr_value = &attribute.value.to_string();
 
                 if attr_name == "style" {
-                    let declarations = parse_inline_css(attr_value);
-                    for declaration in declarations {
-                        element.add_style_property(declaration.name.as_str(), declaration.value);
+                    let mut css_parser = CssParser::new(attr_value.clone());
+                    element.set_inline_style(css_parser.parse_inline());
+
+                    for diagnostic in css_parser.take_diagnostics().iter() {
+                        diagnostics.push(diagnostic.clone().with_element(element_idx));
                     }
-                } else {
-                    element.add_attribute(attr_name, attr_value);
+                } else if !element.add_attribute(attr_name, attr_value) {
+                    diagnostics.warning(format!("attribute's name must not contain spaces (found \"{}\")", attr_name));
+                }
+            }
+
+            // Contents of `<style>` elements are not rendered, they are parsed as stylesheets and
+            // applied to the whole document instead, when their media queries match the page.
+            if name.local.as_ref() == "style" {
+                let mut css_parser = CssParser::new(get_text_content(node));
+                let stylesheet = css_parser.parse_stylesheet();
+                diagnostics.append(&mut css_parser.take_diagnostics());
+
+                let stylesheet = loader.resolve_imports(stylesheet, "", diagnostics).with_media(get_media_list(element));
+                doc.add_stylesheet(stylesheet);
+                return Ok(Some(element_idx));
+            }
+
+            if let (true, Some(href)) = (name.local.as_ref() == "link" && is_stylesheet_link(element), element.get_attribute("href")) {
+                let path = resolve_relative_path("", href.trim());
+                let media = get_media_list(element);
+                if let Some(stylesheet) = loader.load(&path, diagnostics) {
+                    doc.add_stylesheet(stylesheet.with_media(media));
                 }
             }
 
@@ -48,19 +76,57 @@ fn walk(handle: &rcdom::Handle, target: &mut html::DocumentRef) -> Option<usize>
         _ => { }
     }
 
-    if result.is_some() {
-        let result_idx = result.unwrap();
+    if let Some(result_idx) = result {
         for child in node.children.borrow().iter() {
-            if let Some(child_element_idx) = walk(child, target) {
-                target.borrow_mut().add_element(child_element_idx, result_idx);
+            if let Some(child_element_idx) = walk(child, target, loader, diagnostics)? {
+                target.borrow_mut().add_element(child_element_idx, result_idx)?;
             }
         }
     }
 
-    return result;
+    return Ok(result);
 }
 
-pub fn parse_text(text: &str) -> Box<html::DocumentRef> {
+/// Returns whether given `<link>` element references a stylesheet. Alternate stylesheets are not applied.
+fn is_stylesheet_link(element: &html::Element) -> bool {
+    let rel = match element.get_attribute("rel") {
+        Some(rel) => rel.to_ascii_lowercase(),
+        None => return false
+    };
+
+    let mut rel_types = rel.split_whitespace();
+    if !rel_types.clone().any(|rel_type| rel_type == "stylesheet") || rel_types.any(|rel_type| rel_type == "alternate") {
+        return false;
+    }
+
+    return element.get_attribute("href").is_some_and(|href| !href.trim().is_empty());
+}
+
+/// Returns the media queries in the `media` attribute of a `<style>` or `<link>` element, which are empty without it.
+fn get_media_list(element: &html::Element) -> MediaQueryList {
+    return element.get_attribute("media").map(|media| parse_media_list(media)).unwrap_or_default();
+}
+
+/// Concatenates the contents of all direct text children of given node.
+fn get_text_content(node: &rcdom::Handle) -> String {
+    let mut text = String::new();
+
+    for child in node.children.borrow().iter() {
+        if let rcdom::NodeData::Text { ref contents } = child.data {
+            text.push_str(&contents.borrow());
+        }
+    }
+
+    return text;
+}
+
+/// Parses an HTML document. External stylesheets are loaded through `resources_manager`.
+/// Media queries are evaluated against `media_environment`, which is the page the document is rendered on.
+/// Problems that do not prevent parsing are added to `diagnostics`.
+///
+/// Elements' style properties hold the cascaded values, `Document::compute_styles`
+/// has to be called to inherit and compute them.
+pub fn parse_text(text: &str, resources_manager: &ResourcesManager, media_environment: MediaEnvironment, diagnostics: &mut Diagnostics) -> Result<Box<html::DocumentRef>> {
     let mut document = Box::new(html::Document::new());
 
     let opts = ParseOpts {
@@ -75,11 +141,17 @@ pub fn parse_text(text: &str) -> Box<html::DocumentRef> {
     let dom = parse_document(rcdom::RcDom::default(), opts)
         .from_utf8()
         .read_from(&mut text.as_bytes())
-        .unwrap();
+        .map_err(|e| Error::Html(e.to_string()))?;
 
-    walk(&dom.document, &mut document);
+    let mut loader = StylesheetLoader::new(resources_manager);
+    walk(&dom.document, &mut document, &mut loader, diagnostics)?;
 
-    document.borrow_mut().cascade_element_styles(0);
+    document.borrow_mut().apply_stylesheets(&media_environment);
+
+    return Ok(document);
+}
 
-    return document;
+/// Parses raw bytes of an HTML document, detecting their character encoding first.
+pub fn parse_bytes(bytes: &[u8], resources_manager: &ResourcesManager, media_environment: MediaEnvironment, diagnostics: &mut Diagnostics) -> Result<Box<html::DocumentRef>> {
+    return parse_text(&decode_html_bytes(bytes), resources_manager, media_environment, diagnostics);
 }
\ No newline at end of file
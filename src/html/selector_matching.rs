@@ -0,0 +1,336 @@
+use crate::css::{Selector, CompoundSelector, SimpleSelector, Combinator};
+use crate::css::{AttributeSelector, AttributeOperator, PseudoClass, PageSelector, PagePseudoClass};
+use crate::html::Document;
+
+/// A page of the document, which page selectors of `@page` rules are matched against.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageContext {
+
+    /// Name of the page type, which is the value of the `page` property of the content that starts the page.
+    pub name: Option<String>,
+
+    /// Index of the page in the document, starting at 0.
+    pub index: usize,
+
+    /// Whether the page was left empty by a forced break.
+    pub is_blank: bool
+}
+
+impl PageContext {
+
+    /// Returns whether this is a left page. Documents are written from left to right,
+    /// so the first page is a right page and left and right pages alternate from there.
+    /// https://www.w3.org/TR/css-page-3/#spread-pseudos
+    pub fn is_left(&self) -> bool {
+        return !self.index.is_multiple_of(2);
+    }
+}
+
+/// Returns whether given page is matched by a page selector.
+pub fn matches_page_selector(selector: &PageSelector, page: &PageContext) -> bool {
+    if selector.name.is_some() && selector.name != page.name {
+        return false;
+    }
+
+    return selector.pseudo_classes.iter().all(|pseudo_class| match pseudo_class {
+        PagePseudoClass::First => page.index == 0,
+        PagePseudoClass::Left => page.is_left(),
+        PagePseudoClass::Right => !page.is_left(),
+        PagePseudoClass::Blank => page.is_blank
+    });
+}
+
+/// Returns whether element at `element_index` is matched by given selector.
+pub fn matches_selector(document: &Document, element_index: usize, selector: &Selector) -> bool {
+    return matches_from(document, element_index, selector, selector.compounds.len() - 1);
+}
+
+/// Checks whether the element matches the compound selector at `compound_index`
+/// and, recursively, whether the elements related to it match all compound selectors before it.
+fn matches_from(document: &Document, element_index: usize, selector: &Selector, compound_index: usize) -> bool {
+    if !matches_compound(document, element_index, &selector.compounds[compound_index]) {
+        return false;
+    }
+
+    if compound_index == 0 {
+        return true;
+    }
+
+    match selector.combinators[compound_index - 1] {
+        Combinator::Descendant => {
+            let mut ancestor = get_parent(document, element_index);
+            while let Some(ancestor_index) = ancestor {
+                if matches_from(document, ancestor_index, selector, compound_index - 1) {
+                    return true;
+                }
+
+                ancestor = get_parent(document, ancestor_index);
+            }
+
+            return false;
+        }
+
+        Combinator::Child => {
+            return get_parent(document, element_index)
+                .is_some_and(|parent_index| matches_from(document, parent_index, selector, compound_index - 1));
+        }
+
+        Combinator::NextSibling => {
+            let siblings = get_element_siblings(document, element_index);
+            let position = siblings.iter().position(|&idx| idx == element_index).unwrap_or(0);
+            if position == 0 {
+                return false;
+            }
+
+            return matches_from(document, siblings[position - 1], selector, compound_index - 1);
+        }
+
+        Combinator::SubsequentSibling => {
+            let siblings = get_element_siblings(document, element_index);
+            return siblings.iter()
+                .take_while(|&&idx| idx != element_index)
+                .any(|&idx| matches_from(document, idx, selector, compound_index - 1));
+        }
+    }
+}
+
+fn matches_compound(document: &Document, element_index: usize, compound: &CompoundSelector) -> bool {
+    let element = document.get_element_immutable(element_index);
+
+    // Neither text nodes nor the document itself can be matched by selectors.
+    if element.is_text_node() || element_index == document.get_root_index() {
+        return false;
+    }
+
+    return compound.components.iter().all(|component| match component {
+        SimpleSelector::Universal => true,
+        SimpleSelector::Type(name) => element.tag.eq_ignore_ascii_case(name),
+        SimpleSelector::Class(name) => element.has_class(name),
+        SimpleSelector::Id(name) => element.get_attribute("id") == Some(name),
+        SimpleSelector::Attribute(attribute) => matches_attribute(document, element_index, attribute),
+        SimpleSelector::PseudoClass(pseudo_class) => matches_pseudo_class(document, element_index, pseudo_class)
+    });
+}
+
+fn matches_attribute(document: &Document, element_index: usize, selector: &AttributeSelector) -> bool {
+    let value = match document.get_element_immutable(element_index).get_attribute(&selector.name) {
+        Some(value) => value,
+        None => return false
+    };
+
+    let (operator, expected) = match &selector.condition {
+        Some(condition) => condition,
+        None => return true
+    };
+
+    let (value, expected) = if selector.case_insensitive {
+        (value.to_lowercase(), expected.to_lowercase())
+    } else {
+        (value.clone(), expected.clone())
+    };
+
+    match operator {
+        AttributeOperator::Equals => value == expected,
+        AttributeOperator::Includes => {
+            !expected.is_empty() && !expected.contains(char::is_whitespace) && value.split_whitespace().any(|word| word == expected)
+        }
+
+        AttributeOperator::DashMatch => value == expected || value.starts_with(&format!("{}-", expected)),
+        AttributeOperator::Prefix => !expected.is_empty() && value.starts_with(&expected),
+        AttributeOperator::Suffix => !expected.is_empty() && value.ends_with(&expected),
+        AttributeOperator::Substring => !expected.is_empty() && value.contains(&expected)
+    }
+}
+
+fn matches_pseudo_class(document: &Document, element_index: usize, pseudo_class: &PseudoClass) -> bool {
+    let element = document.get_element_immutable(element_index);
+
+    match pseudo_class {
+        PseudoClass::Root => get_parent(document, element_index).is_none(),
+        PseudoClass::Empty => element.children.is_empty(),
+        PseudoClass::FirstChild => matches_nth(document, element_index, 0, 1, false, false),
+        PseudoClass::LastChild => matches_nth(document, element_index, 0, 1, true, false),
+        PseudoClass::OnlyChild => get_element_siblings(document, element_index).len() == 1,
+        PseudoClass::FirstOfType => matches_nth(document, element_index, 0, 1, false, true),
+        PseudoClass::LastOfType => matches_nth(document, element_index, 0, 1, true, true),
+        PseudoClass::OnlyOfType => {
+            matches_nth(document, element_index, 0, 1, false, true) && matches_nth(document, element_index, 0, 1, true, true)
+        }
+
+        PseudoClass::NthChild(a, b) => matches_nth(document, element_index, *a, *b, false, false),
+        PseudoClass::NthLastChild(a, b) => matches_nth(document, element_index, *a, *b, true, false),
+        PseudoClass::NthOfType(a, b) => matches_nth(document, element_index, *a, *b, false, true),
+        PseudoClass::NthLastOfType(a, b) => matches_nth(document, element_index, *a, *b, true, true),
+        PseudoClass::Not(selectors) => !selectors.iter().any(|selector| matches_selector(document, element_index, selector)),
+        PseudoClass::Is(selectors) => selectors.iter().any(|selector| matches_selector(document, element_index, selector))
+    }
+}
+
+/// Checks whether element's position among its siblings is `an+b` for some non-negative `n`.
+/// Positions start at 1 and are counted from the end if `from_end` is set.
+/// If `of_type` is set, only siblings with the same tag name are counted.
+fn matches_nth(document: &Document, element_index: usize, a: i32, b: i32, from_end: bool, of_type: bool) -> bool {
+    let tag = &document.get_element_immutable(element_index).tag;
+    let mut siblings = get_element_siblings(document, element_index);
+
+    if of_type {
+        siblings.retain(|&idx| document.get_element_immutable(idx).tag.eq_ignore_ascii_case(tag));
+    }
+
+    if from_end {
+        siblings.reverse();
+    }
+
+    let position = match siblings.iter().position(|&idx| idx == element_index) {
+        Some(position) => position as i32 + 1,
+        None => return false
+    };
+
+    if a == 0 {
+        return position == b;
+    }
+
+    return (position - b) % a == 0 && (position - b) / a >= 0;
+}
+
+/// Returns the index of element's parent, unless it is the document itself.
+fn get_parent(document: &Document, element_index: usize) -> Option<usize> {
+    let element = document.get_element_immutable(element_index);
+    if !element.has_parent || element.parent == document.get_root_index() {
+        return None;
+    }
+
+    return Some(element.parent);
+}
+
+/// Returns indices of all children of element's parent, including the element itself, that are not text nodes.
+fn get_element_siblings(document: &Document, element_index: usize) -> Vec<usize> {
+    let element = document.get_element_immutable(element_index);
+    if !element.has_parent {
+        return vec![element_index];
+    }
+
+    return document.get_element_immutable(element.parent).children.iter()
+        .cloned()
+        .filter(|&idx| !document.get_element_immutable(idx).is_text_node())
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::{parse_stylesheet, Specificity};
+    use crate::html::test_support::{parse_document, find_element};
+
+    /// Parses a selector list, returning `None` if it is invalid.
+    fn parse_selectors(text: &str) -> Option<Vec<Selector>> {
+        return parse_stylesheet(&format!("{} {{}}", text)).rules.into_iter().next().map(|rule| rule.selectors);
+    }
+
+    fn specificity(text: &str) -> Specificity {
+        return parse_selectors(text).expect("selector should be valid")[0].specificity();
+    }
+
+    fn nth_child(argument: &str) -> Option<(i32, i32)> {
+        let selectors = parse_selectors(&format!(":nth-child({})", argument))?;
+        match selectors[0].compounds[0].components[..] {
+            [SimpleSelector::PseudoClass(PseudoClass::NthChild(a, b))] => Some((a, b)),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn specificity_counts_ids_classes_and_types() {
+        assert_eq!(specificity("*"), Specificity(0, 0, 0));
+        assert_eq!(specificity("li"), Specificity(0, 0, 1));
+        assert_eq!(specificity("ul li"), Specificity(0, 0, 2));
+        assert_eq!(specificity("ul ol + li"), Specificity(0, 0, 3));
+        assert_eq!(specificity("h1 + *[rel=up]"), Specificity(0, 1, 1));
+        assert_eq!(specificity("ul ol li.red"), Specificity(0, 1, 3));
+        assert_eq!(specificity("li.red.level"), Specificity(0, 2, 1));
+        assert_eq!(specificity("#x34y"), Specificity(1, 0, 0));
+        assert_eq!(specificity("p:first-child:nth-child(2n)"), Specificity(0, 2, 1));
+    }
+
+    #[test]
+    fn specificity_of_not_and_is_is_the_most_specific_argument() {
+        assert_eq!(specificity("#s12:not(FOO)"), Specificity(1, 0, 1));
+        assert_eq!(specificity(":not(em, strong#foo)"), Specificity(1, 0, 1));
+        assert_eq!(specificity(":is(em, #foo)"), Specificity(1, 0, 0));
+        assert_eq!(specificity("div:is(.a, p span)"), Specificity(0, 1, 1));
+        assert_eq!(specificity(":not(:is(.a .b, c))"), Specificity(0, 2, 0));
+    }
+
+    #[test]
+    fn nth_child_arguments() {
+        assert_eq!(nth_child("odd"), Some((2, 1)));
+        assert_eq!(nth_child("EVEN"), Some((2, 0)));
+        assert_eq!(nth_child("3"), Some((0, 3)));
+        assert_eq!(nth_child("-3"), Some((0, -3)));
+        assert_eq!(nth_child("n"), Some((1, 0)));
+        assert_eq!(nth_child("-n+3"), Some((-1, 3)));
+        assert_eq!(nth_child("+n"), Some((1, 0)));
+        assert_eq!(nth_child("2n+1"), Some((2, 1)));
+        assert_eq!(nth_child("2n-1"), Some((2, -1)));
+        assert_eq!(nth_child(" 3n + 2 "), Some((3, 2)));
+        assert_eq!(nth_child("-2n - 4"), Some((-2, -4)));
+        assert_eq!(nth_child("10N"), Some((10, 0)));
+
+        assert_eq!(nth_child(""), None);
+        assert_eq!(nth_child("1.5"), None);
+        assert_eq!(nth_child("2n+"), None);
+        assert_eq!(nth_child("2n 1"), None);
+        assert_eq!(nth_child("n+-1"), None);
+        assert_eq!(nth_child("first"), None);
+    }
+
+    #[test]
+    fn matches_nth_child() {
+        let document = parse_document("<ul><li id=a></li><li id=b></li><li id=c></li><li id=d></li><li id=e></li></ul>");
+        let document = document.borrow();
+        let matching = |text: &str| -> Vec<String> {
+            let selectors = parse_selectors(text).expect("selector should be valid");
+            return ["a", "b", "c", "d", "e"].iter()
+                .filter(|id| selectors.iter().any(|selector| matches_selector(&document, find_element(&document, id), selector)))
+                .map(|id| id.to_string())
+                .collect();
+        };
+
+        assert_eq!(matching("li:nth-child(odd)"), vec!["a", "c", "e"]);
+        assert_eq!(matching("li:nth-child(-n+2)"), vec!["a", "b"]);
+        assert_eq!(matching("li:nth-child(3n-1)"), vec!["b", "e"]);
+        assert_eq!(matching("li:nth-last-child(2)"), vec!["d"]);
+        assert_eq!(matching("li:not(:nth-child(n+2))"), vec!["a"]);
+        assert_eq!(matching("li:is(#b, :last-child)"), vec!["b", "e"]);
+    }
+
+    #[test]
+    fn matches_page_pseudo_classes_and_names() {
+        let pages = [
+            PageContext { name: None, index: 0, is_blank: false },
+            PageContext { name: None, index: 1, is_blank: true },
+            PageContext { name: Some(String::from("chapter")), index: 2, is_blank: false },
+            PageContext { name: Some(String::from("chapter")), index: 3, is_blank: false }
+        ];
+
+        let matching = |text: &str| -> Vec<usize> {
+            let stylesheet = parse_stylesheet(&format!("@page {} {{}}", text));
+            let selectors = &stylesheet.page_rules.first().expect("page selector should be valid").selectors;
+            return pages.iter()
+                .filter(|page| selectors.iter().any(|selector| matches_page_selector(selector, page)))
+                .map(|page| page.index)
+                .collect();
+        };
+
+        assert_eq!(matching(""), vec![0, 1, 2, 3]);
+        assert_eq!(matching(":first"), vec![0]);
+        assert_eq!(matching(":left"), vec![1, 3]);
+        assert_eq!(matching(":right"), vec![0, 2]);
+        assert_eq!(matching(":blank"), vec![1]);
+        assert_eq!(matching("chapter"), vec![2, 3]);
+        assert_eq!(matching("chapter:left"), vec![3]);
+        assert_eq!(matching(":first:right"), vec![0]);
+        assert_eq!(matching(":first:left"), Vec::<usize>::new());
+        assert_eq!(matching(":blank, chapter:right"), vec![1, 2]);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::css::{Parser as CssParser, Stylesheet};
+use crate::diagnostics::Diagnostics;
+use crate::generator::{ResourcesManager, resolve_relative_path};
+
+/// Loads external stylesheets and the stylesheets they import.
+pub struct StylesheetLoader<'a> {
+    resources_manager: &'a ResourcesManager,
+
+    /// Paths of the stylesheets being loaded, used to detect `@import` cycles.
+    loading: Vec<String>
+}
+
+impl<'a> StylesheetLoader<'a> {
+
+    /// Loads and parses the stylesheet at given path, including all stylesheets it imports.
+    /// Returns `None` if the stylesheet could not be loaded.
+    pub fn load(&mut self, path: &str, diagnostics: &mut Diagnostics) -> Option<Stylesheet> {
+        if self.loading.iter().any(|loading_path| loading_path == path) {
+            diagnostics.warning(format!("importing stylesheet {} would create a cycle, import ignored", path));
+            return None;
+        }
+
+        let data = match self.resources_manager.load(path) {
+            Some(data) => data,
+            None => {
+                diagnostics.warning(format!("unable to load stylesheet {}", path));
+                return None;
+            }
+        };
+
+        let mut parser = CssParser::new(String::from_utf8_lossy(&data).into_owned());
+        let stylesheet = parser.parse_stylesheet();
+        for diagnostic in parser.take_diagnostics().iter() {
+            diagnostics.push(diagnostic.clone().with_source(path));
+        }
+
+        self.loading.push(path.to_string());
+        let stylesheet = self.resolve_imports(stylesheet, path, diagnostics);
+        self.loading.pop();
+
+        return Some(stylesheet);
+    }
+
+    /// Loads all stylesheets imported by given stylesheet and puts their rules before its own rules.
+    /// `path` is the path of the stylesheet itself, which imported paths are relative to.
+    /// Imported rules keep the media list of their `@import` rule, so they can be matched against any page.
+    pub fn resolve_imports(&mut self, stylesheet: Stylesheet, path: &str, diagnostics: &mut Diagnostics) -> Stylesheet {
+        let mut result = Stylesheet::new();
+
+        for import in &stylesheet.imports {
+            let import_path = resolve_relative_path(path, &import.url);
+            if let Some(imported) = self.load(&import_path, diagnostics).map(|imported| imported.with_media(import.media.clone())) {
+                result.rules.extend(imported.rules);
+                result.page_rules.extend(imported.page_rules);
+            }
+        }
+
+        result.rules.extend(stylesheet.rules);
+        result.page_rules.extend(stylesheet.page_rules);
+        return result;
+    }
+
+    pub fn new(resources_manager: &'a ResourcesManager) -> Self {
+        Self {
+            resources_manager: resources_manager,
+            loading: Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::css::MediaEnvironment;
+    use crate::generator::ResourceResolver;
+    use crate::html::test_support::MEDIA_ENVIRONMENT;
+
+    /// Resolves paths to the stylesheets it was given, without touching the file system.
+    struct MemoryResolver {
+        files: HashMap<String, String>
+    }
+
+    impl ResourceResolver for MemoryResolver {
+        fn resolve(&self, path: &str) -> Option<Vec<u8>> {
+            return self.files.get(path).map(|text| text.as_bytes().to_vec());
+        }
+
+        fn resolve_font(&self, _family: &str) -> Option<Vec<u8>> {
+            return None;
+        }
+    }
+
+    /// Returns a resources manager that loads given files, each given by its path and its contents.
+    fn memory_manager(files: &[(&str, &str)]) -> ResourcesManager {
+        return ResourcesManager::new(Box::new(MemoryResolver {
+            files: files.iter().map(|(path, text)| (path.to_string(), text.to_string())).collect()
+        }));
+    }
+
+    /// Loads the stylesheet at `path` from given files. Returns the width each of its rules sets,
+    /// which identifies the rule, and the messages of all problems found.
+    fn load(files: &[(&str, &str)], path: &str) -> (Vec<f64>, Vec<String>) {
+        let manager = memory_manager(files);
+        let mut diagnostics = Diagnostics::new();
+        let stylesheet = StylesheetLoader::new(&manager).load(path, &mut diagnostics).unwrap_or_default();
+
+        let widths = stylesheet.rules.iter().map(|rule| rule.declarations[0].value.as_dimension_value().value).collect();
+        return (widths, diagnostics.iter().map(|diagnostic| diagnostic.message.clone()).collect());
+    }
+
+    #[test]
+    fn imported_rules_precede_the_importing_ones() {
+        let files = [
+            ("a.css", "@import 'b.css'; @import url(c.css); p { width: 1pt }"),
+            ("b.css", "@import 'd.css'; p { width: 2pt }"),
+            ("c.css", "p { width: 3pt }"),
+            ("d.css", "p { width: 4pt }")
+        ];
+
+        assert_eq!(load(&files, "a.css"), (vec![4.0, 2.0, 3.0, 1.0], Vec::new()));
+    }
+
+    #[test]
+    fn import_cycles_are_reported_once() {
+        let files = [
+            ("a.css", "@import 'b.css'; p { width: 1pt }"),
+            ("b.css", "@import 'a.css'; p { width: 2pt }")
+        ];
+
+        let (widths, messages) = load(&files, "a.css");
+        assert_eq!(widths, vec![2.0, 1.0]);
+        assert_eq!(messages, vec!["importing stylesheet a.css would create a cycle, import ignored"]);
+
+        // A stylesheet imported twice, but not by itself, is not a cycle.
+        let files = [
+            ("a.css", "@import 'b.css'; @import 'b.css'; p { width: 1pt }"),
+            ("b.css", "p { width: 2pt }")
+        ];
+
+        assert_eq!(load(&files, "a.css"), (vec![2.0, 2.0, 1.0], Vec::new()));
+    }
+
+    #[test]
+    fn missing_stylesheets_are_reported() {
+        let (widths, messages) = load(&[("a.css", "@import 'missing.css'; p { width: 1pt }")], "a.css");
+        assert_eq!(widths, vec![1.0]);
+        assert_eq!(messages, vec!["unable to load stylesheet missing.css"]);
+
+        let (widths, messages) = load(&[], "a.css");
+        assert!(widths.is_empty());
+        assert_eq!(messages, vec!["unable to load stylesheet a.css"]);
+    }
+
+    #[test]
+    fn imports_are_relative_to_the_importing_stylesheet() {
+        let files = [
+            ("css/main.css", "@import '../base.css'; @import 'parts/part.css'; p { width: 1pt }"),
+            ("base.css", "p { width: 2pt }"),
+            ("css/parts/part.css", "@import 'more.css'; p { width: 3pt }"),
+            ("css/parts/more.css", "p { width: 4pt }")
+        ];
+
+        assert_eq!(load(&files, "css/main.css"), (vec![2.0, 4.0, 3.0, 1.0], Vec::new()));
+    }
+
+    #[test]
+    fn imported_rules_keep_the_media_of_the_import() {
+        let manager = memory_manager(&[
+            ("a.css", "@import 'b.css' (orientation: landscape); p { width: 1pt }"),
+            ("b.css", "@media print { p { width: 2pt } } p { width: 3pt }")
+        ]);
+
+        let stylesheet = StylesheetLoader::new(&manager).load("a.css", &mut Diagnostics::new()).unwrap();
+        let landscape = MediaEnvironment { width: MEDIA_ENVIRONMENT.height, height: MEDIA_ENVIRONMENT.width };
+
+        let matching_rules = |environment: &MediaEnvironment| {
+            return stylesheet.rules.iter()
+                .filter(|rule| rule.media.iter().all(|media| media.matches(environment)))
+                .map(|rule| rule.declarations[0].value.as_dimension_value().value)
+                .collect::<Vec<f64>>();
+        };
+
+        assert_eq!(matching_rules(&MEDIA_ENVIRONMENT), vec![1.0]);
+        assert_eq!(matching_rules(&landscape), vec![2.0, 3.0, 1.0]);
+    }
+}
@@ -0,0 +1,25 @@
+use std::path::PathBuf;
+use crate::css::MediaEnvironment;
+use crate::diagnostics::Diagnostics;
+use crate::generator::{FileResourceResolver, ResourcesManager};
+use crate::html::{parse_text, Document, DocumentRef};
+
+// Helpers shared by the unit tests of the html module.
+
+/// Media environment of an A4 page, in points.
+pub const MEDIA_ENVIRONMENT: MediaEnvironment = MediaEnvironment { width: 595.0, height: 842.0 };
+
+/// Parses given HTML document, which must not reference external resources.
+pub fn parse_document(html: &str) -> DocumentRef {
+    let manager = ResourcesManager::new(Box::new(FileResourceResolver::new(PathBuf::from("."))));
+    let mut diagnostics = Diagnostics::new();
+    return *parse_text(html, &manager, MEDIA_ENVIRONMENT, &mut diagnostics).expect("test document should parse");
+}
+
+/// Returns the index of the element with given `id` attribute.
+pub fn find_element(document: &Document, id: &str) -> usize {
+    return document.get_elements()
+        .find(|element| element.get_attribute("id").is_some_and(|element_id| element_id == id))
+        .map(|element| element.index)
+        .expect("test document should contain the element");
+}
@@ -0,0 +1,12 @@
+use crate::css::{parse_stylesheet, Stylesheet};
+
+lazy_static! {
+    // Declarations the parser does not support yet are dropped without a warning,
+    // as problems in the default stylesheet are of no interest to users.
+    static ref USER_AGENT_STYLESHEET: Stylesheet = parse_stylesheet(include_str!("user_agent.css"));
+}
+
+/// Returns the stylesheet with default styles of HTML elements.
+pub fn user_agent_stylesheet() -> &'static Stylesheet {
+    return &USER_AGENT_STYLESHEET;
+}
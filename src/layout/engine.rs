@@ -1,14 +1,96 @@
 #![allow(unused)]
 
+use std::collections::HashMap;
 use std::ops::IndexMut;
 use std::rc::Rc;
 use std::cell::RefCell;
 use crate::html;
+use crate::html::{PageContext, Viewport};
+use crate::css::{PrimitiveValue, MarginBoxPosition};
+use crate::layout::{Page, PageGeometry, MarginBox};
+use crate::layout::page::{MarginBoxContent, generate_margin_box_content, place_margin_boxes};
+use crate::layout::running::{RunningAssignment, RunningContent};
+use crate::layout::fragmentation::{self, ForcedBreak, PageBreak};
+use crate::layout::inline::{self, InlineItem};
+use crate::utils::{units, counter};
 use crate::generator::resources_manager::{ResourcesManagerRef, FontResource};
+use crate::generator::ConversionOptions;
 
 pub struct Engine {
     document: html::DocumentRef,
-    resource_manager: ResourcesManagerRef
+    resource_manager: ResourcesManagerRef,
+    options: ConversionOptions,
+
+    /// Area viewport-percentage lengths are relative to, which is the page area of the first page.
+    viewport: Viewport,
+
+    /// Index of the page each element starts on, by element index.
+    element_pages: RefCell<HashMap<usize, usize>>,
+
+    /// Indices of the first and the last page sequence each element is in, by element index.
+    sequence_ranges: RefCell<HashMap<usize, (usize, usize)>>
+}
+
+/// State of assigning elements to page sequences, see `Engine::assign_pages`.
+struct PageAssignment {
+
+    /// Page sequences created so far. Each sequence starts on a new page
+    /// and takes as many pages as its content needs.
+    sequences: Vec<PageSequence>,
+
+    /// Whether anything has been placed in the last sequence yet.
+    has_content: bool,
+
+    /// Break forced by `break-after` of the elements that precede the next one.
+    pending_break: Option<ForcedBreak>,
+
+    /// Displayed elements in document order, each with the index of the sequence it starts in.
+    flow: Vec<(usize, usize)>
+}
+
+/// Content of the document that is laid out on consecutive pages of the same name.
+#[derive(Debug, Clone, Default)]
+struct PageSequence {
+    name: Option<String>,
+
+    /// Break that starts the sequence, if it is forced to start on a left or a right page.
+    forced_break: Option<ForcedBreak>
+}
+
+/// Size of the box that percentages of an element's properties refer to, in points.
+/// A size is `None` when it depends on the content, so percentages of it cannot be resolved.
+#[derive(Debug, Clone, Copy)]
+struct ContainingBlock {
+    width: Option<f64>,
+    height: Option<f64>
+}
+
+/// Widths of the four edges of a box, like its margins, in points.
+/// https://www.w3.org/TR/CSS2/box.html#box-dimensions
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Edges {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64
+}
+
+/// What a layout element is the box of.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElementKind {
+
+    /// Box of an HTML element. An inline element gets a box on every line it is on.
+    Box,
+
+    /// Line box of a block container, whose index is the element's `element`.
+    /// https://www.w3.org/TR/css-inline-3/#line-box
+    Line,
+
+    /// Part of a text node that is on a single line, `baseline` is the distance from the top of the box to its baseline.
+    Text {
+        text: String,
+        baseline: f64
+    }
 }
 
 /// Represents the result of layout calculations for a single HTML element.
@@ -18,6 +100,18 @@ pub struct Element {
     /// Index of the corresponding HTML element.
     pub element: usize,
 
+    /// Whether this is the box of the element, a line box or a part of a text node.
+    pub kind: ElementKind,
+
+    /// Widths of the margins around the element's box. Vertical margins are those left after collapsing
+    /// with the margins of the element's children, margins between siblings collapse too.
+    /// https://www.w3.org/TR/CSS2/box.html#collapsing-margins
+    pub margin: Edges,
+
+    /// Widths of the borders, which are inside the element's box, like the padding.
+    pub border: Edges,
+    pub padding: Edges,
+
     /// Final width of the element, taking into account its children and display properties.
     pub width: f64,
 
@@ -40,85 +134,577 @@ pub struct Element {
     pub children: Vec<Element>,
 }
 
+impl ContainingBlock {
+
+    /// Returns the containing block of the root element, which is the page area of given page.
+    fn page_area(geometry: &PageGeometry) -> Self {
+        ContainingBlock {
+            width: Some(geometry.content_width()),
+            height: Some(geometry.content_height())
+        }
+    }
+}
+
 impl Engine {
-    pub fn process_document(&self) -> Element {
+
+    /// Lays out the document on pages. Every page gets the size and margins
+    /// that the `@page` rules matching it give, see `Document::compute_page_style`.
+    /// Content that does not fit on a page continues on the next one.
+    pub fn process_document(&self) -> Vec<Page> {
         let doc = self.document.borrow();
         let root = doc.get_root_immutable();
-        let mut root_element = self.process_element(root, None);
+
+        let mut assignment = PageAssignment {
+            sequences: vec![PageSequence::default()],
+            has_content: false,
+            pending_break: None,
+            flow: Vec::new()
+        };
+
+        self.assign_pages(root, &None, &mut assignment);
+
+        {
+            let sequence_starts: HashMap<usize, usize> = assignment.flow.iter().copied().collect();
+            self.collect_ranges(root, &sequence_starts, &mut self.sequence_ranges.borrow_mut());
+        }
+
+        let viewport = self.viewport;
+        let mut pages = Vec::<Page>::new();
+        let mut page_styles = Vec::<html::ElementStyleProperties>::new();
+
+        for (sequence_index, sequence) in assignment.sequences.iter().enumerate() {
+            let page_at = |index: usize| {
+                let context = PageContext { name: sequence.name.clone(), index: index, is_blank: false };
+                let page_style = doc.compute_page_style(&context, viewport);
+                let geometry = PageGeometry::from_properties(&page_style, &self.options);
+                return (context, page_style, geometry);
+            };
+
+            // A sequence forced to start on a left or a right page is preceded by a blank page
+            // when the next page is on the other side. The blank page is named like the sequence.
+            // https://www.w3.org/TR/css-page-3/#blank-pages
+            let required_side = match sequence.forced_break {
+                Some(ForcedBreak::Left) => Some(true),
+                Some(ForcedBreak::Right) => Some(false),
+                _ => None
+            };
+
+            let next_context = PageContext { name: sequence.name.clone(), index: pages.len(), is_blank: false };
+            if required_side.is_some_and(|is_left| is_left != next_context.is_left()) {
+                let context = PageContext { is_blank: true, ..next_context };
+                let page_style = doc.compute_page_style(&context, viewport);
+                let geometry = PageGeometry::from_properties(&page_style, &self.options);
+
+                // Nothing starts on the blank page, so the root element is on it without any content.
+                let root_element = self.process_lonely_element(root, None, &ContainingBlock::page_area(&geometry));
+
+                pages.push(Page {
+                    context: context,
+                    geometry: geometry,
+                    root_element: root_element,
+                    margin_boxes: Vec::new()
+                });
+
+                page_styles.push(page_style);
+            }
+
+            // The sequence is laid out once, in the page area of its first page, and then split into pages.
+            let first_page = pages.len();
+            let mut sequence_pages = vec![page_at(first_page)];
+            let content = self.process_page(root, &sequence_pages[0].2, sequence_index);
+            let leaves = fragmentation::collect_leaves(&content);
+            let mut page_breaks = Vec::<PageBreak>::new();
+
+            // Every page takes as much of the content that is left as fits into its page area.
+            loop {
+                let (start, top) = page_breaks.last().map_or((0, 0.0), |page_break| (page_break.leaf, page_break.y));
+                let content_height = sequence_pages[page_breaks.len()].2.content_height();
+
+                match fragmentation::find_page_break(&doc, &leaves, start, top, content_height) {
+                    Some(page_break) => page_breaks.push(page_break),
+                    None => break
+                }
+
+                sequence_pages.push(page_at(first_page + sequence_pages.len()));
+            }
+
+            let page_roots = fragmentation::split_into_pages(&content, &page_breaks);
+            for ((context, page_style, geometry), root_element) in sequence_pages.into_iter().zip(page_roots) {
+                self.record_element_pages(&root_element, context.index);
+
+                pages.push(Page {
+                    context: context,
+                    geometry: geometry,
+                    root_element: root_element,
+                    margin_boxes: Vec::new()
+                });
+
+                page_styles.push(page_style);
+            }
+
+            // Elements without boxes, like running elements, are on the page of the content before them.
+            let mut element_pages = self.element_pages.borrow_mut();
+            let mut page_index = first_page;
+
+            for (element_index, _) in assignment.flow.iter().filter(|(_, element_sequence)| *element_sequence == sequence_index) {
+                page_index = *element_pages.entry(*element_index).or_insert(page_index);
+            }
+        }
+
+        let running = self.assign_running_content(&assignment.flow);
+
+        // Margin boxes can only be generated once the number of pages is known.
+        let page_count = pages.len();
+        for (page, page_style) in pages.iter_mut().zip(page_styles.iter()) {
+            page.margin_boxes = self.process_margin_boxes(page, page_style, page_count, &running, viewport);
+        }
+
+        return pages;
+    }
+
+    /// Lays out the part of the document that is in the page sequence at `sequence_index`, in the page area of given page.
+    fn process_page(&self, root: &html::Element, geometry: &PageGeometry, sequence_index: usize) -> Element {
+        let mut root_element = self.process_element(root, None, &ContainingBlock::page_area(geometry), sequence_index);
         root_element.children = self.adjust_children_position(&root_element, root_element.children.clone());
         return root_element;
     }
 
-    /// Calculates layout properties of a generic element.
-    fn process_element(&self, element: &html::Element, parent: Option<&Element>) -> Element {
-        if element.children.len() == 0 {
-            return self.process_lonely_element(element, parent);
+    /// Records that elements in given part of a laid out page start on that page, at `page_index`,
+    /// unless they start on an earlier one.
+    fn record_element_pages(&self, element: &Element, page_index: usize) {
+        if element.kind != ElementKind::Line {
+            self.element_pages.borrow_mut().entry(element.element).or_insert(page_index);
         }
 
-        return self.process_crowded_element(element, parent);
+        for child in &element.children {
+            self.record_element_pages(child, page_index);
+        }
     }
 
-    /// Calculates layout properties of an element with no children.
-    fn process_lonely_element(&self, element: &html::Element, parent: Option<&Element>) -> Element {
-        let mut elem = Element::default(element.index);
+    /// Collects the named strings and running elements that elements of given flow assign,
+    /// once it is known on which pages the elements are.
+    fn assign_running_content(&self, flow: &[(usize, usize)]) -> RunningContent {
+        let doc = self.document.borrow();
+        let element_pages = self.element_pages.borrow();
+        let mut running = RunningContent::default();
+
+        // Page of the last line, or empty box, found so far.
+        let mut content_page = None;
+
+        for (element_index, _) in flow {
+            let element = doc.get_element_immutable(*element_index);
+            let page_index = element_pages[element_index];
+            let is_at_page_start = content_page != Some(page_index);
+
+            if let Some(name) = running_element_name(element) {
+                running.elements.push(RunningAssignment {
+                    name: name,
+                    page_index: page_index,
+                    is_at_page_start: is_at_page_start,
+                    value: element.index
+                });
+
+                continue;
+            }
+
+            for (name, value) in self.evaluate_string_set(element, page_index) {
+                running.strings.push(RunningAssignment {
+                    name: name,
+                    page_index: page_index,
+                    is_at_page_start: is_at_page_start,
+                    value: value
+                });
+            }
+
+            if element.children.is_empty() {
+                content_page = Some(page_index);
+            }
+        }
+
+        return running;
+    }
+
+    /// Lays out the margin boxes of given page, whose page context has computed values `page_style`.
+    /// A margin box is only generated if its `content` is not `none`.
+    fn process_margin_boxes(&self, page: &Page, page_style: &html::ElementStyleProperties, page_count: usize, running: &RunningContent, viewport: Viewport) -> Vec<MarginBox> {
+        let doc = self.document.borrow();
+        let resource_man = self.resource_manager.borrow();
+        let mut margin_boxes = Vec::<MarginBox>::new();
+
+        for position in MarginBoxPosition::ALL.iter() {
+            let style = doc.compute_margin_box_style(&page.context, *position, page_style, viewport);
+            let content = match generate_margin_box_content(style.get("content"), page.context.index, page_count, running) {
+                Some(content) => content,
+                None => continue
+            };
+
+            // Margin boxes are sized to their content first, `place_margin_boxes` then gives them their final size.
+            let (mut width, mut height) = (0.0, 0.0);
+            let (mut text, mut element) = (String::new(), None);
+
+            match content {
+                MarginBoxContent::Text(content_text) => {
+                    if let Some(font) = resource_man.get_font(&resource_man.select_font(&style.get_font_families(), &self.options.default_font)) {
+                        let text_box = font.font.get_text_bounding_box(&content_text, style.get_font_size(), true);
+                        width = text_box.width;
+                        height = text_box.height;
+                    }
+
+                    text = content_text;
+                }
+
+                // A running element is laid out as it would be in the page sequence it was taken from.
+                MarginBoxContent::Element(element_index) => {
+                    let source_sequence = self.sequence_ranges.borrow()[&element_index].0;
+                    let page_area = ContainingBlock::page_area(&page.geometry);
+                    let mut running_element = self.process_element(doc.get_element_immutable(element_index), None, &page_area, source_sequence);
+                    running_element.children = self.adjust_children_position(&running_element, running_element.children.clone());
+
+                    width = running_element.width;
+                    height = running_element.height;
+                    element = Some(running_element);
+                }
+            }
+
+            margin_boxes.push(MarginBox {
+                position: *position,
+                x: 0.0,
+                y: 0.0,
+                width: width,
+                height: height,
+                text: text,
+                element: element,
+                text_x: 0.0,
+                text_y: 0.0,
+                style: style
+            });
+        }
+
+        place_margin_boxes(&page.geometry, &mut margin_boxes);
+
+        // Running elements are aligned like text.
+        for margin_box in margin_boxes.iter_mut() {
+            if let Some(element) = &mut margin_box.element {
+                element.x = margin_box.text_x;
+                element.y = margin_box.text_y;
+                element.children = self.adjust_children_position(element, element.children.clone());
+            }
+        }
+
+        return margin_boxes;
+    }
+
+    /// Decides which page sequences given element and its descendants are in. A new sequence starts wherever
+    /// the used value of the `page` property changes between neighbouring content, and it is named
+    /// after the content that starts it. `auto` means the same page as the parent's.
+    /// https://www.w3.org/TR/css-page-3/#using-named-pages
+    ///
+    /// Breaks forced by `break-before` and `break-after` start a new sequence as well.
+    /// Like breaks between differently named pages, they are ignored before the first content.
+    /// https://www.w3.org/TR/css-break-3/#forced-breaks
+    fn assign_pages(&self, element: &html::Element, parent_page: &Option<String>, assignment: &mut PageAssignment) {
+        let doc = self.document.borrow();
+
+        // A running element is taken out of flow, so it does not break pages and its descendants are not part of it.
+        if running_element_name(element).is_some() {
+            assignment.flow.push((element.index, assignment.sequences.len() - 1));
+            return;
+        }
+
+        let page = match element.get_style_property("page") {
+            Some(value) if value.is_identifier() && !value.is_keyword("auto") => Some(value.as_string().clone()),
+            _ => parent_page.clone()
+        };
+
+        let forced_break = ForcedBreak::combine(assignment.pending_break.take(), fragmentation::get_forced_break(element, "break-before"));
+
+        let is_first = assignment.sequences.len() == 1;
+        match assignment.sequences.last_mut() {
+            Some(sequence) if !assignment.has_content => {
+                sequence.name = page.clone();
+
+                // Breaks at the start of a sequence other than the first combine with the one that started it.
+                if !is_first {
+                    sequence.forced_break = ForcedBreak::combine(sequence.forced_break, forced_break);
+                }
+            }
+
+            Some(sequence) if forced_break.is_none() && sequence.name == page => {}
+
+            _ => {
+                assignment.sequences.push(PageSequence {
+                    name: page.clone(),
+                    forced_break: forced_break
+                });
+
+                assignment.has_content = false;
+            }
+        }
+
+        assignment.flow.push((element.index, assignment.sequences.len() - 1));
+
+        for child_index in &element.children {
+            let child = doc.get_element_immutable(*child_index);
+            if child.is_displayed() {
+                self.assign_pages(child, &page, assignment);
+            }
+        }
+
+        if element.children.is_empty() {
+            assignment.has_content = true;
+        }
+
+        assignment.pending_break = ForcedBreak::combine(assignment.pending_break, fragmentation::get_forced_break(element, "break-after"));
+    }
+
+    /// Collects the ranges of page sequences, or pages, given element and its descendants are in into `ranges`,
+    /// given the one each of them starts in. Returns the range, or `None` if the element does not start anywhere.
+    fn collect_ranges(&self, element: &html::Element, starts: &HashMap<usize, usize>, ranges: &mut HashMap<usize, (usize, usize)>) -> Option<(usize, usize)> {
+        let doc = self.document.borrow();
+        let first = *starts.get(&element.index)?;
+
+        // A running element and its descendants stay where the element would have been.
+        if running_element_name(element).is_some() {
+            self.set_range(element, first, ranges);
+            return Some((first, first));
+        }
+
+        let mut last = first;
+        for child_index in &element.children {
+            let child = doc.get_element_immutable(*child_index);
+            if let Some((_, child_last)) = self.collect_ranges(child, starts, ranges) {
+                last = last.max(child_last);
+            }
+        }
+
+        ranges.insert(element.index, (first, last));
+        return Some((first, last));
+    }
+
+    /// Puts given element and all its descendants into the page sequence, or page, at `index` only.
+    fn set_range(&self, element: &html::Element, index: usize, ranges: &mut HashMap<usize, (usize, usize)>) {
+        let doc = self.document.borrow();
+        ranges.insert(element.index, (index, index));
+
+        for child_index in &element.children {
+            self.set_range(doc.get_element_immutable(*child_index), index, ranges);
+        }
+    }
+
+    /// Returns names and values of the named strings that given element on page at `page_index` sets.
+    /// There is no generated content, so `content(before)` and `content(after)` are empty.
+    /// https://www.w3.org/TR/css-gcpm-3/#setting-named-strings-the-string-set-pro
+    fn evaluate_string_set(&self, element: &html::Element, page_index: usize) -> Vec<(String, String)> {
+        let doc = self.document.borrow();
+        let items = match element.get_style_property("string-set") {
+            Some(value) if value.is_list() => value.as_items(),
+            _ => return Vec::new()
+        };
+
+        let text_content = doc.get_text_content(element.index);
+        let mut strings = Vec::<(String, String)>::new();
+
+        // Each name is followed by the items of its value.
+        for item in items {
+            if item.is_identifier() {
+                strings.push((item.as_string().clone(), String::new()));
+                continue;
+            }
+
+            let value = match strings.last_mut() {
+                Some((_, value)) => value,
+                None => continue
+            };
+
+            if item.is_string() {
+                value.push_str(item.as_string());
+                continue;
+            }
+
+            let arguments = item.as_items();
+            match item.as_string().as_str() {
+                "counter" | "counters" => {
+                    let counter_value = if arguments[0].as_string() == "page" { page_index as i64 + 1 } else { 0 };
+                    let style = arguments.iter().skip(1).find(|argument| argument.is_identifier());
+                    value.push_str(&counter::format_counter(counter_value, style.map_or("decimal", |style| style.as_string())));
+                }
+
+                "attr" => {
+                    value.push_str(element.get_attribute(arguments[0].as_string()).map_or("", |attribute| attribute.as_str()));
+                }
+
+                "content" => {
+                    match arguments.first().map(|argument| argument.as_string().to_ascii_lowercase()).as_deref() {
+                        None | Some("text") => value.push_str(&text_content),
+                        Some("first-letter") => value.extend(text_content.chars().next()),
+                        _ => {}
+                    }
+                }
+
+                _ => {}
+            }
+        }
+
+        return strings;
+    }
+
+    /// Returns whether given element is laid out in the page sequence at `sequence_index`, at least partially.
+    /// Running elements are not, they are taken out of the flow and laid out in margin boxes instead.
+    fn is_in_sequence(&self, element: &html::Element, sequence_index: usize) -> bool {
+        return element.is_displayed() && running_element_name(element).is_none() && self.sequence_ranges.borrow()
+            .get(&element.index)
+            .is_some_and(|(first_sequence, last_sequence)| *first_sequence <= sequence_index && sequence_index <= *last_sequence);
+    }
 
+    /// Returns whether given element is laid out in lines, which it is if it is a text node,
+    /// or an inline element with only inline-level content.
+    fn is_inline_level(&self, element: &html::Element) -> bool {
         if element.is_text_node() {
-            let line_height = 0.0;
-            let resource_man = self.resource_manager.borrow();
-            if let Some(font) = resource_man.get_font(&self.get_font_name(&element)) {
-                let text_box = font.font.get_text_bounding_box(&element.text, 12.0, true);
-                elem.width = text_box.width;
-                elem.height = text_box.height + line_height;
+            return true;
+        }
+
+        if !element.get_style_property("display").is_none_or(|display| display.is_keyword("inline")) {
+            return false;
+        }
+
+        let doc = self.document.borrow();
+        return element.children.iter()
+            .map(|child_index| doc.get_element_immutable(*child_index))
+            .filter(|child| child.is_displayed())
+            .all(|child| self.is_inline_level(child));
+    }
+
+    /// Collects the words and line breaks of given inline-level element in the page sequence at `sequence_index` into `items`.
+    /// `ancestors` are the inline elements the element is in.
+    fn collect_inline_items(&self, element: &html::Element, ancestors: &mut Vec<usize>, sequence_index: usize, items: &mut Vec<InlineItem>) {
+        if element.is_text_node() {
+            for word in element.text.split_whitespace() {
+                items.push(InlineItem::Word {
+                    text_node: element.index,
+                    ancestors: ancestors.clone(),
+                    text: word.to_string()
+                });
             }
+
+            return;
+        }
+
+        if element.tag == "br" {
+            items.push(InlineItem::LineBreak);
+            return;
+        }
+
+        let doc = self.document.borrow();
+        ancestors.push(element.index);
+
+        for child_index in &element.children {
+            let child = doc.get_element_immutable(*child_index);
+            if self.is_in_sequence(child, sequence_index) {
+                self.collect_inline_items(child, ancestors, sequence_index, items);
+            }
+        }
+
+        ancestors.pop();
+    }
+
+    /// Calculates layout properties of the part of a generic element that is in the page sequence at `sequence_index`.
+    fn process_element(&self, element: &html::Element, parent: Option<&Element>, containing_block: &ContainingBlock, sequence_index: usize) -> Element {
+        if element.children.is_empty() {
+            return self.process_lonely_element(element, parent, containing_block);
         }
 
-        self.clamp_element_size(&element, &mut elem);
-        self.adjust_element_position(&element, &mut elem);
+        return self.process_crowded_element(element, parent, containing_block, sequence_index);
+    }
+
+    /// Calculates layout properties of an element with no children.
+    fn process_lonely_element(&self, element: &html::Element, parent: Option<&Element>, containing_block: &ContainingBlock) -> Element {
+        let mut elem = Element::default(element.index);
+        let content_width = self.resolve_box_edges(element, &mut elem, containing_block);
+
+        self.set_box_size(element, &mut elem, content_width.unwrap_or(0.0), 0.0, containing_block);
+        self.adjust_element_position(element, &mut elem, containing_block);
 
         return elem;
     }
 
     /// Calculates layout properties of an element that has some children.
-    fn process_crowded_element(&self, element: &html::Element, parent: Option<&Element>) -> Element {
+    /// Only the children that are in the page sequence at `sequence_index` are laid out.
+    fn process_crowded_element(&self, element: &html::Element, parent: Option<&Element>, containing_block: &ContainingBlock, sequence_index: usize) -> Element {
         let doc = self.document.borrow();
         let mut elem = Element::default(element.index);
         let mut children = Vec::<Element>::new();
 
-        for element_idx in &element.children {
-            let element = doc.get_element_immutable(*element_idx);
-            let mut child = self.process_element(element, Some(&elem));
-            children.push(child);
-        }
+        // Children are laid out in the element's content box. Percentages of an element's height
+        // are ignored when it has no definite height.
+        let content_width = self.resolve_box_edges(element, &mut elem, containing_block);
+        let children_containing_block = ContainingBlock {
+            width: content_width,
+            height: resolve_length(element.get_style_property("height"), containing_block.height)
+        };
 
-        let mut children_clone = children.clone();
-        let children_num = children_clone.len();
+        // Consecutive inline-level children are laid out in lines, which are stacked together with the block-level children.
+        // https://www.w3.org/TR/CSS2/visuren.html#anonymous-block-level
+        let mut inline_items = Vec::<InlineItem>::new();
 
-        // Calculate positions of element's children.
-        // todo: Change this to avoid cloning children on every loop!!
-        for idx in 1..children_num {
-            let mut child = children.index_mut(idx);
-            let previous_child = &children_clone[idx - 1];
+        for child_index in &element.children {
+            let child = doc.get_element_immutable(*child_index);
+            if !self.is_in_sequence(child, sequence_index) {
+                continue;
+            }
+
+            if self.is_inline_level(child) {
+                self.collect_inline_items(child, &mut Vec::new(), sequence_index, &mut inline_items);
+                continue;
+            }
 
-            let y = previous_child.local_y + previous_child.height;
-            child.local_y += y;
-            children_clone = children.clone();
+            children.extend(self.process_lines(element, &std::mem::take(&mut inline_items), &children_containing_block));
+            children.push(self.process_element(child, Some(&elem), &children_containing_block, sequence_index));
         }
 
-        // Calculate width and height of the element itself.
-        let mut width: f64 = 0.0;
-        let mut height: f64 = 0.0;
+        children.extend(self.process_lines(element, &inline_items, &children_containing_block));
 
-        for child in &children {
-            width = width.max(child.local_x + child.width);
-            height = height.max(child.local_y + child.height);
+        // Children are stacked in the content box, and the margins between them collapse. The margins of the first
+        // and the last child collapse with the element's own when no border or padding separates them.
+        // Margins of the root element do not collapse.
+        let content_left = elem.border.left + elem.padding.left;
+        let content_top = elem.border.top + elem.padding.top;
+        let collapses_top = parent.is_some() && content_top == 0.0;
+        let collapses_bottom = parent.is_some() && elem.border.bottom + elem.padding.bottom == 0.0
+            && element.get_style_property("height").is_none() && element.get_style_property("min-height").is_none();
+
+        let mut content_height = 0.0;
+        let mut previous_margin: Option<f64> = None;
+
+        for child in children.iter_mut() {
+            let gap = match previous_margin {
+                Some(margin) => collapse_margins(margin, child.margin.top),
+                None if collapses_top => {
+                    elem.margin.top = collapse_margins(elem.margin.top, child.margin.top);
+                    0.0
+                }
+                None => child.margin.top
+            };
+
+            child.local_x += content_left + child.margin.left;
+            child.local_y += content_top + content_height + gap;
+            content_height += gap + child.height;
+            previous_margin = Some(child.margin.bottom);
+        }
+
+        match previous_margin {
+            Some(margin) if collapses_bottom => elem.margin.bottom = collapse_margins(elem.margin.bottom, margin),
+            Some(margin) => content_height += margin,
+            None => {}
         }
 
-        elem.width = width;
-        elem.height = height;
+        let content_width = content_width.unwrap_or_else(|| {
+            children.iter().map(|child| child.local_x - content_left + child.width + child.margin.right).fold(0.0, f64::max)
+        });
+
         elem.children = children;
 
-        self.clamp_element_size(&element, &mut elem);
-        self.adjust_element_position(&element, &mut elem);
+        self.set_box_size(element, &mut elem, content_width, content_height, containing_block);
+        self.adjust_element_position(element, &mut elem, containing_block);
 
         if let Some(parent_element) = parent {
             elem.x += parent_element.x;
@@ -131,40 +717,114 @@ impl Engine {
         return elem;
     }
 
-    /// Clamps given layout element's width and height to be within the range
-    /// specified by HTML element's [min-width, max-width] and [min-height, max-height]
-    /// style properties.
-    fn clamp_element_size(&self, html_element: &html::Element, element: &mut Element) {
-        fn get_and_parse_or(html_element: &html::Element, name: &str, default: f64) -> f64 {
-            if let Some(prop_value) = html_element.get_style_property(name) {
-                return prop_value.as_dimension_value().value;
+    /// Lays out inline content of given block container in lines as wide as the container's containing block for its children.
+    fn process_lines(&self, element: &html::Element, items: &[InlineItem], containing_block: &ContainingBlock) -> Vec<Element> {
+        if items.is_empty() {
+            return Vec::new();
+        }
+
+        let doc = self.document.borrow();
+        let resource_man = self.resource_manager.borrow();
+        return inline::layout_lines(&doc, &resource_man, &self.options.default_font, element, items, containing_block.width);
+    }
+
+    /// Resolves the margins, borders and padding of given element, and returns the width of its content box,
+    /// which is `None` if the width of the containing block is not known. An element without a width fills
+    /// its containing block, auto margins take the space that is left.
+    /// https://www.w3.org/TR/CSS2/visudet.html#blockwidth
+    fn resolve_box_edges(&self, html_element: &html::Element, element: &mut Element, containing_block: &ContainingBlock) -> Option<f64> {
+
+        // Percentages of all margins and paddings refer to the width of the containing block.
+        let resolve = |name: &str| resolve_length(html_element.get_style_property(name), containing_block.width).unwrap_or(0.0);
+
+        element.margin = Edges {
+            top: resolve("margin-top"),
+            right: resolve("margin-right"),
+            bottom: resolve("margin-bottom"),
+            left: resolve("margin-left")
+        };
+
+        element.border = Edges {
+            top: border_width(html_element, "top"),
+            right: border_width(html_element, "right"),
+            bottom: border_width(html_element, "bottom"),
+            left: border_width(html_element, "left")
+        };
+
+        element.padding = Edges {
+            top: resolve("padding-top"),
+            right: resolve("padding-right"),
+            bottom: resolve("padding-bottom"),
+            left: resolve("padding-left")
+        };
+
+        let available_width = containing_block.width?;
+        let edges_width = element.border.left + element.border.right + element.padding.left + element.padding.right;
+        let margins_width = element.margin.left + element.margin.right;
+
+        let min_width = resolve_length(html_element.get_style_property("min-width"), containing_block.width).unwrap_or(0.0);
+        let max_width = resolve_length(html_element.get_style_property("max-width"), containing_block.width).unwrap_or(f64::INFINITY);
+        let content_width = resolve_length(html_element.get_style_property("width"), containing_block.width)
+            .unwrap_or(available_width - margins_width - edges_width);
+
+        let content_width = clamp(content_width, min_width, max_width).max(0.0);
+        let space_left = (available_width - margins_width - edges_width - content_width).max(0.0);
+        let is_auto = |name: &str| html_element.get_style_property(name).is_some_and(|value| value.is_keyword("auto"));
+
+        match (is_auto("margin-left"), is_auto("margin-right")) {
+            (true, true) => {
+                element.margin.left = space_left / 2.0;
+                element.margin.right = space_left / 2.0;
             }
-            
-            return default;
+
+            (true, false) => element.margin.left = space_left,
+            (false, true) => element.margin.right = space_left,
+            (false, false) => {}
         }
 
+        return Some(content_width);
+    }
+
+    /// Sets the size of given element's box to fit content of given size, along with the element's padding and borders.
+    /// The size of the content is clamped by the element's sizing properties first.
+    fn set_box_size(&self, html_element: &html::Element, element: &mut Element, content_width: f64, content_height: f64, containing_block: &ContainingBlock) {
+        element.width = content_width;
+        element.height = content_height;
+        self.clamp_element_size(html_element, element, containing_block);
+
+        element.width += element.border.left + element.padding.left + element.padding.right + element.border.right;
+        element.height += element.border.top + element.padding.top + element.padding.bottom + element.border.bottom;
+    }
+
+    /// Clamps given layout element's width and height to be within the range
+    /// specified by HTML element's [min-width, max-width] and [min-height, max-height]
+    /// style properties. Percentages refer to the size of the containing block.
+    fn clamp_element_size(&self, html_element: &html::Element, element: &mut Element, containing_block: &ContainingBlock) {
+        let get_and_parse_or = |html_element: &html::Element, name: &str, default: f64| -> f64 {
+            let reference = if name.ends_with("width") { containing_block.width } else { containing_block.height };
+            return resolve_length(html_element.get_style_property(name), reference).unwrap_or(default);
+        };
+
         // width and height effectively work like min-width and min-height in this context,
         // so first try them, and if they are not set, then try min-width and min-height.
 
         let min_width = get_and_parse_or(html_element, "width", get_and_parse_or(html_element, "min-width", 0.0));
-        let max_width = get_and_parse_or(html_element, "max-width", std::f64::INFINITY);
+        let max_width = get_and_parse_or(html_element, "max-width", f64::INFINITY);
         
         let min_height = get_and_parse_or(html_element, "height", get_and_parse_or(html_element, "min-height", 0.0));
-        let max_height = get_and_parse_or(html_element, "max-height", std::f64::INFINITY);
+        let max_height = get_and_parse_or(html_element, "max-height", f64::INFINITY);
         
         element.width = clamp(element.width, min_width, max_width);
         element.height = clamp(element.height, min_height, max_height);
     }
 
     /// Moves the element according to its `top` and `left` style properties.
-    fn adjust_element_position(&self, html_element: &html::Element, element: &mut Element) {
-        if let Some(left_prop) = html_element.get_style_property("left") {
-            let left: f64 = left_prop.as_dimension_value().value;
+    fn adjust_element_position(&self, html_element: &html::Element, element: &mut Element, containing_block: &ContainingBlock) {
+        if let Some(left) = resolve_length(html_element.get_style_property("left"), containing_block.width) {
             element.local_x += left;
         }
 
-        if let Some(top_prop) = html_element.get_style_property("top") {
-            let top: f64 = top_prop.as_dimension_value().value;
+        if let Some(top) = resolve_length(html_element.get_style_property("top"), containing_block.height) {
             element.local_y += top;
         }
     }
@@ -172,29 +832,25 @@ impl Engine {
     /// Calculates final position of given elements. Uses `parent` as the origin.
     /// Calls this method for all elements in the tree recursively.
     fn adjust_children_position(&self, parent: &Element, children: Vec<Element>) -> Vec<Element> {
-        let mut result = children.clone();
+        let mut result = children;
 
-        for idx in 0..children.len() {
-            result[idx].x = parent.x + result[idx].local_x;
-            result[idx].y = parent.y + result[idx].local_y;
-            result[idx].children = self.adjust_children_position(&result[idx], result[idx].children.clone());
+        for child in result.iter_mut() {
+            child.x = parent.x + child.local_x;
+            child.y = parent.y + child.local_y;
+            child.children = self.adjust_children_position(child, child.children.clone());
         }
 
         return result;
     }
 
-    fn get_font_name(&self, element: &html::Element) -> String {
-        if let Some(font_prop) = element.get_style_property("font") {
-            return font_prop.as_string().clone();
-        }
-
-        return String::from("Arial");
-    }
-
-    pub fn new(document: html::DocumentRef, resource_manager: ResourcesManagerRef) -> Engine {
+    pub fn new(document: html::DocumentRef, resource_manager: ResourcesManagerRef, options: ConversionOptions, viewport: Viewport) -> Engine {
         Engine {
             document: document,
-            resource_manager: resource_manager
+            resource_manager: resource_manager,
+            options: options,
+            viewport: viewport,
+            element_pages: RefCell::new(HashMap::new()),
+            sequence_ranges: RefCell::new(HashMap::new())
         }
     }
 }
@@ -203,6 +859,10 @@ impl Element {
     pub fn default(element_index: usize) -> Element {
         Element {
             element: element_index,
+            kind: ElementKind::Box,
+            margin: Edges::default(),
+            border: Edges::default(),
+            padding: Edges::default(),
             width: 0.0,
             height: 0.0,
             x: 0.0,
@@ -214,6 +874,52 @@ impl Element {
     }
 }
 
+/// Returns the name of the running element given element is made into by `position: running(name)`.
+/// https://www.w3.org/TR/css-gcpm-3/#running-elements
+fn running_element_name(element: &html::Element) -> Option<String> {
+    match element.get_style_property("position") {
+        Some(position) if position.is_function() && position.as_string() == "running" => Some(position.as_items()[0].as_string().clone()),
+        _ => None
+    }
+}
+
+/// Returns the width of the border on given side of an element, which is zero if the border has no style.
+fn border_width(element: &html::Element, side: &str) -> f64 {
+    let style = element.get_style_property(&format!("border-{}-style", side));
+    if style.is_none_or(|style| style.is_keyword("none") || style.is_keyword("hidden")) {
+        return 0.0;
+    }
+
+    return resolve_length(element.get_style_property(&format!("border-{}-width", side)), None).unwrap_or(html::MEDIUM_BORDER_WIDTH);
+}
+
+/// Returns the width of the margin that two adjoining margins collapse into.
+/// https://www.w3.org/TR/CSS2/box.html#collapsing-margins
+fn collapse_margins(first: f64, second: f64) -> f64 {
+    return first.max(second).max(0.0) + first.min(second).min(0.0);
+}
+
+/// Returns a length in points, resolving percentages against `reference`.
+/// Returns `None` for values that are not lengths, like `auto`, and for percentages of an unknown size.
+pub fn resolve_length(value: Option<&PrimitiveValue>, reference: Option<f64>) -> Option<f64> {
+    let value = value?;
+
+    if value.is_dimension_value() {
+        return Some(value.as_dimension_value().value);
+    }
+
+    if value.is_percentage() {
+        return reference.map(|reference| reference * value.as_number() / 100.0);
+    }
+
+    // Math functions have their lengths in points already, only percentages are left to resolve.
+    if let Some(expression) = value.as_math() {
+        return expression.evaluate(reference);
+    }
+
+    return None;
+}
+
 /// Clamps given value to given range.
 fn clamp(val: f64, min: f64, max: f64) -> f64 {
     if val < min {
@@ -225,4 +931,258 @@ fn clamp(val: f64, min: f64, max: f64) -> f64 {
     }
 
     return val;
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Converter;
+
+    const WORDS: &str = "one two three four five six seven eight nine ten eleven twelve thirteen fourteen fifteen \
+        sixteen seventeen eighteen nineteen twenty twenty-one twenty-two twenty-three twenty-four twenty-five";
+
+    fn layout(html: &str) -> Vec<Page> {
+        return Converter::new().html(html).layout().expect("test document should lay out").pages;
+    }
+
+    /// Collects the line boxes in given element, in order.
+    fn collect_lines<'a>(element: &'a Element, lines: &mut Vec<&'a Element>) {
+        if element.kind == ElementKind::Line {
+            lines.push(element);
+            return;
+        }
+
+        for child in &element.children {
+            collect_lines(child, lines);
+        }
+    }
+
+    /// Returns the text on given line, with the parts of different text nodes separated by a space.
+    fn line_text(line: &Element) -> String {
+        fn collect_text(element: &Element, parts: &mut Vec<String>) {
+            if let ElementKind::Text { text, .. } = &element.kind {
+                parts.push(text.clone());
+            }
+
+            for child in &element.children {
+                collect_text(child, parts);
+            }
+        }
+
+        let mut parts = Vec::<String>::new();
+        collect_text(line, &mut parts);
+        return parts.join(" ");
+    }
+
+    fn page_lines(page: &Page) -> Vec<&Element> {
+        let mut lines = Vec::<&Element>::new();
+        collect_lines(&page.root_element, &mut lines);
+        return lines;
+    }
+
+    #[test]
+    fn text_wraps_at_the_width_of_the_containing_block() {
+        let pages = layout(&format!("<style>@page {{ size: 200pt 1000pt; margin: 0 }} body, p {{ margin: 0 }}</style><p>{}</p>", WORDS));
+        let lines = page_lines(&pages[0]);
+
+        assert!(lines.len() > 1);
+        for line in &lines {
+            assert_eq!(line.width, 200.0);
+            assert!(line.children.iter().all(|text| text.x + text.width <= 200.01));
+        }
+
+        for pair in lines.windows(2) {
+            assert_eq!(pair[1].y, pair[0].y + pair[0].height);
+        }
+
+        let text: Vec<String> = lines.iter().map(|line| line_text(line)).collect();
+        assert_eq!(text.join(" "), WORDS);
+    }
+
+    #[test]
+    fn inline_elements_are_on_the_lines_of_their_block() {
+        let pages = layout("<p>Short <b>bold <i>text</i></b> and <span>more</span><br>after</p>");
+        let lines = page_lines(&pages[0]);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(line_text(lines[0]), "Short bold text and more");
+        assert_eq!(line_text(lines[1]), "after");
+
+        let first_line = &lines[0].children;
+        assert_eq!(first_line.len(), 4);
+        assert_eq!(first_line[1].kind, ElementKind::Box);
+        assert_eq!(first_line[1].children.len(), 2);
+        assert!(first_line[1].x > first_line[0].x + first_line[0].width);
+        assert!(first_line[2].x > first_line[1].x + first_line[1].width);
+    }
+
+    #[test]
+    fn blocks_interrupt_lines() {
+        let pages = layout("<div>before<p>inside</p>after</div>");
+        let lines = page_lines(&pages[0]);
+        let text: Vec<String> = lines.iter().map(|line| line_text(line)).collect();
+        assert_eq!(text, vec!["before", "inside", "after"]);
+
+        let div = &pages[0].root_element.children[0].children[0].children[0];
+        assert_eq!(div.children.iter().map(|child| child.kind.clone()).collect::<Vec<_>>(), vec![ElementKind::Line, ElementKind::Box, ElementKind::Line]);
+    }
+
+    #[test]
+    fn box_model() {
+        let pages = layout("<style>@page { size: 300pt 300pt; margin: 0 } body { margin: 0 } div { font-size: 8pt; line-height: 10pt }</style>\
+            <div id=a style='border: 2pt solid; padding: 5pt 10pt; margin: 10pt auto; width: 100pt'>a</div>\
+            <div id=b style='padding-left: 10%; border-left: thick solid'>b</div>\
+            <div id=c style='margin: 20pt 0'>c</div>\
+            <div id=d style='margin-top: 30pt; border-top: 1pt solid'><div style='margin-top: 10pt'>d</div></div>");
+
+        let body = &pages[0].root_element.children[0].children[0];
+        let boxes: Vec<(f64, f64, f64, f64)> = body.children.iter().map(|element| (element.x, element.y, element.width, element.height)).collect();
+
+        // Auto margins center the box, whose size includes the padding and the borders. The margins
+        // of the first box collapse with those of its ancestors, margins of siblings collapse too.
+        assert_eq!(boxes[0], (88.0, 10.0, 124.0, 24.0));
+        assert_eq!(boxes[1], (0.0, 44.0, 300.0, 10.0));
+        assert_eq!(boxes[2], (0.0, 74.0, 300.0, 10.0));
+        assert_eq!(boxes[3], (0.0, 114.0, 300.0, 21.0));
+        assert_eq!(pages[0].root_element.children[0].y, 10.0);
+
+        assert_eq!(body.children[0].children[0].x, 100.0);
+        assert_eq!(body.children[1].children[0].x, 33.75);
+        assert_eq!(body.children[3].children[0].y, 125.0);
+    }
+
+    #[test]
+    fn paragraphs_taller_than_a_page_continue_on_the_next_pages() {
+        let pages = layout(&format!("<style>@page {{ size: 150pt 60pt; margin: 0 }} body, p {{ margin: 0 }}</style><p>{}</p>", WORDS));
+        assert!(pages.len() > 2);
+
+        let mut text = Vec::<String>::new();
+        for page in &pages {
+            let lines = page_lines(page);
+            assert!(!lines.is_empty());
+            assert_eq!(lines[0].y, 0.0);
+            assert!(lines.iter().all(|line| line.y + line.height <= 60.01));
+
+            // Only the lines on the page are in the part of the paragraph on it.
+            let paragraph = &page.root_element.children[0].children[0].children[0];
+            assert_eq!(paragraph.children.len(), lines.len());
+
+            text.extend(lines.iter().map(|line| line_text(line)));
+        }
+
+        assert_eq!(text.join(" "), WORDS);
+    }
+
+    #[test]
+    fn viewport_is_the_page_area_of_the_first_page() {
+        let pages = layout("<style>@page { size: 200pt 200pt; margin: 0 } body { margin: 0 } div { width: 50vw; height: 25vh }</style><div></div>");
+        let div = &pages[0].root_element.children[0].children[0].children[0];
+        assert_eq!(div.width, 100.0);
+        assert_eq!(div.height, 50.0);
+    }
+
+    #[test]
+    fn media_queries_match_the_page_size_of_page_rules() {
+        let pages = layout("<style>@page { size: 300pt 200pt; margin: 0 } body { margin: 0 } div { width: 10pt; height: 10pt } \
+            @media (orientation: landscape) and (min-width: 300pt) { div { width: 50pt } }</style><div></div>");
+        let div = &pages[0].root_element.children[0].children[0].children[0];
+        assert_eq!(div.width, 50.0);
+    }
+
+    /// Returns the margin box at given position on given page.
+    fn margin_box(page: &Page, position: MarginBoxPosition) -> &MarginBox {
+        return page.margin_boxes.iter().find(|margin_box| margin_box.position == position).expect("margin box should be generated");
+    }
+
+    #[test]
+    fn named_strings_set_at_the_start_of_a_page_or_in_the_middle_of_it() {
+        let pages = layout("<style>\
+            @page { size: 200pt 150pt; margin: 20pt 0 20pt 0;\
+                @top-left { content: string(title) } @top-center { content: string(title, start) }\
+                @top-right { content: string(title, last) } @bottom-center { content: 'Page ' counter(page) ': ' string(title, first-except) } }\
+            body, h1, p { margin: 0 } p { height: 10pt } h1 { string-set: title content() }\
+            .next { break-before: page }</style>\
+            <h1>A</h1><p></p><h1>B</h1>\
+            <p class=next></p><h1>C</h1>\
+            <h1 class=next>D</h1>\
+            <p class=next></p>");
+
+        let texts: Vec<Vec<&str>> = pages.iter()
+            .map(|page| [MarginBoxPosition::TopLeft, MarginBoxPosition::TopCenter, MarginBoxPosition::TopRight, MarginBoxPosition::BottomCenter].iter()
+                .map(|position| margin_box(page, *position).text.as_str())
+                .collect())
+            .collect();
+
+        assert_eq!(texts, vec![
+            vec!["A", "A", "B", "Page 1: "],
+            vec!["C", "B", "C", "Page 2: "],
+            vec!["D", "D", "D", "Page 3: "],
+            vec!["D", "D", "D", "Page 4: D"]
+        ]);
+    }
+
+    #[test]
+    fn string_set_values() {
+        let pages = layout("<style>@page { margin: 20pt; @top-center { content: string(a) '|' string(b) } }\
+            p { string-set: a 'p. ' counter(page, lower-roman) ' ' attr(title), b content(text) content(before) }</style>\
+            <p title='Intro'>Text</p><p style='break-before: page' title='Next'>More</p>");
+
+        assert_eq!(margin_box(&pages[0], MarginBoxPosition::TopCenter).text, "p. i Intro|Text");
+        assert_eq!(margin_box(&pages[1], MarginBoxPosition::TopCenter).text, "p. ii Next|More");
+    }
+
+    #[test]
+    fn running_elements_repeat_on_the_following_pages() {
+        let pages = layout("<style>@page { size: 200pt 100pt; margin: 20pt 0; @top-center { content: element(header) } }\
+            body, p { margin: 0 } .header { position: running(header) } .next { break-before: page }</style>\
+            <div class=header>One</div><p>a</p><p class=next>b</p>\
+            <p class=next>c</p><div class=header>Two</div>");
+
+        let headers: Vec<String> = pages.iter()
+            .map(|page| margin_box(page, MarginBoxPosition::TopCenter).element.as_ref().map_or(String::new(), |element| {
+                let mut lines = Vec::new();
+                collect_lines(element, &mut lines);
+                return lines.iter().map(|line| line_text(line)).collect::<Vec<String>>().join(" ");
+            }))
+            .collect();
+
+        // The second header is not at the start of its page, but the first assignment on a page wins.
+        assert_eq!(headers, vec!["One", "One", "Two"]);
+
+        // Running elements are not in the normal flow.
+        assert!(page_lines(&pages[0]).iter().all(|line| line_text(line) != "One"));
+    }
+
+    #[test]
+    fn named_pages_start_new_page_sequences() {
+        let pages = layout("<style>@page { size: 200pt 100pt; margin: 0 } @page wide { size: 300pt 100pt }\
+            body, p, div { margin: 0 } .wide { page: wide } .right { break-before: right }</style>\
+            <p>a</p><p>b</p>\
+            <div class=wide><p>c</p><p>d</p></div><div class=wide><p>e</p></div>\
+            <p>f</p>\
+            <p class=right>g</p>");
+
+        let summary: Vec<(Option<&str>, bool, f64, Vec<String>)> = pages.iter()
+            .map(|page| (
+                page.context.name.as_deref(),
+                page.context.is_blank,
+                page.geometry.width,
+                page_lines(page).iter().map(|line| line_text(line)).collect()
+            ))
+            .collect();
+
+        // Neighbouring elements on the same named page share a sequence, and a forced break to a right page inserts a blank one.
+        assert_eq!(summary, vec![
+            (None, false, 200.0, vec![String::from("a"), String::from("b")]),
+            (Some("wide"), false, 300.0, vec![String::from("c"), String::from("d"), String::from("e")]),
+            (None, false, 200.0, vec![String::from("f")]),
+            (None, true, 200.0, vec![]),
+            (None, false, 200.0, vec![String::from("g")])
+        ]);
+
+        // Before the first content, a named page names the first sequence instead of starting another one.
+        let pages = layout("<style>@page wide { size: 300pt 100pt } .wide { page: wide; break-before: page }</style>\
+            <div class=wide><p>a</p></div><p>b</p>");
+
+        let names: Vec<Option<&str>> = pages.iter().map(|page| page.context.name.as_deref()).collect();
+        assert_eq!(names, vec![Some("wide"), None]);
+    }
+}
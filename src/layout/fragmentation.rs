@@ -0,0 +1,351 @@
+use crate::html;
+use crate::layout::{Element, ElementKind};
+
+// Content that does not fit on a page continues on the next one. Pages break between line boxes
+// and empty boxes, preferring places where the break properties, `orphans` and `widows` do not mind a break.
+// https://www.w3.org/TR/css-break-3/
+
+/// Lines are allowed to overflow the page area by this much, in points, so rounding errors do not break pages.
+const OVERFLOW_TOLERANCE: f64 = 0.01;
+
+/// A break between elements forced by `break-before` or `break-after`.
+/// https://www.w3.org/TR/css-break-3/#break-between
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForcedBreak {
+
+    /// Content continues on the next page.
+    Page,
+
+    /// Content continues on the next left page, a blank page is inserted if needed.
+    Left,
+
+    /// Content continues on the next right page, a blank page is inserted if needed.
+    Right
+}
+
+/// Where content laid out in a single layout tree breaks, to continue on the next page.
+#[derive(Debug, Clone, Copy)]
+pub struct PageBreak {
+
+    /// Index of the first line or empty box after the break, in the order of `collect_leaves`.
+    pub leaf: usize,
+
+    /// Position of the content after the break in the layout tree, which is where the next page starts.
+    pub y: f64
+}
+
+/// A place on a page where it can break, which is before a line or an empty box.
+struct BreakPoint<'a> {
+
+    /// Boxes starting after the break, outermost first. The line or empty box itself is the last.
+    starting: &'a [&'a Element],
+
+    /// Boxes ending before the break, outermost first.
+    ending: &'a [&'a Element],
+
+    /// Boxes that are split by the break, outermost first.
+    spanning: &'a [&'a Element],
+
+    /// Paths to the lines and empty boxes that are on the page before the break.
+    preceding_leaves: &'a [Vec<&'a Element>],
+
+    /// Paths to the lines and empty boxes after the break.
+    following_leaves: &'a [Vec<&'a Element>]
+}
+
+/// Rules that make a break point less suitable, in order of importance. When no break point
+/// follows all of them, the least important ones are relaxed first.
+/// https://www.w3.org/TR/css-break-3/#break-rules
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BreakRule {
+
+    /// `break-before: avoid` and `break-after: avoid` on the elements around the break.
+    AvoidBetween,
+
+    /// `break-inside: avoid` on the elements split by the break.
+    AvoidInside,
+
+    /// `orphans` and `widows` of the blocks split by the break.
+    OrphansAndWidows
+}
+
+impl ForcedBreak {
+
+    /// Combines forced breaks at the same place. A break to a side wins over a plain page break,
+    /// and of two breaks to a side the one of the latter element wins.
+    /// https://www.w3.org/TR/css-break-3/#forced-breaks
+    pub fn combine(earlier: Option<ForcedBreak>, later: Option<ForcedBreak>) -> Option<ForcedBreak> {
+        match (earlier, later) {
+            (earlier, None) => earlier,
+            (Some(earlier), Some(ForcedBreak::Page)) => Some(earlier),
+            (_, later) => later
+        }
+    }
+}
+
+/// Returns the break that given break property, `break-before` or `break-after`, of an element forces.
+/// Multi-column layout and regions are not supported, so their breaks are not forced.
+pub fn get_forced_break(element: &html::Element, property: &str) -> Option<ForcedBreak> {
+    let value = element.get_style_property(property)?;
+    if !value.is_identifier() {
+        return None;
+    }
+
+    // Pages progress left to right, so the recto page is the right one.
+    match value.as_string().to_ascii_lowercase().as_str() {
+        "page" | "always" | "all" => Some(ForcedBreak::Page),
+        "left" | "verso" => Some(ForcedBreak::Left),
+        "right" | "recto" => Some(ForcedBreak::Right),
+        _ => None
+    }
+}
+
+/// Finds where a page has to break for its content to fit into a page area of given height. The content is laid out
+/// in a single layout tree whose lines and empty boxes are `leaves`, see `collect_leaves`. The page starts with
+/// the leaf at `start`, which is at `top` in the tree. Returns `None` if all the content that is left fits.
+///
+/// The break is as late as possible before the first line or empty box that overflows the page area,
+/// at a break point that follows as many break rules as possible.
+pub fn find_page_break(document: &html::Document, leaves: &[Vec<&Element>], start: usize, top: f64, content_height: f64) -> Option<PageBreak> {
+    let overflows = |path: &Vec<&Element>| path.last().is_some_and(|leaf| leaf.y + leaf.height - top > content_height + OVERFLOW_TOLERANCE);
+
+    // The first line on a page does not fit anywhere else, so breaking before it does not help.
+    let overflow = leaves.iter().skip(start + 1).position(overflows)? + start + 1;
+
+    let break_points: Vec<(PageBreak, BreakPoint)> = (start + 1..=overflow)
+        .map(|idx| {
+            let (previous_path, path) = (&leaves[idx - 1], &leaves[idx]);
+            let common = previous_path.iter().zip(path.iter()).take_while(|(previous, next)| std::ptr::eq(**previous, **next)).count();
+
+            let break_point = BreakPoint {
+                starting: &path[common..],
+                ending: &previous_path[common..],
+                spanning: &path[..common],
+                preceding_leaves: &leaves[start..idx],
+                following_leaves: &leaves[idx..]
+            };
+
+            (PageBreak { leaf: idx, y: path[common].y }, break_point)
+        })
+        .collect();
+
+    let rule_sets: [&[BreakRule]; 3] = [
+        &[BreakRule::AvoidBetween, BreakRule::AvoidInside, BreakRule::OrphansAndWidows],
+        &[BreakRule::AvoidBetween, BreakRule::AvoidInside],
+        &[BreakRule::AvoidBetween]
+    ];
+
+    for rules in rule_sets.iter() {
+        let suitable = break_points.iter().rev().find(|(_, break_point)| {
+            rules.iter().all(|rule| follows_rule(document, break_point, *rule))
+        });
+
+        if let Some((page_break, _)) = suitable {
+            return Some(*page_break);
+        }
+    }
+
+    // With all rules relaxed, the page breaks right before the overflowing content.
+    return break_points.last().map(|(page_break, _)| *page_break);
+}
+
+/// Returns the paths from `root` to all lines and empty boxes in it, in document order.
+pub fn collect_leaves(root: &Element) -> Vec<Vec<&Element>> {
+    let mut paths = Vec::<Vec<&Element>>::new();
+    collect_leaf_paths(root, &mut Vec::new(), &mut paths);
+    return paths;
+}
+
+fn collect_leaf_paths<'a>(element: &'a Element, path: &mut Vec<&'a Element>, paths: &mut Vec<Vec<&'a Element>>) {
+    path.push(element);
+
+    if is_leaf(element) {
+        paths.push(path.clone());
+    } else {
+        for child in &element.children {
+            collect_leaf_paths(child, path, paths);
+        }
+    }
+
+    path.pop();
+}
+
+/// Returns whether pages break around given element rather than inside it, which they do around lines and empty boxes.
+fn is_leaf(element: &Element) -> bool {
+    return element.kind == ElementKind::Line || element.children.is_empty();
+}
+
+/// Splits content laid out as `root` into pages at given breaks, see `find_page_break`. Every page gets the parts
+/// of the elements whose lines and empty boxes are on it, moved up so that the content starts at the top of the page.
+/// Elements that continue on other pages are cut at the breaks.
+pub fn split_into_pages(root: &Element, page_breaks: &[PageBreak]) -> Vec<Element> {
+    return split_element(root, page_breaks, &mut 0).into_iter()
+        .map(|(_, part)| part)
+        .collect();
+}
+
+/// Splits given element into the parts on each page, each with the index of its page.
+/// `next_leaf` is the index of the first line or empty box in the element.
+fn split_element(element: &Element, page_breaks: &[PageBreak], next_leaf: &mut usize) -> Vec<(usize, Element)> {
+    let page_top = |page_index: usize| if page_index == 0 { 0.0 } else { page_breaks[page_index - 1].y };
+
+    if is_leaf(element) {
+        let page_index = page_breaks.partition_point(|page_break| page_break.leaf <= *next_leaf);
+        *next_leaf += 1;
+
+        let mut part = element.clone();
+        move_up(&mut part, page_top(page_index));
+        return vec![(page_index, part)];
+    }
+
+    let mut parts = Vec::<(usize, Element)>::new();
+    for child in &element.children {
+        for (page_index, child_part) in split_element(child, page_breaks, next_leaf) {
+            match parts.last_mut() {
+                Some((last_page_index, part)) if *last_page_index == page_index => part.children.push(child_part),
+                _ => parts.push((page_index, Element {
+                    kind: element.kind.clone(),
+                    children: vec![child_part],
+                    ..*element
+                }))
+            }
+        }
+    }
+
+    let first_page_index = parts.first().map_or(0, |(page_index, _)| *page_index);
+    let last_page_index = parts.last().map_or(0, |(page_index, _)| *page_index);
+
+    for (page_index, part) in parts.iter_mut() {
+        let top = if *page_index > first_page_index { page_top(*page_index) } else { element.y };
+        let bottom = if *page_index < last_page_index { page_top(*page_index + 1) } else { element.y + element.height };
+
+        part.y = top - page_top(*page_index);
+        part.height = bottom - top;
+
+        for child in part.children.iter_mut() {
+            child.local_y = child.y - part.y;
+        }
+    }
+
+    return parts;
+}
+
+/// Moves given element and its descendants up by given distance.
+fn move_up(element: &mut Element, distance: f64) {
+    element.y -= distance;
+
+    for child in element.children.iter_mut() {
+        move_up(child, distance);
+    }
+}
+
+/// Returns whether breaking at given break point follows given rule.
+fn follows_rule(document: &html::Document, break_point: &BreakPoint, rule: BreakRule) -> bool {
+    let has_keyword = |element_index: usize, property: &str, keywords: &[&str]| {
+        document.get_element_immutable(element_index)
+            .get_style_property(property)
+            .is_some_and(|value| keywords.iter().any(|keyword| value.is_keyword(keyword)))
+    };
+
+    match rule {
+        BreakRule::AvoidBetween => {
+            let avoided_before = boxes(break_point.starting).any(|element| has_keyword(element, "break-before", &["avoid", "avoid-page"]));
+            let avoided_after = boxes(break_point.ending).any(|element| has_keyword(element, "break-after", &["avoid", "avoid-page"]));
+            return !avoided_before && !avoided_after;
+        }
+
+        BreakRule::AvoidInside => {
+            return !boxes(break_point.spanning).any(|element| has_keyword(element, "break-inside", &["avoid", "avoid-page"]));
+        }
+
+        BreakRule::OrphansAndWidows => {
+            return break_point.spanning.iter().enumerate().all(|(depth, element)| {
+                let is_in_element = |path: &&Vec<&Element>| path.get(depth).is_some_and(|ancestor| std::ptr::eq(*ancestor, *element));
+
+                // Lines of a block container are its line boxes, the lines of blocks inside it do not count.
+                // https://www.w3.org/TR/css-break-3/#widows-orphans
+                let is_line = |path: &&Vec<&Element>| path.len() == depth + 2 && path[depth + 1].kind == ElementKind::Line;
+
+                // Lines on previous pages are neither before nor after this break.
+                let before = break_point.preceding_leaves.iter().rev().take_while(is_in_element).filter(is_line).count();
+                let after = break_point.following_leaves.iter().take_while(is_in_element).filter(is_line).count();
+
+                let html_element = document.get_element_immutable(element.element);
+                let orphans = get_integer(html_element, "orphans", 2);
+                let widows = get_integer(html_element, "widows", 2);
+                (before == 0 || before >= orphans) && (after == 0 || after >= widows)
+            });
+        }
+    }
+}
+
+/// Returns the indices of the HTML elements whose boxes are on given path, leaving out line boxes.
+fn boxes<'a>(path: &'a [&'a Element]) -> impl Iterator<Item = usize> + 'a {
+    return path.iter()
+        .filter(|element| element.kind != ElementKind::Line)
+        .map(|element| element.element);
+}
+
+fn get_integer(element: &html::Element, property: &str, default: usize) -> usize {
+    return element.get_style_property(property)
+        .filter(|value| value.is_number())
+        .map_or(default, |value| value.as_number().max(1.0) as usize);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Converter;
+
+    /// Lays out given paragraphs on pages that fit five of their lines, and returns the number of lines on each page.
+    /// Each paragraph is given by its style and its number of lines.
+    fn lines_per_page(paragraphs: &[(&str, usize)]) -> Vec<usize> {
+        fn count_lines(element: &Element) -> usize {
+            if element.kind == ElementKind::Line {
+                return 1;
+            }
+
+            return element.children.iter().map(count_lines).sum();
+        }
+
+        let mut html = String::from("<style>@page { size: 100pt 50pt; margin: 0 } body, p { margin: 0 } p { font-size: 8pt; line-height: 10pt }</style>");
+        for (style, line_count) in paragraphs {
+            let lines: Vec<String> = (0..*line_count).map(|line| format!("line{}", line)).collect();
+            html.push_str(&format!("<p style='{}'>{}</p>", style, lines.join("<br>")));
+        }
+
+        let pages = Converter::new().html(&html).layout().expect("test document should lay out").pages;
+        return pages.iter().map(|page| count_lines(&page.root_element)).collect();
+    }
+
+    #[test]
+    fn widows() {
+        assert_eq!(lines_per_page(&[("", 7)]), vec![5, 2]);
+        assert_eq!(lines_per_page(&[("widows: 3", 7)]), vec![4, 3]);
+
+        // Rules that no break follows are relaxed.
+        assert_eq!(lines_per_page(&[("orphans: 3; widows: 5", 7)]), vec![5, 2]);
+    }
+
+    #[test]
+    fn orphans() {
+        assert_eq!(lines_per_page(&[("", 4), ("orphans: 1", 4)]), vec![5, 3]);
+        assert_eq!(lines_per_page(&[("", 4), ("", 4)]), vec![4, 4]);
+    }
+
+    #[test]
+    fn lines_of_nested_blocks_do_not_count() {
+        let html = "<style>@page { size: 100pt 50pt; margin: 0 } body, div { margin: 0; font-size: 8pt; line-height: 10pt }</style>\
+            <div style='widows: 3'>a<br>b<br>c<br>d<div>e</div>f</div>";
+
+        let pages = Converter::new().html(html).layout().expect("test document should lay out").pages;
+        assert_eq!(pages.len(), 2);
+    }
+
+    #[test]
+    fn break_avoidance() {
+        assert_eq!(lines_per_page(&[("", 3), ("break-inside: avoid", 3)]), vec![3, 3]);
+        assert_eq!(lines_per_page(&[("break-after: avoid", 3), ("", 3)]), vec![5, 1]);
+        assert_eq!(lines_per_page(&[("", 4), ("break-before: avoid", 2)]), vec![2, 4]);
+    }
+}
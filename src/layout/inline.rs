@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use crate::html;
+use crate::layout::{Element, ElementKind};
+use crate::generator::resources_manager::ResourcesManager;
+use crate::utils::font::Font;
+
+// Inline content of a block container is laid out in line boxes, which are stacked like block boxes.
+// Lines break at spaces between words and at `<br>` elements, a word that does not fit on a line starts the next one.
+// https://www.w3.org/TR/css-inline-3/#line-boxes
+//
+// The parser trims text nodes, so neighbouring text nodes are assumed to be separated by a space.
+
+/// Lines may be wider than the space available by this much, in points, so rounding errors do not break lines.
+const OVERFLOW_TOLERANCE: f64 = 0.01;
+
+/// A piece of the inline content of a block container.
+#[derive(Debug, Clone)]
+pub enum InlineItem {
+
+    /// A word of a text node. `ancestors` are the inline elements the text node is in, outermost first.
+    Word {
+        text_node: usize,
+        ancestors: Vec<usize>,
+        text: String
+    },
+
+    /// A forced line break, like `<br>`.
+    LineBreak
+}
+
+/// Font and vertical metrics of an element's text, in points.
+#[derive(Clone, Copy)]
+struct TextMetrics<'a> {
+    font: Option<&'a Font>,
+    font_size: f64,
+
+    /// Computed value of `line-height`, which is the height of the text's box on a line.
+    line_height: f64,
+
+    /// Distance from the top of the text's box to the baseline, which includes half of the leading.
+    /// https://www.w3.org/TR/CSS2/visudet.html#leading
+    baseline: f64,
+
+    space_width: f64
+}
+
+/// Words of a text node that are next to each other on a line.
+struct Fragment<'a> {
+    text_node: usize,
+    ancestors: &'a [usize],
+    text: String,
+    x: f64,
+    width: f64
+}
+
+impl<'a> TextMetrics<'a> {
+
+    /// Returns the width of given text in this font.
+    fn measure(&self, text: &str) -> f64 {
+        return self.font.map_or(0.0, |font| font.get_text_bounding_box(text, self.font_size, false).width);
+    }
+
+    fn new(style: &html::ElementStyleProperties, resources: &'a ResourcesManager, default_font: &String) -> Self {
+        let font = resources.get_font(&resources.select_font(&style.get_font_families(), default_font)).map(|resource| &resource.font);
+        let font_size = style.get_font_size();
+        let (ascent, descent, line_gap) = font.map_or((0.0, 0.0, 0.0), |font| font.get_vertical_metrics(font_size));
+
+        let line_height = match style.get("line-height") {
+            Some(value) if value.is_number() => value.as_number() * font_size,
+            Some(value) if value.is_dimension_value() => value.as_dimension_value().value,
+            _ => ascent + descent + line_gap
+        };
+
+        let mut metrics = TextMetrics {
+            font: font,
+            font_size: font_size,
+            line_height: line_height,
+            baseline: (line_height - ascent - descent) / 2.0 + ascent,
+            space_width: 0.0
+        };
+
+        metrics.space_width = metrics.measure(" ");
+        return metrics;
+    }
+}
+
+/// Lays out inline content of block container `container` in lines of given width, which is unlimited if it is not known.
+/// Returns the line boxes, each at the origin. A line is as wide as the width given, or as its content if there is none.
+pub fn layout_lines(document: &html::Document, resources: &ResourcesManager, default_font: &String, container: &html::Element, items: &[InlineItem], width: Option<f64>) -> Vec<Element> {
+    let mut text_metrics = HashMap::<usize, TextMetrics>::new();
+    let mut get_metrics = |text_node: usize| -> TextMetrics {
+        return *text_metrics.entry(text_node)
+            .or_insert_with(|| TextMetrics::new(document.get_element_immutable(text_node).get_style_properties(), resources, default_font));
+    };
+
+    let available_width = width.unwrap_or(f64::INFINITY);
+    let mut lines = Vec::<Vec<Fragment>>::new();
+    let mut line = Vec::<Fragment>::new();
+    let mut line_width = 0.0;
+
+    for item in items {
+        let (text_node, ancestors, text) = match item {
+            InlineItem::Word { text_node, ancestors, text } => (*text_node, ancestors, text),
+            InlineItem::LineBreak => {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0.0;
+                continue;
+            }
+        };
+
+        let metrics = get_metrics(text_node);
+        let word_width = metrics.measure(text);
+
+        // A word that is wider than the line does not fit anywhere else, so it overflows the line.
+        if !line.is_empty() && line_width + metrics.space_width + word_width > available_width + OVERFLOW_TOLERANCE {
+            lines.push(std::mem::take(&mut line));
+            line_width = 0.0;
+        }
+
+        let space_width = if line.is_empty() { 0.0 } else { metrics.space_width };
+        match line.last_mut() {
+            Some(fragment) if fragment.text_node == text_node => {
+                fragment.text.push(' ');
+                fragment.text.push_str(text);
+                fragment.width += space_width + word_width;
+            }
+
+            _ => line.push(Fragment {
+                text_node: text_node,
+                ancestors: ancestors,
+                text: text.clone(),
+                x: line_width + space_width,
+                width: word_width
+            })
+        }
+
+        line_width += space_width + word_width;
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    // Every line is at least as high as the container's own text would make it.
+    // https://www.w3.org/TR/CSS2/visudet.html#strut
+    let strut = TextMetrics::new(container.get_style_properties(), resources, default_font);
+    let alignment = match container.get_style_property("text-align") {
+        Some(value) if value.is_keyword("center") => 0.5,
+        Some(value) if value.is_keyword("right") || value.is_keyword("end") => 1.0,
+        _ => 0.0
+    };
+
+    return lines.into_iter()
+        .map(|fragments| {
+            // Texts on a line share the baseline, the line is as high as it needs to be for all of them to fit.
+            let mut above_baseline = strut.baseline;
+            let mut below_baseline = strut.line_height - strut.baseline;
+
+            for fragment in &fragments {
+                let metrics = get_metrics(fragment.text_node);
+                above_baseline = f64::max(above_baseline, metrics.baseline);
+                below_baseline = f64::max(below_baseline, metrics.line_height - metrics.baseline);
+            }
+
+            let content_width = fragments.last().map_or(0.0, |fragment| fragment.x + fragment.width);
+            let mut line = Element::default(container.index);
+            line.kind = ElementKind::Line;
+            line.width = width.unwrap_or(content_width);
+            line.height = above_baseline + below_baseline;
+
+            let offset = (line.width - content_width).max(0.0) * alignment;
+
+            for fragment in fragments {
+                let metrics = get_metrics(fragment.text_node);
+                let mut text = Element::default(fragment.text_node);
+                text.kind = ElementKind::Text { text: fragment.text, baseline: metrics.baseline };
+                text.local_x = fragment.x + offset;
+                text.local_y = above_baseline - metrics.baseline;
+                text.width = fragment.width;
+                text.height = metrics.line_height;
+
+                place_in_inline_boxes(&mut line.children, fragment.ancestors, text);
+            }
+
+            for child in line.children.iter_mut() {
+                fit_inline_box(child);
+            }
+
+            return line;
+        })
+        .collect();
+}
+
+/// Puts a part of a text node on a line into the boxes of inline elements `ancestors`, outermost first.
+/// Neighbouring parts of the same inline element share its box.
+fn place_in_inline_boxes(children: &mut Vec<Element>, ancestors: &[usize], text: Element) {
+    let (ancestor, rest) = match ancestors.split_first() {
+        Some(split) => split,
+        None => {
+            children.push(text);
+            return;
+        }
+    };
+
+    match children.last_mut() {
+        Some(inline_box) if inline_box.kind == ElementKind::Box && inline_box.element == *ancestor => {
+            place_in_inline_boxes(&mut inline_box.children, rest, text);
+        }
+
+        _ => {
+            let mut inline_box = Element::default(*ancestor);
+            place_in_inline_boxes(&mut inline_box.children, rest, text);
+            children.push(inline_box);
+        }
+    }
+}
+
+/// Makes given inline box as large as the text in it, whose positions are relative to the line until then.
+fn fit_inline_box(element: &mut Element) {
+    if element.children.is_empty() {
+        return;
+    }
+
+    for child in element.children.iter_mut() {
+        fit_inline_box(child);
+    }
+
+    let left = element.children.iter().map(|child| child.local_x).fold(f64::INFINITY, f64::min);
+    let top = element.children.iter().map(|child| child.local_y).fold(f64::INFINITY, f64::min);
+    let right = element.children.iter().map(|child| child.local_x + child.width).fold(f64::NEG_INFINITY, f64::max);
+    let bottom = element.children.iter().map(|child| child.local_y + child.height).fold(f64::NEG_INFINITY, f64::max);
+
+    element.local_x = left;
+    element.local_y = top;
+    element.width = right - left;
+    element.height = bottom - top;
+
+    for child in element.children.iter_mut() {
+        child.local_x -= left;
+        child.local_y -= top;
+    }
+}
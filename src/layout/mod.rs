@@ -1,6 +1,13 @@
 mod engine;
+mod page;
+mod running;
+mod fragmentation;
+mod inline;
 
 pub use self::engine::{
     Engine,
-    Element
-};
\ No newline at end of file
+    Element,
+    ElementKind
+};
+
+pub use self::page::{Page, PageGeometry, MarginBox};
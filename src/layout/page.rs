@@ -0,0 +1,425 @@
+use crate::css::{PrimitiveValue, MarginBoxPosition};
+use crate::html::{ElementStyleProperties, PageContext};
+use crate::generator::{ConversionOptions, PageSize};
+use crate::layout::Element;
+use crate::layout::engine::resolve_length;
+use crate::layout::running::{RunningContent, RunningPolicy};
+use crate::utils::{units, counter};
+
+/// Size of a page and its margins, in points.
+/// https://www.w3.org/TR/css-page-3/#page-model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageGeometry {
+    pub width: f64,
+    pub height: f64,
+    pub margin_top: f64,
+    pub margin_right: f64,
+    pub margin_bottom: f64,
+    pub margin_left: f64
+}
+
+/// A laid out page of the document.
+#[derive(Debug, Clone)]
+pub struct Page {
+
+    /// Position and name of the page, which decided what `@page` rules apply to it.
+    pub context: PageContext,
+    pub geometry: PageGeometry,
+
+    /// The part of the document that is on this page. Positions are relative to the page area.
+    pub root_element: Element,
+
+    /// Margin boxes generated for this page, in the order of `MarginBoxPosition::ALL`.
+    pub margin_boxes: Vec<MarginBox>
+}
+
+/// A laid out margin box, like a running header or footer.
+/// Positions are relative to the page area, like those of the page's elements.
+/// https://www.w3.org/TR/css-page-3/#margin-boxes
+#[derive(Debug, Clone)]
+pub struct MarginBox {
+    pub position: MarginBoxPosition,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+
+    /// Text generated by the box's `content` property, empty if the box contains a running element.
+    pub text: String,
+
+    /// Running element placed into the box by `content: element()`.
+    pub element: Option<Element>,
+
+    /// Position of the text, or of the running element, aligned by the box's `text-align` and `vertical-align`.
+    pub text_x: f64,
+    pub text_y: f64,
+
+    /// Computed values of the box's properties.
+    pub style: ElementStyleProperties
+}
+
+impl PageGeometry {
+
+    /// Returns the width of the page area, which is the page without its left and right margins.
+    pub fn content_width(&self) -> f64 {
+        return self.width - self.margin_left - self.margin_right;
+    }
+
+    /// Returns the height of the page area, which is the page without its top and bottom margins.
+    pub fn content_height(&self) -> f64 {
+        return self.height - self.margin_top - self.margin_bottom;
+    }
+
+    /// Returns geometry of a page with given computed properties of its page context.
+    /// The size and margins from `options` are used for anything the properties do not set.
+    pub fn from_properties(properties: &ElementStyleProperties, options: &ConversionOptions) -> Self {
+        let (width, height) = properties.get("size")
+            .and_then(|size| parse_page_size(size, options))
+            .unwrap_or((units::mm_to_pt(options.page_width()), units::mm_to_pt(options.page_height())));
+
+        // Percentages of vertical margins refer to the page's height, of horizontal ones to its width.
+        let margin = |name: &str, reference: f64, default: f64| {
+            return resolve_length(properties.get(name), Some(reference)).unwrap_or(units::mm_to_pt(default));
+        };
+
+        return Self {
+            width: width,
+            height: height,
+            margin_top: margin("margin-top", height, options.margins.top),
+            margin_right: margin("margin-right", width, options.margins.right),
+            margin_bottom: margin("margin-bottom", height, options.margins.bottom),
+            margin_left: margin("margin-left", width, options.margins.left)
+        };
+    }
+}
+
+/// Returns the page size, in points, given by the computed value of the `size` property.
+/// Returns `None` for `auto` and any value that is not a valid size.
+/// https://www.w3.org/TR/css-page-3/#page-size-prop
+fn parse_page_size(value: &PrimitiveValue, options: &ConversionOptions) -> Option<(f64, f64)> {
+    let items = if value.is_list() { value.as_items().clone() } else { vec![value.clone()] };
+
+    if items.iter().all(|item| item.is_dimension_value()) {
+        return match items.as_slice() {
+            [size] => Some((size.as_dimension_value().value, size.as_dimension_value().value)),
+            [width, height] => Some((width.as_dimension_value().value, height.as_dimension_value().value)),
+            _ => None
+        };
+    }
+
+    // A page size keyword and an orientation, either of which may be missing.
+    let mut size = PageSize { width: options.page_width(), height: options.page_height() };
+    let mut is_landscape = None;
+
+    for item in &items {
+        match item.as_string().to_ascii_lowercase().as_str() {
+            "auto" => return None,
+            "portrait" => is_landscape = Some(false),
+            "landscape" => is_landscape = Some(true),
+            name => size = PageSize::from_name(name)?
+        }
+    }
+
+    let (short_side, long_side) = (size.width.min(size.height), size.width.max(size.height));
+    let (width, height) = match is_landscape {
+        Some(true) => (long_side, short_side),
+        Some(false) => (short_side, long_side),
+        None => (size.width, size.height)
+    };
+
+    return Some((units::mm_to_pt(width), units::mm_to_pt(height)));
+}
+
+/// What a margin box contains.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarginBoxContent {
+    Text(String),
+
+    /// A running element, by the index of its HTML element.
+    Element(usize)
+}
+
+/// Returns the content that the `content` property of a margin box on page at `page_index` generates,
+/// or `None` if it generates no box. A running element replaces all other content of the box.
+/// Counters other than `page` and `pages` are not defined in the page context, so their value is zero.
+/// https://www.w3.org/TR/css-page-3/#page-based-counters
+pub fn generate_margin_box_content(content: Option<&PrimitiveValue>, page_index: usize, page_count: usize, running: &RunningContent) -> Option<MarginBoxContent> {
+    let content = content?;
+    if content.is_keyword("none") || content.is_keyword("normal") {
+        return None;
+    }
+
+    let items = if content.is_list() { content.as_items().clone() } else { vec![content.clone()] };
+    let mut text = String::new();
+
+    for item in &items {
+        if item.is_string() {
+            text.push_str(item.as_string());
+            continue;
+        }
+
+        if !item.is_function() {
+            continue;
+        }
+
+        let arguments = item.as_items();
+        match item.as_string().as_str() {
+            "counter" | "counters" => {
+                let value = match arguments[0].as_string().as_str() {
+                    "page" => page_index as i64 + 1,
+                    "pages" => page_count as i64,
+                    _ => 0
+                };
+
+                // The style is the last argument, after the separator string of `counters()`.
+                let style = arguments.iter().skip(1).find(|argument| argument.is_identifier());
+                text.push_str(&counter::format_counter(value, style.map_or("decimal", |style| style.as_string())));
+            }
+
+            "string" => {
+                text.push_str(&running.get_string(arguments[0].as_string(), page_index, RunningPolicy::from_arguments(arguments)));
+            }
+
+            "element" => {
+                if let Some(element_index) = running.get_element(arguments[0].as_string(), page_index, RunningPolicy::from_arguments(arguments)) {
+                    return Some(MarginBoxContent::Element(element_index));
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    return Some(MarginBoxContent::Text(text));
+}
+
+/// Positions margin boxes on a page. Each box is expected to be sized to its content, boxes
+/// along an edge of the page then share its length in proportion to their content, while
+/// keeping the box at the center of the edge centered.
+/// https://www.w3.org/TR/css-page-3/#margin-dimension
+pub fn place_margin_boxes(geometry: &PageGeometry, margin_boxes: &mut [MarginBox]) {
+    let content_width = geometry.content_width();
+    let content_height = geometry.content_height();
+
+    // Sizes of each box's content along the edge it is on.
+    let width_of = |position: MarginBoxPosition| margin_boxes.iter().find(|margin_box| margin_box.position == position).map(|margin_box| margin_box.width);
+    let height_of = |position: MarginBoxPosition| margin_boxes.iter().find(|margin_box| margin_box.position == position).map(|margin_box| margin_box.height);
+
+    let top = distribute_edge(content_width, width_of(MarginBoxPosition::TopLeft), width_of(MarginBoxPosition::TopCenter), width_of(MarginBoxPosition::TopRight));
+    let bottom = distribute_edge(content_width, width_of(MarginBoxPosition::BottomLeft), width_of(MarginBoxPosition::BottomCenter), width_of(MarginBoxPosition::BottomRight));
+    let left = distribute_edge(content_height, height_of(MarginBoxPosition::LeftTop), height_of(MarginBoxPosition::LeftMiddle), height_of(MarginBoxPosition::LeftBottom));
+    let right = distribute_edge(content_height, height_of(MarginBoxPosition::RightTop), height_of(MarginBoxPosition::RightMiddle), height_of(MarginBoxPosition::RightBottom));
+
+    // Relative to the page area, the left and top margins are at negative positions.
+    let (left_x, right_x) = (-geometry.margin_left, content_width);
+    let (top_y, bottom_y) = (-geometry.margin_top, content_height);
+
+    for margin_box in margin_boxes.iter_mut() {
+        let (text_width, text_height) = (margin_box.width, margin_box.height);
+
+        let (x, y, width, height) = match margin_box.position {
+            MarginBoxPosition::TopLeftCorner => (left_x, top_y, geometry.margin_left, geometry.margin_top),
+            MarginBoxPosition::TopLeft => (top[0].0, top_y, top[0].1, geometry.margin_top),
+            MarginBoxPosition::TopCenter => (top[1].0, top_y, top[1].1, geometry.margin_top),
+            MarginBoxPosition::TopRight => (top[2].0, top_y, top[2].1, geometry.margin_top),
+            MarginBoxPosition::TopRightCorner => (right_x, top_y, geometry.margin_right, geometry.margin_top),
+            MarginBoxPosition::RightTop => (right_x, right[0].0, geometry.margin_right, right[0].1),
+            MarginBoxPosition::RightMiddle => (right_x, right[1].0, geometry.margin_right, right[1].1),
+            MarginBoxPosition::RightBottom => (right_x, right[2].0, geometry.margin_right, right[2].1),
+            MarginBoxPosition::BottomRightCorner => (right_x, bottom_y, geometry.margin_right, geometry.margin_bottom),
+            MarginBoxPosition::BottomRight => (bottom[2].0, bottom_y, bottom[2].1, geometry.margin_bottom),
+            MarginBoxPosition::BottomCenter => (bottom[1].0, bottom_y, bottom[1].1, geometry.margin_bottom),
+            MarginBoxPosition::BottomLeft => (bottom[0].0, bottom_y, bottom[0].1, geometry.margin_bottom),
+            MarginBoxPosition::BottomLeftCorner => (left_x, bottom_y, geometry.margin_left, geometry.margin_bottom),
+            MarginBoxPosition::LeftBottom => (left_x, left[2].0, geometry.margin_left, left[2].1),
+            MarginBoxPosition::LeftMiddle => (left_x, left[1].0, geometry.margin_left, left[1].1),
+            MarginBoxPosition::LeftTop => (left_x, left[0].0, geometry.margin_left, left[0].1)
+        };
+
+        let keyword = |name: &str| margin_box.style.get(name).map_or(String::new(), |value| value.as_string().to_ascii_lowercase());
+        let (text_align, vertical_align) = (keyword("text-align"), keyword("vertical-align"));
+
+        margin_box.text_x = x + match text_align.as_str() {
+            "right" => width - text_width,
+            "center" => (width - text_width) / 2.0,
+            _ => 0.0
+        };
+
+        margin_box.text_y = y + match vertical_align.as_str() {
+            "top" => 0.0,
+            "bottom" => height - text_height,
+            _ => (height - text_height) / 2.0
+        };
+
+        margin_box.x = x;
+        margin_box.y = y;
+        margin_box.width = width;
+        margin_box.height = height;
+    }
+}
+
+/// Splits an edge of the page area of given length between its three margin boxes, given the size of
+/// their content or `None` for boxes that are not generated. Returns offset and size of each box.
+fn distribute_edge(length: f64, start: Option<f64>, center: Option<f64>, end: Option<f64>) -> [(f64, f64); 3] {
+
+    // Share of `length` of a box with content of size `size`, next to a box with content of size `other_size`.
+    let share = |size: f64, other_size: f64| {
+        if size + other_size <= 0.0 {
+            return length / 2.0;
+        }
+
+        return length * size / (size + other_size);
+    };
+
+    let (start_size, end_size) = match (start, center, end) {
+
+        // The center box is kept centered by giving both side boxes the size of the larger one.
+        (_, Some(center), _) => {
+            let side_size = ((length - share(center, 2.0 * start.unwrap_or(0.0).max(end.unwrap_or(0.0)))) / 2.0).max(0.0);
+            (side_size, side_size)
+        }
+
+        (Some(start), None, Some(end)) => (share(start, end), length - share(start, end)),
+        (Some(_), None, None) => (length, 0.0),
+        (None, None, _) => (0.0, length)
+    };
+
+    return [(0.0, start_size), (start_size, length - start_size - end_size), (length - end_size, end_size)];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::Parser as CssParser;
+    use crate::layout::running::RunningAssignment;
+
+    /// Returns the value of the single declaration of given property.
+    fn parse_value(property: &str, value: &str) -> PrimitiveValue {
+        let declarations = CssParser::new(format!("{}: {}", property, value)).parse_inline();
+        return declarations.into_iter().find(|declaration| declaration.name == property).expect("value should be valid").value;
+    }
+
+    /// Returns a margin box whose content has given size, before it is placed.
+    fn margin_box(position: MarginBoxPosition, width: f64, height: f64, text_align: &str) -> MarginBox {
+        let mut style = ElementStyleProperties::new();
+        style.set("text-align", parse_value("text-align", text_align));
+
+        return MarginBox {
+            position: position,
+            x: 0.0,
+            y: 0.0,
+            width: width,
+            height: height,
+            text: String::new(),
+            element: None,
+            text_x: 0.0,
+            text_y: 0.0,
+            style: style
+        };
+    }
+
+    #[test]
+    fn page_sizes() {
+        let options = ConversionOptions::default();
+        let size = |value: &str| -> Option<(f64, f64)> {
+            return parse_page_size(&parse_value("size", value), &options).map(|(width, height)| (width.round(), height.round()));
+        };
+
+        assert_eq!(size("100pt"), Some((100.0, 100.0)));
+        assert_eq!(size("100pt 200pt"), Some((100.0, 200.0)));
+        assert_eq!(size("letter"), Some((612.0, 792.0)));
+        assert_eq!(size("letter landscape"), Some((792.0, 612.0)));
+        assert_eq!(size("landscape LETTER"), Some((792.0, 612.0)));
+        assert_eq!(size("a5 portrait"), Some((420.0, 595.0)));
+
+        // An orientation alone turns the page size of the options.
+        assert_eq!(size("landscape"), Some((842.0, 595.0)));
+
+        assert_eq!(size("auto"), None);
+    }
+
+    #[test]
+    fn center_boxes_stay_centered() {
+        assert_eq!(distribute_edge(300.0, None, Some(100.0), None), [(0.0, 0.0), (0.0, 300.0), (300.0, 0.0)]);
+        assert_eq!(distribute_edge(300.0, Some(50.0), Some(100.0), None), [(0.0, 75.0), (75.0, 150.0), (225.0, 75.0)]);
+
+        // The larger side box decides the size of both.
+        assert_eq!(distribute_edge(300.0, Some(20.0), Some(100.0), Some(50.0)), [(0.0, 75.0), (75.0, 150.0), (225.0, 75.0)]);
+
+        // Content wider than the edge is shrunk in proportion too.
+        assert_eq!(distribute_edge(300.0, Some(50.0), Some(400.0), None), [(0.0, 30.0), (30.0, 240.0), (270.0, 30.0)]);
+    }
+
+    #[test]
+    fn side_boxes_share_the_edge_in_proportion_to_their_content() {
+        assert_eq!(distribute_edge(300.0, Some(100.0), None, Some(200.0)), [(0.0, 100.0), (100.0, 0.0), (100.0, 200.0)]);
+        assert_eq!(distribute_edge(300.0, Some(0.0), None, Some(0.0)), [(0.0, 150.0), (150.0, 0.0), (150.0, 150.0)]);
+        assert_eq!(distribute_edge(300.0, Some(10.0), None, None), [(0.0, 300.0), (300.0, 0.0), (300.0, 0.0)]);
+        assert_eq!(distribute_edge(300.0, None, None, Some(10.0)), [(0.0, 0.0), (0.0, 0.0), (0.0, 300.0)]);
+    }
+
+    #[test]
+    fn margin_boxes_are_placed_in_the_page_margins() {
+        let geometry = PageGeometry { width: 400.0, height: 300.0, margin_top: 50.0, margin_right: 40.0, margin_bottom: 30.0, margin_left: 60.0 };
+        let mut margin_boxes = vec![
+            margin_box(MarginBoxPosition::TopLeftCorner, 10.0, 10.0, "left"),
+            margin_box(MarginBoxPosition::TopLeft, 50.0, 10.0, "left"),
+            margin_box(MarginBoxPosition::TopCenter, 100.0, 10.0, "center"),
+            margin_box(MarginBoxPosition::BottomRight, 20.0, 10.0, "right"),
+            margin_box(MarginBoxPosition::RightMiddle, 10.0, 100.0, "left")
+        ];
+
+        place_margin_boxes(&geometry, &mut margin_boxes);
+        let placement: Vec<(f64, f64, f64, f64)> = margin_boxes.iter()
+            .map(|margin_box| (margin_box.x, margin_box.y, margin_box.width, margin_box.height))
+            .collect();
+
+        assert_eq!(placement, vec![
+            (-60.0, -50.0, 60.0, 50.0),
+            (0.0, -50.0, 75.0, 50.0),
+            (75.0, -50.0, 150.0, 50.0),
+            (0.0, 220.0, 300.0, 30.0),
+            (300.0, 0.0, 40.0, 220.0)
+        ]);
+
+        // Text is aligned in the box horizontally by `text-align`, and vertically centered by default.
+        assert_eq!((margin_boxes[2].text_x, margin_boxes[2].text_y), (100.0, -30.0));
+        assert_eq!((margin_boxes[3].text_x, margin_boxes[3].text_y), (280.0, 230.0));
+    }
+
+    #[test]
+    fn page_counters_in_margin_box_content() {
+        let running = RunningContent::default();
+        let content = |value: &str, page_index: usize| {
+            return generate_margin_box_content(Some(&parse_value("content", value)), page_index, 12, &running);
+        };
+
+        let text = |text: &str| Some(MarginBoxContent::Text(String::from(text)));
+        assert_eq!(content("'Page ' counter(page) ' of ' counter(pages)", 2), text("Page 3 of 12"));
+        assert_eq!(content("counter(page, upper-roman) '/' counter(pages, lower-alpha)", 3), text("IV/l"));
+        assert_eq!(content("counters(page, '.') counter(chapter)", 0), text("10"));
+
+        assert_eq!(content("none", 0), None);
+        assert_eq!(content("normal", 0), None);
+        assert_eq!(generate_margin_box_content(None, 0, 12, &running), None);
+    }
+
+    #[test]
+    fn running_content_in_margin_box_content() {
+        let running = RunningContent {
+            strings: vec![RunningAssignment { name: String::from("title"), page_index: 1, is_at_page_start: false, value: String::from("Intro") }],
+            elements: vec![RunningAssignment { name: String::from("header"), page_index: 0, is_at_page_start: true, value: 7 }]
+        };
+
+        let content = |value: &str, page_index: usize| {
+            return generate_margin_box_content(Some(&parse_value("content", value)), page_index, 3, &running);
+        };
+
+        assert_eq!(content("'[' string(title) ']'", 0), Some(MarginBoxContent::Text(String::from("[]"))));
+        assert_eq!(content("'[' string(title) ']'", 2), Some(MarginBoxContent::Text(String::from("[Intro]"))));
+
+        // A running element replaces the rest of the content.
+        assert_eq!(content("'a' element(header) 'b'", 1), Some(MarginBoxContent::Element(7)));
+        assert_eq!(content("element(footer)", 1), Some(MarginBoxContent::Text(String::new())));
+    }
+}
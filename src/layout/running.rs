@@ -0,0 +1,162 @@
+use crate::css::PrimitiveValue;
+
+// Named strings and running elements carry content from the document into margin boxes.
+// Both are assigned by elements in document order, a margin box then shows one of the
+// assignments made on its page, or the one in effect when the page started.
+// https://www.w3.org/TR/css-gcpm-3/
+
+/// A named string set by `string-set`, or a running element taken out of flow by `position: running()`.
+#[derive(Debug, Clone)]
+pub struct RunningAssignment<T> {
+    pub name: String,
+
+    /// Index of the page the assigning element is on.
+    pub page_index: usize,
+
+    /// Whether nothing was on the page before the assigning element.
+    pub is_at_page_start: bool,
+    pub value: T
+}
+
+/// Which assignment on a page `string()` and `element()` refer to.
+/// https://www.w3.org/TR/css-gcpm-3/#using-named-strings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunningPolicy {
+
+    /// The first assignment on the page.
+    First,
+
+    /// The value at the start of the page, which is the first assignment if it starts the page.
+    Start,
+
+    /// The value at the end of the page.
+    Last,
+
+    /// Like `First`, but nothing on the page that makes an assignment.
+    FirstExcept
+}
+
+/// All named strings and running elements of a document, in document order.
+#[derive(Debug, Clone, Default)]
+pub struct RunningContent {
+    pub strings: Vec<RunningAssignment<String>>,
+
+    /// Running elements, by the index of their HTML element.
+    pub elements: Vec<RunningAssignment<usize>>
+}
+
+impl RunningPolicy {
+
+    /// Returns the policy given as the optional second argument of `string()` or `element()`.
+    pub fn from_arguments(arguments: &[PrimitiveValue]) -> Self {
+        match arguments.get(1).map(|argument| argument.as_string().to_ascii_lowercase()).as_deref() {
+            Some("start") => RunningPolicy::Start,
+            Some("last") => RunningPolicy::Last,
+            Some("first-except") => RunningPolicy::FirstExcept,
+            _ => RunningPolicy::First
+        }
+    }
+}
+
+impl RunningContent {
+
+    /// Returns the value of named string `name` on page at `page_index`, empty if the string has not been set yet.
+    pub fn get_string(&self, name: &str, page_index: usize, policy: RunningPolicy) -> String {
+        return select_assignment(&self.strings, name, page_index, policy).cloned().unwrap_or_default();
+    }
+
+    /// Returns the index of the running element named `name` to show on page at `page_index`.
+    pub fn get_element(&self, name: &str, page_index: usize, policy: RunningPolicy) -> Option<usize> {
+        return select_assignment(&self.elements, name, page_index, policy).copied();
+    }
+}
+
+/// Returns the value of the assignment to `name` that given policy picks for page at `page_index`.
+/// When there are no assignments on the page, the value is the one in effect after the previous pages.
+fn select_assignment<'a, T>(assignments: &'a [RunningAssignment<T>], name: &str, page_index: usize, policy: RunningPolicy) -> Option<&'a T> {
+    let named = || assignments.iter().filter(move |assignment| assignment.name == name);
+
+    let entry_value = named().take_while(|assignment| assignment.page_index < page_index).last();
+    let on_page: Vec<&RunningAssignment<T>> = named().filter(|assignment| assignment.page_index == page_index).collect();
+
+    let (first, last) = match (on_page.first(), on_page.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return entry_value.map(|assignment| &assignment.value)
+    };
+
+    let assignment = match policy {
+        RunningPolicy::First => Some(first),
+        RunningPolicy::Start if first.is_at_page_start => Some(first),
+        RunningPolicy::Start => entry_value,
+        RunningPolicy::Last => Some(last),
+        RunningPolicy::FirstExcept => None
+    };
+
+    return assignment.map(|assignment| &assignment.value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assignment(name: &str, page_index: usize, is_at_page_start: bool, value: &'static str) -> RunningAssignment<&'static str> {
+        return RunningAssignment {
+            name: name.to_string(),
+            page_index: page_index,
+            is_at_page_start: is_at_page_start,
+            value: value
+        };
+    }
+
+    /// Returns what each policy selects for page at `page_index`, in the order First, Start, Last and FirstExcept.
+    fn select_all(assignments: &[RunningAssignment<&'static str>], page_index: usize) -> Vec<Option<&'static str>> {
+        return [RunningPolicy::First, RunningPolicy::Start, RunningPolicy::Last, RunningPolicy::FirstExcept].iter()
+            .map(|policy| select_assignment(assignments, "title", page_index, *policy).copied())
+            .collect();
+    }
+
+    #[test]
+    fn policies_select_assignments_on_the_page() {
+        let assignments = vec![
+            assignment("title", 0, true, "a"),
+            assignment("other", 0, false, "x"),
+            assignment("title", 0, false, "b"),
+            assignment("title", 1, false, "c"),
+            assignment("title", 1, false, "d"),
+            assignment("title", 3, true, "e")
+        ];
+
+        assert_eq!(select_all(&assignments, 0), vec![Some("a"), Some("a"), Some("b"), None]);
+
+        // Content before the first assignment on the page still shows the previous value.
+        assert_eq!(select_all(&assignments, 1), vec![Some("c"), Some("b"), Some("d"), None]);
+
+        // Pages without assignments show the value in effect after the previous pages.
+        assert_eq!(select_all(&assignments, 2), vec![Some("d"), Some("d"), Some("d"), Some("d")]);
+        assert_eq!(select_all(&assignments, 3), vec![Some("e"), Some("e"), Some("e"), None]);
+        assert_eq!(select_all(&assignments, 4), vec![Some("e"), Some("e"), Some("e"), Some("e")]);
+    }
+
+    #[test]
+    fn nothing_is_selected_before_the_first_assignment() {
+        let assignments = vec![assignment("title", 1, false, "a")];
+        assert_eq!(select_all(&assignments, 0), vec![None, None, None, None]);
+        assert_eq!(select_all(&assignments, 1), vec![Some("a"), None, Some("a"), None]);
+        assert_eq!(select_assignment(&assignments, "other", 1, RunningPolicy::First), None);
+    }
+
+    #[test]
+    fn policies_are_the_second_argument() {
+        let policy = |argument: Option<&str>| {
+            let mut arguments = vec![PrimitiveValue::from_identifier("title")];
+            arguments.extend(argument.map(PrimitiveValue::from_identifier));
+            return RunningPolicy::from_arguments(&arguments);
+        };
+
+        assert_eq!(policy(None), RunningPolicy::First);
+        assert_eq!(policy(Some("first")), RunningPolicy::First);
+        assert_eq!(policy(Some("START")), RunningPolicy::Start);
+        assert_eq!(policy(Some("last")), RunningPolicy::Last);
+        assert_eq!(policy(Some("first-except")), RunningPolicy::FirstExcept);
+    }
+}
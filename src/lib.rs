@@ -0,0 +1,27 @@
+// The codebase consistently writes explicit `return` statements and explicit field initializers.
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
+
+pub(crate) mod css;
+pub(crate) mod generator;
+pub(crate) mod html;
+pub(crate) mod layout;
+pub(crate) mod utils;
+mod diagnostics;
+mod error;
+mod converter;
+
+#[macro_use]
+extern crate lazy_static;
+
+pub use self::converter::{Converter, LayoutResult, Output};
+pub use self::diagnostics::{Diagnostic, Diagnostics, Severity, SourceLocation};
+pub use self::error::{Error, Result};
+pub use self::generator::{
+    ColorMode,
+    ConversionOptions,
+    FileResourceResolver,
+    PageMargins,
+    PageOrientation,
+    PageSize,
+    ResourceResolver
+};
@@ -1,13 +1,8 @@
-mod css;
-mod cli;
-mod generator;
-mod html;
-mod layout;
-mod utils;
+// The codebase prefers explicit `return` statements and explicit field initializers.
+#![allow(clippy::needless_return, clippy::redundant_field_names)]
 
-#[macro_use]
-extern crate lazy_static;
+mod cli;
 
 fn main() {
-    cli::run_cli();
-}
\ No newline at end of file
+    std::process::exit(cli::run_cli());
+}
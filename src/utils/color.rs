@@ -1,5 +1,4 @@
 use std::collections::HashMap;
-use std::ops::{Range, Index};
 use hex as hex_utils;
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -7,75 +6,191 @@ pub struct Color {
     pub red: u8,
     pub green: u8,
     pub blue: u8,
-    pub alpha: u8
+    pub alpha: u8,
+
+    /// The color in CMYK, if it was given as one. `red`, `green` and `blue` are then an approximation of it.
+    pub cmyk: Option<Cmyk>
+}
+
+/// A color in the device CMYK color space, each component being between 0 and 1.
+/// There is no spot color counterpart, see `ColorMode`.
+/// https://www.w3.org/TR/css-color-5/#device-cmyk
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Cmyk {
+    pub cyan: f64,
+    pub magenta: f64,
+    pub yellow: f64,
+    pub key: f64
 }
 
 impl Color {
     pub fn from_rgb(red: u8, green: u8, blue: u8) -> Color {
+        return Color::from_rgba(red, green, blue, 255);
+    }
+
+    pub fn from_rgba(red: u8, green: u8, blue: u8, alpha: u8) -> Color {
         Color {
             red: red,
             green: green,
             blue: blue,
-            alpha: 255
+            alpha: alpha,
+            cmyk: None
         }
     }
 
-    pub fn from_hex(hex: &str) -> Color {
-        let mut hex_str = String::from(hex);
+    /// Creates a color from its CMYK components, with the RGB components computed naively.
+    /// https://www.w3.org/TR/css-color-5/#cmyk-rgb
+    pub fn from_cmyk(cmyk: Cmyk, alpha: u8) -> Color {
+        let channel = |component: f64| (255.0 * (1.0 - component) * (1.0 - cmyk.key)).round() as u8;
+
+        return Color {
+            cmyk: Some(cmyk),
+            ..Color::from_rgba(channel(cmyk.cyan), channel(cmyk.magenta), channel(cmyk.yellow), alpha)
+        };
+    }
+
+    /// Creates a color from its hue in degrees, saturation and lightness between 0 and 1, and alpha between 0 and 255.
+    /// https://www.w3.org/TR/css-color-4/#hsl-to-rgb
+    pub fn from_hsl(hue: f64, saturation: f64, lightness: f64, alpha: u8) -> Color {
+        let hue = hue.rem_euclid(360.0);
+        let saturation = saturation.clamp(0.0, 1.0);
+        let lightness = lightness.clamp(0.0, 1.0);
+
+        let channel = |offset: f64| {
+            let k = (offset + hue / 30.0) % 12.0;
+            let a = saturation * lightness.min(1.0 - lightness);
+            let value = lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0);
+            return (value * 255.0).round() as u8;
+        };
 
-        // todo: support for 3 characters long color hexes (#123 -> #112233)
+        return Color::from_rgba(channel(0.0), channel(8.0), channel(4.0), alpha);
+    }
 
-        if hex_str.len() == 7 && hex_str.starts_with('#') {
-            hex_str = hex_str.index(Range { start: 1, end: 7 }).to_string();
+    /// Returns the color in CMYK: the components it was given with, or a naive conversion of its RGB components.
+    pub fn to_cmyk(self) -> Cmyk {
+        if let Some(cmyk) = self.cmyk {
+            return cmyk;
         }
 
-        if hex_str.len() == 6 {
-            // todo: this can surely be optimized
-            let rr = hex_utils::decode(hex_str[0..2].to_string()).ok().unwrap()[0];
-            let gg = hex_utils::decode(hex_str[2..4].to_string()).ok().unwrap()[0];
-            let bb = hex_utils::decode(hex_str[4..6].to_string()).ok().unwrap()[0];
-
-            return Color {
-                red: rr,
-                green: gg,
-                blue: bb,
-                alpha: 255
-            }
+        let red = self.red as f64 / 255.0;
+        let green = self.green as f64 / 255.0;
+        let blue = self.blue as f64 / 255.0;
+        let key = 1.0 - red.max(green).max(blue);
+
+        if key >= 1.0 {
+            return Cmyk { cyan: 0.0, magenta: 0.0, yellow: 0.0, key: 1.0 };
         }
 
-        Color::default()
+        return Cmyk {
+            cyan: (1.0 - red - key) / (1.0 - key),
+            magenta: (1.0 - green - key) / (1.0 - key),
+            yellow: (1.0 - blue - key) / (1.0 - key),
+            key: key
+        };
     }
 }
 
-pub fn get_predefined_colors() -> HashMap<&'static str, Color> {
-    let mut map: HashMap<&str, Color> = HashMap::new();
+/// Parses the digits of a `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa` color code (without the `#`).
+/// https://www.w3.org/TR/css-color-4/#hex-notation
+pub fn parse_hex_color(digits: &str) -> Option<Color> {
+    let expanded = match digits.len() {
+        3 | 4 => digits.chars().flat_map(|digit| vec![digit, digit]).collect::<String>(),
+        6 | 8 => digits.to_string(),
+        _ => return None
+    };
+
+    let bytes = hex_utils::decode(&expanded).ok()?;
+    let alpha = bytes.get(3).cloned().unwrap_or(255);
+    return Some(Color::from_rgba(bytes[0], bytes[1], bytes[2], alpha));
+}
+
+/// All named colors of CSS Color 4 with their `#rrggbb` codes.
+/// https://www.w3.org/TR/css-color-4/#named-colors
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("aliceblue", 0xf0f8ff), ("antiquewhite", 0xfaebd7), ("aqua", 0x00ffff), ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff), ("beige", 0xf5f5dc), ("bisque", 0xffe4c4), ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd), ("blue", 0x0000ff), ("blueviolet", 0x8a2be2), ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887), ("cadetblue", 0x5f9ea0), ("chartreuse", 0x7fff00), ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50), ("cornflowerblue", 0x6495ed), ("cornsilk", 0xfff8dc), ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff), ("darkblue", 0x00008b), ("darkcyan", 0x008b8b), ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9), ("darkgreen", 0x006400), ("darkgrey", 0xa9a9a9), ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b), ("darkolivegreen", 0x556b2f), ("darkorange", 0xff8c00), ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000), ("darksalmon", 0xe9967a), ("darkseagreen", 0x8fbc8f), ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f), ("darkslategrey", 0x2f4f4f), ("darkturquoise", 0x00ced1), ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493), ("deepskyblue", 0x00bfff), ("dimgray", 0x696969), ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff), ("firebrick", 0xb22222), ("floralwhite", 0xfffaf0), ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff), ("gainsboro", 0xdcdcdc), ("ghostwhite", 0xf8f8ff), ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520), ("gray", 0x808080), ("green", 0x008000), ("greenyellow", 0xadff2f),
+    ("grey", 0x808080), ("honeydew", 0xf0fff0), ("hotpink", 0xff69b4), ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082), ("ivory", 0xfffff0), ("khaki", 0xf0e68c), ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5), ("lawngreen", 0x7cfc00), ("lemonchiffon", 0xfffacd), ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080), ("lightcyan", 0xe0ffff), ("lightgoldenrodyellow", 0xfafad2), ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90), ("lightgrey", 0xd3d3d3), ("lightpink", 0xffb6c1), ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa), ("lightskyblue", 0x87cefa), ("lightslategray", 0x778899), ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de), ("lightyellow", 0xffffe0), ("lime", 0x00ff00), ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6), ("magenta", 0xff00ff), ("maroon", 0x800000), ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd), ("mediumorchid", 0xba55d3), ("mediumpurple", 0x9370db), ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee), ("mediumspringgreen", 0x00fa9a), ("mediumturquoise", 0x48d1cc), ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970), ("mintcream", 0xf5fffa), ("mistyrose", 0xffe4e1), ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead), ("navy", 0x000080), ("oldlace", 0xfdf5e6), ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23), ("orange", 0xffa500), ("orangered", 0xff4500), ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa), ("palegreen", 0x98fb98), ("paleturquoise", 0xafeeee), ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5), ("peachpuff", 0xffdab9), ("peru", 0xcd853f), ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd), ("powderblue", 0xb0e0e6), ("purple", 0x800080), ("rebeccapurple", 0x663399),
+    ("red", 0xff0000), ("rosybrown", 0xbc8f8f), ("royalblue", 0x4169e1), ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072), ("sandybrown", 0xf4a460), ("seagreen", 0x2e8b57), ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d), ("silver", 0xc0c0c0), ("skyblue", 0x87ceeb), ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090), ("slategrey", 0x708090), ("snow", 0xfffafa), ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4), ("tan", 0xd2b48c), ("teal", 0x008080), ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347), ("turquoise", 0x40e0d0), ("violet", 0xee82ee), ("wheat", 0xf5deb3),
+    ("white", 0xffffff), ("whitesmoke", 0xf5f5f5), ("yellow", 0xffff00), ("yellowgreen", 0x9acd32)
+];
 
-    map.insert("black", Color::from_hex("000000"));
-    map.insert("white", Color::from_hex("ffffff"));
-    map.insert("red", Color::from_hex("ff0000"));
-    map.insert("green", Color::from_hex("00ff00"));
-    map.insert("blue", Color::from_hex("0000ff"));
-    map.insert("transparent", Color { red: 0, green: 0, blue: 0, alpha: 0 });
+lazy_static! {
+    static ref PREDEFINED_COLORS: HashMap<&'static str, Color> = {
+        let mut map: HashMap<&str, Color> = NAMED_COLORS.iter()
+            .map(|&(name, code)| (name, Color::from_rgb((code >> 16) as u8, (code >> 8) as u8, code as u8)))
+            .collect();
+
+        map.insert("transparent", Color::from_rgba(0, 0, 0, 0));
+        map
+    };
+}
 
-    return map;
+/// Returns the color with given (case-insensitive) name.
+pub fn get_named_color(name: &str) -> Option<Color> {
+    return PREDEFINED_COLORS.get(name.to_ascii_lowercase().as_str()).cloned();
 }
 
-/// Attempts to transform provided string into a color.
-/// Currently only supports transforming RGB hex color codes (#ff0000) and returning predefined colors.
-/// If given string could not be transformed, black will be returned.
-pub fn code_to_color(code: &str) -> Color {
-    if code.starts_with('#') {
-        return Color::from_hex(code);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the CMYK components of a color, rounded to two decimal places.
+    fn cmyk(color: Color) -> (f64, f64, f64, f64) {
+        let cmyk = color.to_cmyk();
+        let round = |component: f64| (component * 100.0).round() / 100.0;
+        return (round(cmyk.cyan), round(cmyk.magenta), round(cmyk.yellow), round(cmyk.key));
     }
 
-    if let Some(predefined_color) = get_predefined_colors().get(code) {
-        return predefined_color.clone();
+    #[test]
+    fn rgb_colors_are_converted_to_cmyk() {
+        assert_eq!(cmyk(Color::from_rgb(255, 0, 0)), (0.0, 1.0, 1.0, 0.0));
+        assert_eq!(cmyk(Color::from_rgb(255, 255, 255)), (0.0, 0.0, 0.0, 0.0));
+        assert_eq!(cmyk(Color::from_rgb(0, 0, 0)), (0.0, 0.0, 0.0, 1.0));
+        assert_eq!(cmyk(Color::from_rgb(128, 128, 128)), (0.0, 0.0, 0.0, 0.5));
+        assert_eq!(cmyk(Color::from_rgb(51, 102, 204)), (0.75, 0.5, 0.0, 0.2));
     }
 
-    return Color {
-        red: 0,
-        green: 0,
-        blue: 0,
-        alpha: 255
-    };
-}
\ No newline at end of file
+    #[test]
+    fn cmyk_colors_keep_their_components() {
+        let given = Cmyk { cyan: 0.1, magenta: 0.2, yellow: 0.3, key: 0.4 };
+        let color = Color::from_cmyk(given, 128);
+
+        assert_eq!(color.to_cmyk(), given);
+        assert_eq!((color.red, color.green, color.blue, color.alpha), (138, 122, 107, 128));
+
+        // Converting the approximation back would give different components.
+        assert_ne!(Color::from_rgb(138, 122, 107).to_cmyk(), given);
+    }
+}
@@ -0,0 +1,71 @@
+// Counter values are formatted into text according to a counter style,
+// which is one of the `list-style-type` keywords of CSS 2.1.
+// https://www.w3.org/TR/CSS21/generate.html#counter-styles
+
+const ROMAN_NUMERALS: [(i64, &str); 13] = [
+    (1000, "m"), (900, "cm"), (500, "d"), (400, "cd"), (100, "c"), (90, "xc"),
+    (50, "l"), (40, "xl"), (10, "x"), (9, "ix"), (5, "v"), (4, "iv"), (1, "i")
+];
+
+const GREEK_LETTERS: [char; 24] = [
+    'α', 'β', 'γ', 'δ', 'ε', 'ζ', 'η', 'θ', 'ι', 'κ', 'λ', 'μ',
+    'ν', 'ξ', 'ο', 'π', 'ρ', 'σ', 'τ', 'υ', 'φ', 'χ', 'ψ', 'ω'
+];
+
+/// Returns the text representation of a counter's value in given counter style.
+/// Values that a style cannot represent, like zero in an alphabetic style, and styles
+/// that are not supported are formatted as decimal numbers.
+pub fn format_counter(value: i64, style: &str) -> String {
+    let text = match style.to_ascii_lowercase().as_str() {
+        "none" => Some(String::new()),
+        "disc" => Some(String::from("\u{2022}")),
+        "circle" => Some(String::from("\u{25e6}")),
+        "square" => Some(String::from("\u{25aa}")),
+        "decimal-leading-zero" if (0..10).contains(&value) => Some(format!("0{}", value)),
+        "lower-roman" => to_roman(value),
+        "upper-roman" => to_roman(value).map(|text| text.to_ascii_uppercase()),
+        "lower-latin" | "lower-alpha" => to_alphabetic(value, &('a'..='z').collect::<Vec<char>>()),
+        "upper-latin" | "upper-alpha" => to_alphabetic(value, &('A'..='Z').collect::<Vec<char>>()),
+        "lower-greek" => to_alphabetic(value, &GREEK_LETTERS),
+        _ => None
+    };
+
+    return text.unwrap_or_else(|| value.to_string());
+}
+
+/// Returns given value in lowercase roman numerals, which can only represent values from 1 to 3999.
+fn to_roman(value: i64) -> Option<String> {
+    if !(1..4000).contains(&value) {
+        return None;
+    }
+
+    let mut text = String::new();
+    let mut rest = value;
+
+    for (numeral_value, numeral) in ROMAN_NUMERALS.iter() {
+        while rest >= *numeral_value {
+            text.push_str(numeral);
+            rest -= numeral_value;
+        }
+    }
+
+    return Some(text);
+}
+
+/// Returns given value in an alphabetic system, like `a, b, ..., z, aa, ab, ...`, which cannot represent values below 1.
+fn to_alphabetic(value: i64, letters: &[char]) -> Option<String> {
+    if value < 1 {
+        return None;
+    }
+
+    let mut letters_reversed = Vec::<char>::new();
+    let mut rest = value;
+
+    while rest > 0 {
+        rest -= 1;
+        letters_reversed.push(letters[(rest % letters.len() as i64) as usize]);
+        rest /= letters.len() as i64;
+    }
+
+    return Some(letters_reversed.iter().rev().collect());
+}
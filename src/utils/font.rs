@@ -7,7 +7,7 @@ pub struct Font {
 }
 
 impl Font {
-    pub fn get_text_bounding_box(&self, text: &String, font_size: f64, include_max_descent: bool) -> utils::FRect {
+    pub fn get_text_bounding_box(&self, text: &str, font_size: f64, include_max_descent: bool) -> utils::FRect {
         let mut width: f64 = 0.0;
         let mut height: f64 = 0.0;
         let mut min_y: f64 = 0.0;
@@ -33,13 +33,20 @@ impl Font {
         }
     }
 
+    /// Returns the ascent, the descent and the line gap of the font at given size, all positive.
+    pub fn get_vertical_metrics(&self, font_size: f64) -> (f64, f64, f64) {
+        let metrics = self.internal.metrics();
+        let to_px = font_size / metrics.units_per_em as f64;
+
+        return (metrics.ascent.abs() as f64 * to_px, metrics.descent.abs() as f64 * to_px, metrics.line_gap.abs() as f64 * to_px);
+    }
+
     pub fn get_character_bounding_box(&self, character: char, font_size: f64) -> utils::FRect {
         let metrics = self.internal.metrics();
         let to_px = font_size / metrics.units_per_em as f64;
 
         if let Some(glyph) = self.internal.glyph_for_char(character) {
-            if let Ok(bounds) = self.internal.typographic_bounds(glyph) {
-                let advance = self.internal.advance(glyph).unwrap();
+            if let (Ok(bounds), Ok(advance)) = (self.internal.typographic_bounds(glyph), self.internal.advance(glyph)) {
                 let advance_x_px = advance.x() as f64 * to_px;
                 let min_y_px = bounds.min_y() as f64 * to_px;
 
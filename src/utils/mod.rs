@@ -1,7 +1,8 @@
 pub mod color;
+pub mod counter;
 pub mod font;
 pub mod rect;
+pub mod units;
 
 pub use self::color::Color;
-pub use self::font::Font;
-pub use self::rect::{Rect, FRect};
\ No newline at end of file
+pub use self::rect::FRect;
\ No newline at end of file
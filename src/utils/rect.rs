@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 #[derive(Debug, Copy, Clone)]
 pub struct FRect {
     pub x: f64,
@@ -0,0 +1,28 @@
+// Layout works in points, 1/72 of an inch, the same unit PDF uses.
+
+pub const POINTS_PER_INCH: f64 = 72.0;
+pub const MM_PER_INCH: f64 = 25.4;
+
+pub fn mm_to_pt(value: f64) -> f64 {
+    return value * POINTS_PER_INCH / MM_PER_INCH;
+}
+
+pub fn pt_to_mm(value: f64) -> f64 {
+    return value * MM_PER_INCH / POINTS_PER_INCH;
+}
+
+/// Returns the size of given absolute CSS length unit in points,
+/// or `None` if it is a relative unit like `em`.
+/// https://www.w3.org/TR/css-values-3/#absolute-lengths
+pub fn absolute_unit_to_pt(unit: &str) -> Option<f64> {
+    match unit {
+        "pt" => Some(1.0),
+        "px" => Some(POINTS_PER_INCH / 96.0),
+        "pc" => Some(12.0),
+        "in" => Some(POINTS_PER_INCH),
+        "cm" => Some(mm_to_pt(10.0)),
+        "mm" => Some(mm_to_pt(1.0)),
+        "q" => Some(mm_to_pt(0.25)),
+        _ => None
+    }
+}